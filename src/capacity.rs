@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::util;
+
+pub(crate) const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+pub(crate) const MAX_FILL_UNLINK: usize = 16; // per write, mostly ones freeing no space
+
+// what writers do while filesystem is below free thresholds
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) enum CapacityAction {
+    Stop,
+    Throttle, // wait until freed
+}
+
+impl CapacityAction {
+    #[allow(dead_code)]
+    pub(crate) fn is_stop(&self) -> bool {
+        matches!(self, CapacityAction::Stop)
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn is_throttle(&self) -> bool {
+        matches!(self, CapacityAction::Throttle)
+    }
+}
+
+// used space and inodes in percent, as in df(1)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Usage {
+    pub(crate) space: f64,
+    pub(crate) inodes: f64,
+}
+
+// bytes and inodes available to unprivileged users
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Space {
+    used: u64,
+    avail: u64,
+    used_inodes: u64,
+    avail_inodes: u64,
+}
+
+impl Space {
+    fn new(x: &util::Statvfs) -> Self {
+        Self {
+            used: (x.blocks - x.bfree) * x.frsize,
+            avail: x.bavail * x.frsize,
+            used_inodes: x.files - x.ffree,
+            avail_inodes: x.favail,
+        }
+    }
+
+    fn get_usage(&self) -> Usage {
+        Usage {
+            space: get_percent(self.used, self.used + self.avail),
+            inodes: get_percent(self.used_inodes, self.used_inodes + self.avail_inodes),
+        }
+    }
+
+    fn add(&mut self, bytes: u64, inodes: u64) {
+        let bytes = std::cmp::min(bytes, self.avail);
+        self.used += bytes;
+        self.avail -= bytes;
+        let inodes = std::cmp::min(inodes, self.avail_inodes);
+        self.used_inodes += inodes;
+        self.avail_inodes -= inodes;
+    }
+
+    fn sub(&mut self, bytes: u64, inodes: u64) {
+        let bytes = std::cmp::min(bytes, self.used);
+        self.used -= bytes;
+        self.avail += bytes;
+        let inodes = std::cmp::min(inodes, self.used_inodes);
+        self.used_inodes -= inodes;
+        self.avail_inodes += inodes;
+    }
+}
+
+// some filesystems have no fixed number of inodes
+fn get_percent(used: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        used as f64 * 100.0 / total as f64
+    }
+}
+
+// usage of input filesystems shared by writers, refreshed every interval,
+// and estimated from writes in between
+#[derive(Debug, Default)]
+pub(crate) struct Capacity {
+    cache: std::sync::Mutex<HashMap<PathBuf, (std::time::Instant, Space)>>,
+}
+
+impl Capacity {
+    pub(crate) fn get_usage(&self, f: &Path) -> std::io::Result<Usage> {
+        let mut m = self.cache.lock().unwrap();
+        if let Some((t, x)) = m.get(f) {
+            if t.elapsed() < CHECK_INTERVAL {
+                return Ok(x.get_usage());
+            }
+        }
+        let x = Space::new(&util::get_statvfs(f)?);
+        m.insert(f.to_path_buf(), (std::time::Instant::now(), x));
+        Ok(x.get_usage())
+    }
+
+    // account written bytes and created inodes until next refresh
+    pub(crate) fn add(&self, f: &Path, bytes: usize, inodes: usize) {
+        if let Some((_, x)) = self.cache.lock().unwrap().get_mut(f) {
+            x.add(bytes.try_into().unwrap(), inodes.try_into().unwrap());
+        }
+    }
+
+    // account unlinked bytes and inodes until next refresh
+    pub(crate) fn sub(&self, f: &Path, bytes: usize, inodes: usize) {
+        if let Some((_, x)) = self.cache.lock().unwrap().get_mut(f) {
+            x.sub(bytes.try_into().unwrap(), inodes.try_into().unwrap());
+        }
+    }
+
+    // e.g. after ENOSPC
+    pub(crate) fn invalidate(&self, f: &Path) {
+        self.cache.lock().unwrap().remove(f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_get_capacity_action_is_xxx() {
+        assert!(super::CapacityAction::Stop.is_stop());
+        assert!(super::CapacityAction::Throttle.is_throttle());
+    }
+
+    #[test]
+    fn test_space() {
+        let x = crate::util::Statvfs {
+            frsize: 4096,
+            blocks: 1000,
+            bfree: 300,
+            bavail: 200, // 100 reserved
+            files: 0,
+            ffree: 0,
+            favail: 0,
+        };
+        let mut x = super::Space::new(&x);
+        let u = x.get_usage();
+        assert!((u.space - 700.0 * 100.0 / 900.0).abs() < 1e-9, "{u:?}");
+        assert_eq!(u.inodes, 0.0);
+
+        x.add(100 * 4096, 1);
+        assert!((x.get_usage().space - 800.0 * 100.0 / 900.0).abs() < 1e-9);
+        assert_eq!(x.get_usage().inodes, 0.0);
+        x.add(1 << 30, 0);
+        assert_eq!(x.get_usage().space, 100.0);
+        x.sub(1000 * 4096, 0);
+        assert_eq!(x.get_usage().space, 0.0);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let d = std::env::temp_dir();
+        let c = super::Capacity::default();
+        let x = c.get_usage(&d).unwrap();
+        assert!(x.space >= 0.0 && x.space <= 100.0, "{x:?}");
+        assert!(x.inodes >= 0.0 && x.inodes <= 100.0, "{x:?}");
+        assert_eq!(c.get_usage(&d).unwrap(), x); // cached
+        c.add(&d, 1 << 20, 1);
+        assert!(c.get_usage(&d).unwrap().space >= x.space);
+        c.invalidate(&d);
+        assert!(c.get_usage(&d).is_ok());
+        assert!(c.get_usage(&d.join("dirload_test_capacity_noent")).is_err());
+    }
+}
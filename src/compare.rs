@@ -0,0 +1,277 @@
+use crate::report;
+
+// exit status if any threshold failed
+const EXIT_FAIL: i32 = 2;
+
+// e.g. "mib_per_sec:-5" fails if dropped more than 5%,
+// "latency_p99_us:+10" fails if rose more than 10%
+#[derive(Clone, Debug, PartialEq)]
+struct Threshold {
+    metric: String,
+    percent: f64,
+}
+
+fn parse_threshold(s: &str) -> std::io::Result<Threshold> {
+    let (metric, x) = match s.split_once(':') {
+        Some(v) => v,
+        None => return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+    };
+    if !report::Summary::default()
+        .get_metrics()
+        .iter()
+        .any(|m| m.0 == metric)
+    {
+        return Err(std::io::Error::from(std::io::ErrorKind::NotFound));
+    }
+    // sign is required to tell direction
+    if !x.starts_with('+') && !x.starts_with('-') {
+        return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
+    }
+    let percent = match x.parse::<f64>() {
+        Ok(v) if v.is_finite() => v,
+        _ => return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+    };
+    Ok(Threshold {
+        metric: metric.to_string(),
+        percent,
+    })
+}
+
+fn get_change(a: f64, b: f64) -> f64 {
+    if a == b {
+        0.0
+    } else if a == 0.0 {
+        if b > 0.0 {
+            f64::INFINITY
+        } else {
+            f64::NEG_INFINITY
+        }
+    } else {
+        (b - a) / a.abs() * 100.0
+    }
+}
+
+#[derive(Debug)]
+struct Row {
+    metric: &'static str,
+    base: f64,
+    new: f64,
+    change: f64,
+    failed: Option<bool>, // none if no threshold
+}
+
+fn compare_metrics(
+    base: &[(&'static str, f64)],
+    new: &[(&'static str, f64)],
+    thresholds: &[Threshold],
+) -> Vec<Row> {
+    let mut l = vec![];
+    for (i, (metric, a)) in base.iter().enumerate() {
+        assert_eq!(*metric, new[i].0);
+        let b = new[i].1;
+        let change = get_change(*a, b);
+        let mut failed = None;
+        for t in thresholds.iter().filter(|t| t.metric == *metric) {
+            let x = if t.percent < 0.0 {
+                change < t.percent
+            } else {
+                change > t.percent
+            };
+            failed = Some(failed.unwrap_or(false) || x);
+        }
+        l.push(Row {
+            metric,
+            base: *a,
+            new: b,
+            change,
+            failed,
+        });
+    }
+    l
+}
+
+fn print_rows(l: &[Row]) {
+    let width = l.iter().map(|x| x.metric.len()).max().unwrap_or(0);
+    println!(
+        "{:<width$} {:>16} {:>16} {:>16} {:>9} result",
+        "metric", "base", "new", "delta", "change"
+    );
+    println!("{}", "-".repeat(width + 16 * 3 + 9 + 4 + 7));
+    for x in l {
+        let result = match x.failed {
+            Some(true) => "FAIL",
+            Some(false) => "PASS",
+            None => "-",
+        };
+        println!(
+            "{:<width$} {:>16.2} {:>16.2} {:>+16.2} {:>+8.2}% {result}",
+            x.metric,
+            x.base,
+            x.new,
+            x.new - x.base,
+            x.change
+        );
+    }
+}
+
+fn usage(progname: &str, opts: &getopts::Options) {
+    print!(
+        "{}",
+        opts.usage(&format!(
+            "usage: {progname} compare [<options>] <base_result_file> <result_file>..."
+        ))
+    );
+}
+
+// compare result files against the first one, never returns
+pub(crate) fn run(progname: &str, args: &[String]) -> ! {
+    let mut opts = getopts::Options::new();
+    opts.optmulti(
+        "",
+        "threshold",
+        "Fail if change of metric exceeds signed percent, e.g. mib_per_sec:-5,latency_p99_us:+10",
+        "<metric>:<percent>",
+    );
+    opts.optflag("h", "help", "Print usage and exit");
+
+    let matches = match opts.parse(args) {
+        Ok(v) => v,
+        Err(e) => {
+            println!("{e}");
+            usage(progname, &opts);
+            std::process::exit(1);
+        }
+    };
+    if matches.opt_present("h") || matches.free.len() < 2 {
+        usage(progname, &opts);
+        println!(
+            "\nmetrics: {}",
+            report::Summary::default()
+                .get_metrics()
+                .iter()
+                .map(|x| x.0)
+                .collect::<Vec<&str>>()
+                .join(", ")
+        );
+        std::process::exit(1);
+    }
+
+    let mut thresholds = vec![];
+    for v in matches.opt_strs("threshold") {
+        for x in v.split(',') {
+            match parse_threshold(x) {
+                Ok(v) => thresholds.push(v),
+                Err(e) => {
+                    println!("Invalid threshold {x}: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+
+    let mut reportv = vec![];
+    for f in &matches.free {
+        match report::load_report_file(std::path::Path::new(f)) {
+            Ok(v) => reportv.push(v),
+            Err(e) => {
+                println!("{f}: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let base = reportv[0].get_metrics();
+    let mut num_fail = 0;
+    for (i, x) in reportv.iter().enumerate().skip(1) {
+        if i != 1 {
+            println!();
+        }
+        println!("base {}", matches.free[0]);
+        println!("new  {}", matches.free[i]);
+        let l = compare_metrics(&base, &x.get_metrics(), &thresholds);
+        print_rows(&l);
+        num_fail += l.iter().filter(|x| x.failed == Some(true)).count();
+    }
+    if num_fail > 0 {
+        println!("{num_fail} threshold(s) failed");
+        std::process::exit(EXIT_FAIL);
+    }
+    std::process::exit(0);
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_parse_threshold() {
+        let threshold_list = [
+            ("mib_per_sec:-5", "mib_per_sec", -5.0),
+            ("latency_p99_us:+10", "latency_p99_us", 10.0),
+            ("ops_per_sec:-0.5", "ops_per_sec", -0.5),
+            ("num_stat:+0", "num_stat", 0.0),
+        ];
+        for x in &threshold_list {
+            match super::parse_threshold(x.0) {
+                Ok(v) => {
+                    assert_eq!(v.metric, x.1, "{x:?}");
+                    assert_eq!(v.percent, x.2, "{x:?}");
+                }
+                Err(e) => panic!("{e} {x:?}"),
+            }
+        }
+        let invalid_list = [
+            "",
+            "mib_per_sec",
+            "mib_per_sec:",
+            "mib_per_sec:5",
+            "mib_per_sec:-x",
+            "mib_per_sec:+inf",
+            "xxx:-5",
+            ":-5",
+        ];
+        for s in &invalid_list {
+            assert!(super::parse_threshold(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_get_change() {
+        assert_eq!(super::get_change(100.0, 95.0), -5.0);
+        assert_eq!(super::get_change(100.0, 110.0), 10.0);
+        assert_eq!(super::get_change(0.0, 0.0), 0.0);
+        assert_eq!(super::get_change(0.0, 1.0), f64::INFINITY);
+        assert_eq!(super::get_change(-10.0, -5.0), 50.0);
+    }
+
+    #[test]
+    fn test_compare_metrics() {
+        let base = [
+            ("mib_per_sec", 100.0),
+            ("latency_p99_us", 10.0),
+            ("num_stat", 5.0),
+        ];
+        let new = [
+            ("mib_per_sec", 94.0),
+            ("latency_p99_us", 10.5),
+            ("num_stat", 1.0),
+        ];
+        let thresholds = [
+            super::Threshold {
+                metric: "mib_per_sec".to_string(),
+                percent: -5.0,
+            },
+            super::Threshold {
+                metric: "latency_p99_us".to_string(),
+                percent: 10.0,
+            },
+        ];
+        let l = super::compare_metrics(&base, &new, &thresholds);
+        assert_eq!(l.len(), 3);
+        assert_eq!(l[0].failed, Some(true)); // -6% < -5%
+        assert_eq!(l[1].failed, Some(false)); // +5% <= +10%
+        assert_eq!(l[2].failed, None);
+
+        let l = super::compare_metrics(&base, &base, &thresholds);
+        assert!(l.iter().all(|x| x.failed != Some(true)));
+        assert!(l.iter().all(|x| x.change == 0.0));
+    }
+}
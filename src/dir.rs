@@ -1,15 +1,39 @@
-use std::io::Read;
-use std::io::Write;
-use std::os::fd::AsRawFd;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::path::PathBuf;
 
+use crate::capacity;
+use crate::engine;
+use crate::uring;
 use crate::util;
 use crate::worker;
 use crate::Opt;
 
 pub(crate) const MAX_BUFFER_SIZE: usize = 128 * 1024;
 const WRITE_PATHS_PREFIX: &str = "dirload";
+const MAX_WRITE_PATHS_RETRY: usize = 16;
+const MAX_UNLINK_RANDOM_RETRY: usize = 16;
+const XATTR_PREFIX: &str = "user.dirload.";
+pub(crate) const DEFAULT_WRITE_PATHS_DEEP_DEPTH: isize = 16;
 
-#[derive(Clone, Copy, Debug)]
+// more creates than unlinks, so that aged tree grows
+const AGE_OP_WEIGHT: [(AgeOp, usize); 4] = [
+    (AgeOp::Create, 40),
+    (AgeOp::Grow, 25),
+    (AgeOp::Shrink, 15),
+    (AgeOp::Unlink, 20),
+];
+
+// [min, max) of file size, mostly small files with a tail of large ones
+const AGE_SIZE_MIX: [((usize, usize), usize); 4] = [
+    ((1, 4 << 10), 50),
+    ((4 << 10, 64 << 10), 30),
+    ((64 << 10, 1 << 20), 15),
+    ((1 << 20, 16 << 20), 5),
+];
+
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
 pub(crate) enum WritePathsType {
     Dir,
     Reg,
@@ -18,7 +42,6 @@ pub(crate) enum WritePathsType {
 }
 
 impl WritePathsType {
-    #[allow(dead_code)]
     pub(crate) fn is_dir(&self) -> bool {
         matches!(self, WritePathsType::Dir)
     }
@@ -37,12 +60,219 @@ impl WritePathsType {
     }
 }
 
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) enum WritePathsShape {
+    Mirror, // next to each visited entry
+    Deep,   // descend into the directory just created
+    Wide,   // all writers in one directory
+    Spread, // round-robin across all directories
+}
+
+impl WritePathsShape {
+    #[allow(dead_code)]
+    pub(crate) fn is_mirror(&self) -> bool {
+        matches!(self, WritePathsShape::Mirror)
+    }
+
+    pub(crate) fn is_deep(&self) -> bool {
+        matches!(self, WritePathsShape::Deep)
+    }
+
+    pub(crate) fn is_wide(&self) -> bool {
+        matches!(self, WritePathsShape::Wide)
+    }
+
+    pub(crate) fn is_spread(&self) -> bool {
+        matches!(self, WritePathsShape::Spread)
+    }
+}
+
+// what aging writers do per entry
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum AgeOp {
+    Create,
+    Grow,   // append to a regular write path
+    Shrink, // ftruncate(2) a regular write path
+    Unlink,
+}
+
+impl AgeOp {
+    fn is_create(&self) -> bool {
+        matches!(self, AgeOp::Create)
+    }
+
+    fn is_grow(&self) -> bool {
+        matches!(self, AgeOp::Grow)
+    }
+
+    #[allow(dead_code)]
+    fn is_shrink(&self) -> bool {
+        matches!(self, AgeOp::Shrink)
+    }
+
+    #[allow(dead_code)]
+    fn is_unlink(&self) -> bool {
+        matches!(self, AgeOp::Unlink)
+    }
+}
+
+// how file data is read and written
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) enum IoEngine {
+    Std,   // read(2) and write(2)
+    Pread, // pread(2) and pwrite(2)
+    Mmap,  // page faults on shared mapping
+    Uring, // io_uring(7) with queue depth per thread
+}
+
+impl IoEngine {
+    #[allow(dead_code)]
+    pub(crate) fn is_std(&self) -> bool {
+        matches!(self, IoEngine::Std)
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn is_pread(&self) -> bool {
+        matches!(self, IoEngine::Pread)
+    }
+
+    pub(crate) fn is_mmap(&self) -> bool {
+        matches!(self, IoEngine::Mmap)
+    }
+
+    pub(crate) fn is_uring(&self) -> bool {
+        matches!(self, IoEngine::Uring)
+    }
+}
+
+// system call to look up type of entries
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) enum StatEngine {
+    Std,   // lstat(2) and stat(2) of full path
+    Statx, // statx(2) with mask and sync flags
+}
+
+impl StatEngine {
+    #[allow(dead_code)]
+    pub(crate) fn is_std(&self) -> bool {
+        matches!(self, StatEngine::Std)
+    }
+
+    pub(crate) fn is_statx(&self) -> bool {
+        matches!(self, StatEngine::Statx)
+    }
+}
+
+// attributes requested by statx(2)
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) enum StatxMask {
+    Type,
+    Basic, // fields of struct stat
+    All,   // including birth time
+}
+
+impl StatxMask {
+    pub(crate) fn get_mask(&self) -> u32 {
+        match self {
+            StatxMask::Type => libc::STATX_TYPE,
+            StatxMask::Basic => libc::STATX_BASIC_STATS,
+            StatxMask::All => libc::STATX_ALL | libc::STATX_BTIME,
+        }
+    }
+}
+
+// attribute synchronization of statx(2) on network filesystems
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) enum StatxSync {
+    AsStat,
+    Force, // revalidate with server
+    Dont,  // use cached attributes
+}
+
+impl StatxSync {
+    pub(crate) fn get_flags(&self) -> i32 {
+        match self {
+            StatxSync::AsStat => libc::AT_STATX_SYNC_AS_STAT,
+            StatxSync::Force => libc::AT_STATX_FORCE_SYNC,
+            StatxSync::Dont => libc::AT_STATX_DONT_SYNC,
+        }
+    }
+}
+
+// order of touching pages of mapping
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) enum MmapAccess {
+    Sequential,
+    Random,
+}
+
+impl MmapAccess {
+    #[allow(dead_code)]
+    pub(crate) fn is_sequential(&self) -> bool {
+        matches!(self, MmapAccess::Sequential)
+    }
+
+    pub(crate) fn is_random(&self) -> bool {
+        matches!(self, MmapAccess::Random)
+    }
+
+    pub(crate) fn get_advice(&self) -> libc::c_int {
+        match self {
+            MmapAccess::Sequential => libc::MADV_SEQUENTIAL,
+            MmapAccess::Random => libc::MADV_RANDOM,
+        }
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) enum WritePathsName {
+    Default,
+    Hex,
+    Hash,
+    Uuid,
+    Pad,
+    Template(String),
+}
+
+impl WritePathsName {
+    pub(crate) fn new_template(s: &str) -> std::io::Result<Self> {
+        if s.is_empty() || s.contains('/') || s.contains('\0') {
+            return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
+        }
+        let mut has_seq = false;
+        let mut l = s.split('{');
+        l.next(); // before first {
+        for x in l {
+            match x.split_once('}') {
+                Some(("seq", _)) => has_seq = true,
+                Some(("gid" | "ts", _)) => (),
+                _ => return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+            }
+        }
+        // {seq} is required to make names unique within a thread
+        if !has_seq {
+            return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
+        }
+        Ok(WritePathsName::Template(s.to_string()))
+    }
+
+    pub(crate) fn is_default(&self) -> bool {
+        matches!(self, WritePathsName::Default)
+    }
+}
+
 #[derive(Debug, Default)]
 pub(crate) struct ThreadDir {
-    read_buffer: Vec<u8>,
-    write_buffer: Vec<u8>,
-    write_paths: Vec<String>,
+    pub(crate) ring: Option<uring::Ring>, // dropped before buffers
+    read_queue: Vec<(PathBuf, std::time::Instant)>,
+    pub(crate) read_buffer: Vec<u8>,
+    pub(crate) write_buffer: Vec<u8>,
+    xattr_buffer: Vec<u8>,
+    pub(crate) write_paths: Vec<PathBuf>,
     write_paths_counter: u64,
+    write_paths_deep_dir: Option<PathBuf>,
+    write_paths_spread_index: usize,
+    pub(crate) age_bytes: usize, // bytes to write until aged
 }
 
 impl ThreadDir {
@@ -59,35 +289,106 @@ impl ThreadDir {
             ..Default::default()
         }
     }
+
+    // buffer is extended to a slot of original size per queued operation,
+    // and registered to ring
+    pub(crate) fn init_ring(&mut self, depth: usize) -> std::io::Result<()> {
+        assert!(self.ring.is_none());
+        self.read_buffer = self.read_buffer.repeat(depth);
+        self.write_buffer = self.write_buffer.repeat(depth);
+        let b = if self.read_buffer.is_empty() {
+            &mut self.write_buffer
+        } else {
+            &mut self.read_buffer
+        };
+        self.ring = Some(uring::Ring::new(depth, b)?);
+        Ok(())
+    }
 }
 
-#[derive(Debug, Default)]
+// locked memory of buffers registered by uring I/O engine of all threads,
+// a buffer may span an extra page if not page aligned
+pub(crate) fn get_ring_locked_bytes(opt: &Opt) -> usize {
+    let page = util::get_page_size();
+    let f = |bufsiz: usize| match bufsiz * opt.io_depth {
+        0 => 0,
+        n => n.next_multiple_of(page) + page,
+    };
+    opt.num_reader * f(opt.read_buffer_size) + opt.num_writer * f(opt.write_buffer_size)
+}
+
+#[derive(Debug)]
 pub(crate) struct Dir {
+    pub(crate) engine: Box<dyn engine::Engine>,
+    pub(crate) capacity: capacity::Capacity,
     random_write_data: Vec<u8>,
     write_paths_ts: String,
+    write_paths_dirs: Vec<PathBuf>,
 }
 
 impl Dir {
-    pub(crate) fn new(random: bool) -> Self {
+    pub(crate) fn new(opt: &Opt) -> Self {
         let mut dir = Self {
-            ..Default::default()
+            engine: engine::new_engine(opt),
+            capacity: capacity::Capacity::default(),
+            random_write_data: vec![],
+            write_paths_ts: util::get_time_string(),
+            write_paths_dirs: vec![],
         };
-        if random {
+        if opt.random_write_data {
             for _ in 0..MAX_BUFFER_SIZE * 2 {
                 // doubled
                 dir.random_write_data.push(util::get_random(32..128));
             }
         }
-        dir.write_paths_ts = util::get_time_string();
         dir
     }
+
+    // collect target directories for wide and spread shapes
+    pub(crate) fn init_write_paths_dirs(
+        &mut self,
+        input: &[PathBuf],
+        opt: &Opt,
+    ) -> std::io::Result<()> {
+        self.write_paths_dirs.clear();
+        if opt.write_paths_shape.is_wide() {
+            self.write_paths_dirs.push(input[0].clone());
+        } else if opt.write_paths_shape.is_spread() {
+            for f in util::remove_dup_path(input) {
+                for entry in walkdir::WalkDir::new(f)
+                    .into_iter()
+                    .filter_map(std::result::Result::ok)
+                {
+                    let x = entry.path();
+                    if util::get_raw_file_type(x)?.is_dir()
+                        && !(opt.ignore_dot && util::is_dot_path(x))
+                        && !is_write_paths_name(&util::get_basename(x)?, opt)
+                    {
+                        self.write_paths_dirs.push(x.to_path_buf());
+                    }
+                }
+            }
+            println!(
+                "{} directories to spread write paths",
+                self.write_paths_dirs.len()
+            );
+            if self.write_paths_dirs.is_empty() {
+                return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
+            }
+        }
+        Ok(())
+    }
 }
 
-pub(crate) fn cleanup_write_paths(tdv: &[&ThreadDir], opt: &Opt) -> std::io::Result<usize> {
+pub(crate) fn cleanup_write_paths(
+    tdv: &[&ThreadDir],
+    dir: &Dir,
+    opt: &Opt,
+) -> std::io::Result<usize> {
     let mut l = vec![];
     for tdir in tdv {
         for f in &tdir.write_paths {
-            l.push(f.to_string());
+            l.push(f.clone());
         }
     }
 
@@ -95,13 +396,17 @@ pub(crate) fn cleanup_write_paths(tdv: &[&ThreadDir], opt: &Opt) -> std::io::Res
     if opt.keep_write_paths {
         num_remain += l.len();
     } else {
-        unlink_write_paths(&mut l, -1)?;
+        unlink_write_paths(&mut l, -1, dir.engine.as_ref())?;
         num_remain += l.len();
     }
     Ok(num_remain)
 }
 
-pub(crate) fn unlink_write_paths(l: &mut Vec<String>, count: isize) -> std::io::Result<()> {
+pub(crate) fn unlink_write_paths(
+    l: &mut Vec<PathBuf>,
+    count: isize,
+    engine: &dyn engine::Engine,
+) -> std::io::Result<()> {
     let mut n = l.len(); // unlink all by default
     if count > 0 {
         n = count.try_into().unwrap();
@@ -112,128 +417,246 @@ pub(crate) fn unlink_write_paths(l: &mut Vec<String>, count: isize) -> std::io::
     println!("Unlink {n} write paths");
     l.sort();
 
+    // keep going on failure, failed ones remain in the list
+    let mut remain = vec![];
     while n > 0 {
-        let f = &l[l.len() - 1];
-        let t = util::get_raw_file_type(f)?;
-        match t {
-            util::FileType::Dir | util::FileType::Reg | util::FileType::Symlink => {
-                // don't resolve symlink (test symlink itself, not target)
-                if util::path_exists_or_error(f).is_err() {
-                    continue;
+        let f = l.pop().unwrap();
+        n -= 1;
+        let mut retry = 0;
+        loop {
+            match unlink_write_path(&f, engine) {
+                Ok(()) => break,
+                Err(e)
+                    if e.kind() == std::io::ErrorKind::Interrupted
+                        && retry < MAX_WRITE_PATHS_RETRY =>
+                {
+                    retry += 1;
                 }
-                if t.is_dir() {
-                    std::fs::remove_dir(f)?;
-                } else {
-                    std::fs::remove_file(f)?;
+                Err(e) => {
+                    log::info!("{} {}", f.display(), e);
+                    println!("{}: {}", f.display(), e);
+                    remain.push(f);
+                    break;
                 }
-                l.truncate(l.len() - 1);
-                n -= 1;
             }
-            _ => return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
         }
     }
+    remain.reverse();
+    l.extend(remain);
     Ok(())
 }
 
-fn assert_file_path(f: &str) {
+// make room for next write path, bytes and inodes freed or none if none to
+// unlink, directories may contain other write paths
+pub(crate) fn unlink_random_write_path(
+    thr: &mut worker::Thread,
+    dir: &Dir,
+) -> std::io::Result<Option<(usize, usize)>> {
+    // pick another one if non empty directory
+    for _ in 0..MAX_UNLINK_RANDOM_RETRY {
+        let l = &mut thr.dir.write_paths;
+        if l.is_empty() {
+            break;
+        }
+        let f = l.swap_remove(util::get_random(0..l.len()));
+        let freed = get_freed_space(&f, dir.engine.as_ref());
+        match unlink_write_path(&f, dir.engine.as_ref()) {
+            Ok(()) => {
+                // don't descend into unlinked directory
+                if thr.dir.write_paths_deep_dir.as_ref() == Some(&f) {
+                    thr.dir.write_paths_deep_dir = None;
+                }
+                return Ok(Some(freed));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::DirectoryNotEmpty => l.push(f),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(None)
+}
+
+// bytes and inodes freed by unlinking, none if hardlinked or not known
+// to the engine, bytes are file size as accounted by writes
+fn get_freed_space(f: &Path, engine: &dyn engine::Engine) -> (usize, usize) {
+    match engine.lmetadata(f) {
+        Ok(m) if m.is_file() && m.nlink() == 1 => (m.len().try_into().unwrap(), 1),
+        Ok(m) if m.is_file() => (0, 0),
+        Ok(_) => (0, 1),
+        Err(_) => (0, 0),
+    }
+}
+
+fn unlink_write_path(f: &Path, engine: &dyn engine::Engine) -> std::io::Result<()> {
+    // don't resolve symlink (test symlink itself, not target)
+    let t = engine.lstat(f)?;
+    match t {
+        util::FileType::Dir | util::FileType::Reg | util::FileType::Symlink => engine.unlink(f, t),
+        _ => Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+    }
+}
+
+fn assert_file_path(f: &Path) {
     // must always handle file as abs
     assert!(util::is_abspath(f));
 
     // file must not end with "/"
-    assert!(!f.ends_with('/'));
+    assert!(!f.as_os_str().as_bytes().ends_with(b"/"));
 }
 
-pub(crate) fn read_entry(f: &str, thr: &mut worker::Thread, opt: &Opt) -> std::io::Result<()> {
+// entries are read in a batch once depth of engine entries are queued,
+// latency of each entry is since specified start until batch completes
+pub(crate) fn read_entry(
+    f: &Path,
+    t: std::time::Instant,
+    thr: &mut worker::Thread,
+    dir: &Dir,
+    opt: &Opt,
+) -> std::io::Result<()> {
     assert_file_path(f);
-    let mut t = util::get_raw_file_type(f)?;
+    thr.dir.read_queue.push((f.to_path_buf(), t));
+    if thr.dir.read_queue.len() >= dir.engine.get_depth() {
+        flush_read_entries(thr, dir, opt)?;
+    }
+    Ok(())
+}
 
-    // stats by dirwalk itself are not counted
-    thr.stat.inc_num_stat();
+pub(crate) fn flush_read_entries(
+    thr: &mut worker::Thread,
+    dir: &Dir,
+    opt: &Opt,
+) -> std::io::Result<()> {
+    let l = std::mem::take(&mut thr.dir.read_queue);
+    if l.is_empty() {
+        return Ok(());
+    }
+
+    let fl: Vec<PathBuf> = l.iter().map(|x| x.0.clone()).collect();
+    let tv = dir.engine.lstat_batch(&fl, thr)?;
+
+    let mut rl = vec![];
+    for (x, t) in fl.iter().zip(tv) {
+        // stats by dirwalk itself are not counted
+        thr.stat.inc_num_stat();
+        if let Some(y) = get_read_path(x, t, thr, dir, opt)? {
+            rl.push((y, get_read_resid(thr, opt)));
+        }
+    }
+    dir.engine.read_files(&rl, thr, opt)?;
+
+    for x in &l {
+        thr.stat.add_latency(x.1.elapsed());
+    }
+    Ok(())
+}
+
+// path of regular file to read if any
+fn get_read_path(
+    f: &Path,
+    t: util::FileType,
+    thr: &mut worker::Thread,
+    dir: &Dir,
+    opt: &Opt,
+) -> std::io::Result<Option<PathBuf>> {
+    let mut t = t;
 
     // ignore . entries if specified
     if opt.ignore_dot && !t.is_dir() && util::is_dot_path(f) {
-        return Ok(());
+        return Ok(None);
+    }
+
+    // read extended attributes of the entry itself if specified
+    if opt.xattr_read {
+        read_xattr(f, thr, dir)?;
     }
 
     // beyond this is for file read
     if opt.stat_only {
-        return Ok(());
+        return Ok(None);
     }
 
     // find target if symlink
     let mut x;
     if t.is_symlink() {
-        x = util::read_link(f)?;
-        thr.stat.add_num_read_bytes(x.len());
+        x = dir.engine.readlink(f)?;
+        thr.stat.add_num_read_bytes(x.as_os_str().len());
         if !util::is_abspath(&x) {
             x = util::join_path(&util::get_dirpath(f)?, &x);
             assert!(util::is_abspath(&x));
         }
-        t = util::get_file_type(&x)?; // update type
+        t = dir.engine.stat(&x)?; // update type
         thr.stat.inc_num_stat(); // count twice for symlink
         assert!(!t.is_symlink()); // symlink chains resolved
         if !opt.follow_symlink {
-            return Ok(());
+            return Ok(None);
         }
     } else {
-        x = f.to_string();
+        x = f.to_path_buf();
     }
 
     match t {
-        util::FileType::Reg => return read_file(&x, thr, opt),
-        util::FileType::Dir | util::FileType::Device | util::FileType::Unsupported => (),
-        util::FileType::Symlink => panic!("{x} is symlink"),
+        // mapped pages of write paths truncated by writers raise SIGBUS
+        util::FileType::Reg
+            if dir.engine.is_mmap()
+                && opt.num_writer > 0
+                && is_write_paths_name(&util::get_basename(&x)?, opt) =>
+        {
+            Ok(None)
+        }
+        util::FileType::Reg => Ok(Some(x)),
+        util::FileType::Dir | util::FileType::Device | util::FileType::Unsupported => Ok(None),
+        util::FileType::Symlink => panic!("{} is symlink", x.display()),
+    }
+}
+
+fn read_xattr(f: &Path, thr: &mut worker::Thread, dir: &Dir) -> std::io::Result<()> {
+    let l = match dir.engine.listxattr(f) {
+        Ok(v) => v,
+        // filesystem may not support xattr
+        Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => vec![],
+        Err(e) => return Err(e),
+    };
+    thr.stat.inc_num_xattr_read();
+    thr.dir.xattr_buffer.resize(util::MAX_XATTR_SIZE, 0);
+    for name in &l {
+        match dir.engine.getxattr(f, name, &mut thr.dir.xattr_buffer) {
+            Ok(v) => thr.stat.add_num_read_bytes(v),
+            // removed since listed
+            Err(e) if e.raw_os_error() == Some(libc::ENODATA) => (),
+            Err(e) => return Err(e),
+        }
+        thr.stat.inc_num_xattr_read();
     }
     Ok(())
 }
 
-fn read_file(f: &str, thr: &mut worker::Thread, opt: &Opt) -> std::io::Result<()> {
-    let mut fp = std::fs::File::open(f)?;
-    let mut b: &mut [u8] = &mut thr.dir.read_buffer;
+fn get_read_resid(thr: &worker::Thread, opt: &Opt) -> isize {
     let mut resid = opt.read_size; // negative resid means read until EOF
-
     if resid == 0 {
-        resid = isize::try_from(util::get_random(0..b.len())).unwrap() + 1;
+        let n = get_slot_size(&thr.dir.read_buffer, thr);
+        resid = isize::try_from(util::get_random(0..n)).unwrap() + 1;
         assert!(resid > 0);
-        assert!(resid <= b.len().try_into().unwrap());
+        assert!(resid <= n.try_into().unwrap());
     }
     assert!(resid == -1 || resid > 0);
+    resid
+}
 
-    loop {
-        // cut slice size if > positive residual
-        if resid > 0 && b.len() > resid.try_into().unwrap() {
-            b = &mut b[..resid.try_into().unwrap()];
-        }
-
-        let siz = fp.read(b)?;
-        thr.stat.inc_num_read();
-        thr.stat.add_num_read_bytes(siz);
-        if siz == 0 {
-            break;
-        }
-
-        // end if positive residual becomes <= 0
-        if resid > 0 {
-            resid -= isize::try_from(siz).unwrap();
-            if resid <= 0 {
-                if opt.debug {
-                    assert_eq!(resid, 0);
-                }
-                break;
-            }
-        }
+// buffer size per operation
+pub(crate) fn get_slot_size(b: &[u8], thr: &worker::Thread) -> usize {
+    match &thr.dir.ring {
+        Some(v) => b.len() / v.get_depth(),
+        None => b.len(),
     }
-    Ok(())
 }
 
 pub(crate) fn write_entry(
-    f: &str,
+    f: &Path,
     thr: &mut worker::Thread,
     dir: &Dir,
     opt: &Opt,
 ) -> std::io::Result<()> {
     assert_file_path(f);
-    let t = util::get_raw_file_type(f)?;
+    let t = dir.engine.lstat(f)?;
 
     // stats by dirwalk itself are not counted
     thr.stat.inc_num_stat();
@@ -243,17 +666,32 @@ pub(crate) fn write_entry(
         return Ok(());
     }
 
-    match t {
-        util::FileType::Dir => return write_file(f, f, thr, dir, opt),
-        util::FileType::Reg => return write_file(&util::get_dirpath(f)?, f, thr, dir, opt),
-        util::FileType::Device | util::FileType::Symlink | util::FileType::Unsupported => (),
+    let d = match t {
+        util::FileType::Dir => f.to_path_buf(),
+        util::FileType::Reg => util::get_dirpath(f)?,
+        util::FileType::Device | util::FileType::Symlink | util::FileType::Unsupported => {
+            return Ok(())
+        }
+    };
+
+    // select a directory to create a write path in unless mirror
+    if opt.write_paths_shape.is_deep() {
+        if let Some(x) = &thr.dir.write_paths_deep_dir {
+            let x = x.clone();
+            return write_file(&x, f, thr, dir, opt);
+        }
+    } else if opt.write_paths_shape.is_wide() || opt.write_paths_shape.is_spread() {
+        let l = &dir.write_paths_dirs;
+        let i = (thr.gid + thr.dir.write_paths_spread_index) % l.len();
+        thr.dir.write_paths_spread_index += 1;
+        return write_file(&l[i], f, thr, dir, opt);
     }
-    Ok(())
+    write_file(&d, f, thr, dir, opt)
 }
 
 fn write_file(
-    d: &str,
-    f: &str,
+    d: &Path,
+    f: &Path,
     thr: &mut worker::Thread,
     dir: &Dir,
     opt: &Opt,
@@ -262,121 +700,231 @@ fn write_file(
         return Ok(());
     }
 
-    // construct a write path
-    // XXX too long (easily hits ENAMETOOLONG with walk)
-    let newb = format!(
-        "{}_gid{}_{}_{}",
-        get_write_paths_base(opt),
-        thr.gid,
-        dir.write_paths_ts,
-        thr.dir.write_paths_counter
-    );
-    thr.dir.write_paths_counter += 1;
-    let newf = util::join_path(d, &newb);
+    // don't nest into more than max depth of own write paths if specified
+    if opt.write_paths_max_depth > 0
+        && get_write_paths_depth(d, opt) > opt.write_paths_max_depth.try_into().unwrap()
+    {
+        return Ok(());
+    }
 
-    // create an inode
+    // construct a write path and create an inode
     let i = util::get_random(0..opt.write_paths_type.len());
     let t = opt.write_paths_type[i];
-    create_inode(f, &newf, t)?;
+    let mut retry = 0;
+    let (newf, created) = loop {
+        let newb = get_write_paths_name(thr.gid, thr.dir.write_paths_counter, dir, opt);
+        thr.dir.write_paths_counter += 1;
+        let newf = util::join_path(d, Path::new(&newb));
+        match create_inode(f, &newf, t, dir.engine.as_ref()) {
+            Ok(v) => {
+                // keep descending until max depth, then start over
+                if opt.write_paths_shape.is_deep() && v.is_dir() {
+                    thr.dir.write_paths_deep_dir = if get_write_paths_depth(&newf, opt)
+                        <= opt.write_paths_max_depth.try_into().unwrap()
+                    {
+                        Some(newf.clone())
+                    } else {
+                        None
+                    };
+                }
+                break (newf, v);
+            }
+            // non default names are short and may collide with existing ones
+            Err(e)
+                if e.kind() == std::io::ErrorKind::AlreadyExists
+                    && !opt.write_paths_name.is_default()
+                    && retry < MAX_WRITE_PATHS_RETRY =>
+            {
+                retry += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    };
+    thr.dir.write_paths.push(newf.clone());
+
+    // user namespace attributes are not permitted on symlinks,
+    // and hardlinks share inode with input files
+    if opt.num_xattr > 0 && !created.is_symlink() && !created.is_link() {
+        write_xattr(&newf, thr, dir, opt)?;
+    }
     if opt.fsync_write_paths {
-        fsync_inode(&newf)?;
+        fsync_inode(&newf, dir.engine.as_ref())?;
     }
     if opt.dirsync_write_paths {
-        fsync_inode(d)?;
+        fsync_inode(d, dir.engine.as_ref())?;
     }
 
-    // register the write path, and return unless regular file
-    thr.dir.write_paths.push(newf.clone());
+    // return unless regular file
     if !t.is_reg() {
         thr.stat.inc_num_write();
         return Ok(());
     }
 
-    // open the write path and start writing
-    let mut fp = std::fs::OpenOptions::new().append(true).open(newf)?;
-    let mut b: &mut [u8] = &mut thr.dir.write_buffer;
-    let mut resid = opt.write_size; // negative resid means no write
+    // start writing to the write path, aging has its own size mix
+    let mut resid = if is_aging(opt) {
+        get_age_size().try_into().unwrap()
+    } else {
+        opt.write_size // negative resid means no write
+    };
     match resid {
         x if x < 0 => {
             thr.stat.inc_num_write();
             return Ok(());
         }
         0 => {
-            resid = isize::try_from(util::get_random(0..b.len())).unwrap() + 1;
+            let n = get_slot_size(&thr.dir.write_buffer, thr);
+            resid = isize::try_from(util::get_random(0..n)).unwrap() + 1;
             assert!(resid > 0);
-            assert!(resid <= b.len().try_into().unwrap());
+            assert!(resid <= n.try_into().unwrap());
         }
         _ => (),
     }
     assert!(resid > 0);
 
-    if opt.truncate_write_paths {
-        fp.set_len(resid.try_into().unwrap())?;
-        thr.stat.inc_num_write();
-    } else {
-        loop {
-            // cut slice size if > residual
-            if resid > 0 && b.len() > resid.try_into().unwrap() {
-                b = &mut b[..resid.try_into().unwrap()];
-            }
-            if opt.random_write_data {
-                let i = util::get_random(0..dir.random_write_data.len() / 2);
-                b.clone_from_slice(&dir.random_write_data[i..i + b.len()]);
-            }
-
-            let siz = fp.write(b)?;
-            thr.stat.inc_num_write();
-            thr.stat.add_num_write_bytes(siz);
+    dir.engine
+        .write_file(&newf, 0, resid, thr, &dir.random_write_data, opt)
+}
 
-            // end if residual becomes <= 0
-            resid -= isize::try_from(siz).unwrap();
-            if resid <= 0 {
-                if opt.debug {
-                    assert_eq!(resid, 0);
-                }
-                break;
-            }
+fn write_xattr(f: &Path, thr: &mut worker::Thread, dir: &Dir, opt: &Opt) -> std::io::Result<()> {
+    thr.dir.xattr_buffer.resize(opt.xattr_size, 0x41);
+    for i in 0..opt.num_xattr {
+        let b = &mut thr.dir.xattr_buffer[..opt.xattr_size];
+        if opt.random_write_data {
+            let j = util::get_random(0..dir.random_write_data.len() / 2);
+            b.clone_from_slice(&dir.random_write_data[j..j + b.len()]);
         }
-    }
-
-    if opt.fsync_write_paths && nix::unistd::fsync(fp.as_raw_fd()).is_err() {
-        return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
+        let name = format!("{XATTR_PREFIX}{i}");
+        dir.engine.setxattr(f, std::ffi::OsStr::new(&name), b)?;
+        thr.stat.inc_num_xattr_write();
+        thr.stat.add_num_write_bytes(b.len());
     }
     Ok(())
 }
 
-fn create_inode(oldf: &str, newf: &str, t: WritePathsType) -> std::io::Result<()> {
-    let mut t = t;
-    if t.is_link() {
-        if util::get_raw_file_type(oldf)?.is_reg() {
-            return std::fs::hard_link(oldf, newf);
+pub(crate) fn is_aging(opt: &Opt) -> bool {
+    opt.age_bytes > 0 || opt.age_factor > 0.0
+}
+
+fn get_age_op() -> AgeOp {
+    get_weighted(&AGE_OP_WEIGHT)
+}
+
+fn get_age_size() -> usize {
+    let (min, max) = get_weighted(&AGE_SIZE_MIX);
+    util::get_random(min..max)
+}
+
+fn get_weighted<T: Copy>(l: &[(T, usize)]) -> T {
+    let mut n = util::get_random(0..l.iter().map(|x| x.1).sum::<usize>());
+    for (x, w) in l {
+        if n < *w {
+            return *x;
         }
-        t = WritePathsType::Dir; // create a directory instead
+        n -= w;
     }
-    match t {
-        WritePathsType::Dir => {
-            std::fs::create_dir(newf)?;
-        }
-        WritePathsType::Reg => {
-            std::fs::File::create(newf)?;
+    unreachable!();
+}
+
+// one of aging operations on own write paths, creates are the same as
+// write_entry, writers keep going until bytes written reach the target
+pub(crate) fn age_entry(
+    f: &Path,
+    thr: &mut worker::Thread,
+    dir: &Dir,
+    opt: &Opt,
+) -> std::io::Result<()> {
+    if is_write_done(thr, opt) {
+        return Ok(());
+    }
+    let op = if thr.dir.write_paths.is_empty() {
+        AgeOp::Create
+    } else {
+        get_age_op()
+    };
+    let res = match op {
+        AgeOp::Create => write_entry(f, thr, dir, opt),
+        AgeOp::Grow | AgeOp::Shrink => {
+            let i = util::get_random(0..thr.dir.write_paths.len());
+            let newf = thr.dir.write_paths[i].clone();
+            resize_file(&newf, op.is_grow(), thr, dir, opt)
         }
-        WritePathsType::Symlink => {
-            std::os::unix::fs::symlink(oldf, newf)?;
+        AgeOp::Unlink => unlink_random_write_path(thr, dir).map(|_| ()),
+    };
+    match res {
+        // write paths may have been unlinked after listed or walked,
+        // other entries are expected to exist
+        Err(e)
+            if e.kind() == std::io::ErrorKind::NotFound
+                && (!op.is_create() || get_write_paths_depth(f, opt) > 0) =>
+        {
+            Ok(())
         }
-        WritePathsType::Link => (),
+        x => x,
     }
-    Ok(())
 }
 
-fn fsync_inode(f: &str) -> std::io::Result<()> {
-    if nix::unistd::fsync(std::fs::File::open(f)?.as_raw_fd()).is_err() {
-        return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
+// appends to or truncates regular file, hardlinked write paths are skipped
+// as they share inode with input files
+fn resize_file(
+    f: &Path,
+    grow: bool,
+    thr: &mut worker::Thread,
+    dir: &Dir,
+    opt: &Opt,
+) -> std::io::Result<()> {
+    let t = dir.engine.lstat(f)?;
+    thr.stat.inc_num_stat();
+    if !t.is_reg() {
+        return Ok(());
+    }
+    let fp = dir.engine.open(f, libc::O_WRONLY)?;
+    let m = fp.metadata()?;
+    if m.nlink() > 1 {
+        return Ok(());
+    }
+    if grow {
+        drop(fp);
+        let resid = get_age_size().try_into().unwrap();
+        return dir
+            .engine
+            .write_file(f, m.len(), resid, thr, &dir.random_write_data, opt);
+    }
+    if m.len() > 0 {
+        dir.engine.truncate(&fp, util::get_random(0..m.len()))?;
+        thr.stat.inc_num_write();
+    }
+    if opt.fsync_write_paths {
+        dir.engine.fsync(&fp)?;
     }
     Ok(())
 }
 
+// returns the file type actually created
+fn create_inode(
+    oldf: &Path,
+    newf: &Path,
+    t: WritePathsType,
+    engine: &dyn engine::Engine,
+) -> std::io::Result<WritePathsType> {
+    let mut t = t;
+    if t.is_link() && !engine.lstat(oldf)?.is_reg() {
+        t = WritePathsType::Dir; // create a directory instead
+    }
+    engine.create(oldf, newf, t)?;
+    Ok(t)
+}
+
+fn fsync_inode(f: &Path, engine: &dyn engine::Engine) -> std::io::Result<()> {
+    let fp = engine.open(f, libc::O_RDONLY)?;
+    engine.fsync(&fp)
+}
+
 pub(crate) fn is_write_done(thr: &worker::Thread, opt: &Opt) -> bool {
-    if !thr.is_writer(opt) || opt.num_write_paths <= 0 {
+    if !thr.is_writer(opt) {
+        false
+    } else if thr.dir.age_bytes > 0 && thr.stat.get_num_write_bytes() >= thr.dir.age_bytes {
+        true // aged
+    } else if opt.num_write_paths <= 0 {
         false
     } else {
         thr.dir.write_paths.len() >= opt.num_write_paths.try_into().unwrap()
@@ -387,24 +935,79 @@ fn get_write_paths_base(opt: &Opt) -> String {
     format!("{}_{}", WRITE_PATHS_PREFIX, opt.write_paths_base)
 }
 
-pub(crate) fn collect_write_paths(input: &[String], opt: &Opt) -> std::io::Result<Vec<String>> {
+// all names start with the base so that collect_write_paths can find them
+fn get_write_paths_name(gid: usize, seq: u64, dir: &Dir, opt: &Opt) -> String {
     let b = get_write_paths_base(opt);
+    match &opt.write_paths_name {
+        // XXX too long (easily hits ENAMETOOLONG with walk)
+        WritePathsName::Default => format!("{b}_gid{gid}_{}_{seq}", dir.write_paths_ts),
+        WritePathsName::Hex => format!("{b}_{gid:x}_{seq:x}"),
+        WritePathsName::Hash => {
+            let mut h = std::hash::DefaultHasher::new();
+            std::hash::Hash::hash(&(&dir.write_paths_ts, gid, seq), &mut h);
+            format!("{b}_{:08x}", std::hash::Hasher::finish(&h) & 0xffff_ffff)
+        }
+        WritePathsName::Uuid => {
+            let x = util::get_random(0..=u128::MAX);
+            let x = (x & !(0xf << 76) | (0x4 << 76)) & !(0x3 << 62) | (0x2 << 62); // v4
+            format!(
+                "{b}_{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+                x >> 96,
+                (x >> 80) & 0xffff,
+                (x >> 64) & 0xffff,
+                (x >> 48) & 0xffff,
+                x & 0xffff_ffff_ffff
+            )
+        }
+        WritePathsName::Pad => format!("{b}_{gid:04x}{seq:012x}"),
+        WritePathsName::Template(s) => format!(
+            "{b}_{}",
+            s.replace("{gid}", &gid.to_string())
+                .replace("{seq}", &seq.to_string())
+                .replace("{ts}", &dir.write_paths_ts)
+        ),
+    }
+}
+
+fn is_write_paths_name(name: &std::ffi::OsStr, opt: &Opt) -> bool {
+    name.as_bytes()
+        .starts_with(get_write_paths_base(opt).as_bytes())
+}
+
+// number of own write path directories d is nested in, including d itself
+fn get_write_paths_depth(d: &Path, opt: &Opt) -> usize {
+    d.components()
+        .filter(|x| is_write_paths_name(x.as_os_str(), opt))
+        .count()
+}
+
+pub(crate) fn collect_write_paths(
+    input: &[PathBuf],
+    engine: &dyn engine::Engine,
+    opt: &Opt,
+) -> std::io::Result<Vec<PathBuf>> {
     let mut l = vec![];
-    for f in util::remove_dup_string(input) {
-        for entry in walkdir::WalkDir::new(f)
-            .into_iter()
-            .filter_map(std::result::Result::ok)
-        {
-            let x = util::parse_walkdir_entry(&entry)?;
-            let t = util::get_raw_file_type(x)?;
-            match t {
-                util::FileType::Dir | util::FileType::Reg | util::FileType::Symlink => {
+    for f in util::remove_dup_path(input) {
+        // unreadable directories are skipped as in dirwalk
+        let mut dl = vec![f.to_path_buf()];
+        while let Some(d) = dl.pop() {
+            let Ok(v) = engine.readdir(&d) else {
+                continue;
+            };
+            for x in v {
+                let t = engine.lstat(&x)?;
+                if t.is_dir() {
+                    dl.push(x.clone());
+                }
+                match t {
                     // don't resolve symlink (test symlink itself, not target)
-                    if util::get_basename(x)?.starts_with(&b) {
-                        l.push(x.to_string());
+                    util::FileType::Dir | util::FileType::Reg | util::FileType::Symlink
+                        if is_write_paths_name(&util::get_basename(&x)?, opt) =>
+                    {
+                        l.push(x);
                     }
+                    _ => (),
                 }
-                _ => (),
             }
         }
     }
@@ -413,6 +1016,7 @@ pub(crate) fn collect_write_paths(input: &[String], opt: &Opt) -> std::io::Resul
 
 #[cfg(test)]
 mod tests {
+
     #[test]
     fn test_get_write_paths_type_is_xxx() {
         assert!(super::WritePathsType::Dir.is_dir());
@@ -420,4 +1024,385 @@ mod tests {
         assert!(super::WritePathsType::Symlink.is_symlink());
         assert!(super::WritePathsType::Link.is_link());
     }
+
+    #[test]
+    fn test_get_write_paths_shape_is_xxx() {
+        assert!(super::WritePathsShape::Mirror.is_mirror());
+        assert!(super::WritePathsShape::Deep.is_deep());
+        assert!(super::WritePathsShape::Wide.is_wide());
+        assert!(super::WritePathsShape::Spread.is_spread());
+    }
+
+    #[test]
+    fn test_get_io_engine_is_xxx() {
+        assert!(super::IoEngine::Std.is_std());
+        assert!(super::IoEngine::Mmap.is_mmap());
+        assert!(super::IoEngine::Uring.is_uring());
+        assert!(super::MmapAccess::Sequential.is_sequential());
+        assert!(super::MmapAccess::Random.is_random());
+    }
+
+    #[test]
+    fn test_get_age_op_is_xxx() {
+        assert!(super::AgeOp::Create.is_create());
+        assert!(super::AgeOp::Grow.is_grow());
+        assert!(super::AgeOp::Shrink.is_shrink());
+        assert!(super::AgeOp::Unlink.is_unlink());
+    }
+
+    #[test]
+    fn test_get_weighted() {
+        for _ in 0..100 {
+            assert_eq!(super::get_weighted(&[('a', 0), ('b', 1), ('c', 0)]), 'b');
+        }
+        for _ in 0..1000 {
+            let n = super::get_age_size();
+            assert!((1..16 << 20).contains(&n), "{n}");
+        }
+    }
+
+    #[test]
+    fn test_write_xattr_link() {
+        let d =
+            std::env::temp_dir().join(format!("dirload_test_xattr_link_{}", std::process::id()));
+        let f = d.join("a");
+        std::fs::create_dir(&d).unwrap();
+        std::fs::write(&f, b"").unwrap();
+        // user namespace may be unsupported, e.g. tmpfs of older kernels
+        if let Err(e) = crate::util::set_xattr(&f, std::ffi::OsStr::new("user.other"), b"x") {
+            println!("{e}");
+            std::fs::remove_file(&f).unwrap();
+            std::fs::remove_dir(&d).unwrap();
+            return;
+        }
+        let l = crate::util::list_xattr(&f).unwrap();
+
+        let opt = crate::Opt {
+            num_writer: 1,
+            num_write_paths: -1,
+            num_xattr: 2,
+            ..Default::default()
+        };
+        let dir = super::Dir::new(&opt);
+        let mut thr = crate::worker::Thread::default();
+        thr.dir = super::ThreadDir::newwrite(opt.write_buffer_size);
+        thr.stat = crate::stat::ThreadStat::newwrite();
+        for (t, n) in [
+            (super::WritePathsType::Link, 0),
+            (super::WritePathsType::Reg, 2),
+        ] {
+            let opt = crate::Opt {
+                write_paths_type: vec![t],
+                ..opt.clone()
+            };
+            super::write_entry(&f, &mut thr, &dir, &opt).unwrap();
+            let newf = thr.dir.write_paths.last().unwrap();
+            assert_eq!(crate::util::list_xattr(&f).unwrap(), l, "{t:?}");
+            assert_eq!(thr.stat.get_num_xattr_write(), n, "{t:?}");
+            if t.is_reg() {
+                assert_eq!(crate::util::list_xattr(newf).unwrap().len(), n);
+            }
+        }
+        super::unlink_write_paths(&mut thr.dir.write_paths, -1, dir.engine.as_ref()).unwrap();
+        std::fs::remove_file(&f).unwrap();
+        std::fs::remove_dir(&d).unwrap();
+    }
+
+    #[test]
+    fn test_cleanup_write_paths_fault() {
+        let d = std::env::temp_dir().join(format!("dirload_test_cleanup_{}", std::process::id()));
+        let mut tdir = super::ThreadDir::default();
+        std::fs::create_dir(&d).unwrap();
+        for i in 0..8 {
+            let f = d.join(i.to_string());
+            std::fs::write(&f, b"").unwrap();
+            tdir.write_paths.push(f);
+        }
+        tdir.write_paths.push(d.clone()); // parent unlinked last
+
+        // every unlink fails, all remain
+        let mut opt = crate::Opt {
+            fault: vec![crate::fault::parse_fault("unlink:eio:1").unwrap()],
+            ..Default::default()
+        };
+        let dir = super::Dir::new(&opt);
+        assert_eq!(super::cleanup_write_paths(&[&tdir], &dir, &opt).unwrap(), 9);
+        assert_eq!(dir.engine.get_faults().unwrap()["unlink eio"], 9);
+
+        // interrupted unlinks are retried, half of lookups fail
+        opt.fault = vec![
+            crate::fault::parse_fault("unlink:eintr:0.5").unwrap(),
+            crate::fault::parse_fault("lstat:enospc:0.5").unwrap(),
+        ];
+        opt.fault_seed = 1;
+        let dir = super::Dir::new(&opt);
+        let mut l = tdir.write_paths.clone();
+        super::unlink_write_paths(&mut l, -1, dir.engine.as_ref()).unwrap();
+        let m = dir.engine.get_faults().unwrap();
+        assert!(m["unlink eintr"] > 0, "{m:?}");
+        let n = m["lstat enospc"];
+        assert!(n > 0 && n < 9, "{m:?}");
+        // failed ones remain, and so does non empty parent
+        assert!(l.len() >= n, "{l:?}");
+        assert!(l.contains(&d));
+        for f in &tdir.write_paths {
+            assert_eq!(l.contains(f), std::fs::symlink_metadata(f).is_ok(), "{f:?}");
+        }
+
+        // no fault
+        opt.fault.clear();
+        let dir = super::Dir::new(&opt);
+        super::unlink_write_paths(&mut l, -1, dir.engine.as_ref()).unwrap();
+        assert!(l.is_empty());
+        assert!(std::fs::symlink_metadata(&d).is_err());
+    }
+
+    #[test]
+    fn test_write_paths_name_new_template() {
+        let valid_list = ["{seq}", "x{seq}", "{gid}_{seq}", "{ts}{gid}{seq}", "{seq}}"];
+        for s in &valid_list {
+            match super::WritePathsName::new_template(s) {
+                Ok(v) => assert!(!v.is_default(), "{v:?}"),
+                Err(e) => panic!("{e} {s}"),
+            }
+        }
+        let invalid_list = [
+            "",
+            "x",
+            "{gid}",
+            "{seq",
+            "{seq}{",
+            "{xxx}{seq}",
+            "{seq}/x",
+            "{{seq}}",
+        ];
+        for s in &invalid_list {
+            assert!(super::WritePathsName::new_template(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_get_write_paths_name() {
+        let dir = super::Dir::new(&crate::Opt::default());
+        let name_list = [
+            super::WritePathsName::Default,
+            super::WritePathsName::Hex,
+            super::WritePathsName::Hash,
+            super::WritePathsName::Uuid,
+            super::WritePathsName::Pad,
+            super::WritePathsName::new_template("{gid}.{seq}.{ts}").unwrap(),
+        ];
+        for name in &name_list {
+            let opt = crate::Opt {
+                write_paths_name: name.clone(),
+                ..Default::default()
+            };
+            let mut l = vec![];
+            for gid in 0..4 {
+                for seq in 0..256 {
+                    let s = super::get_write_paths_name(gid, seq, &dir, &opt);
+                    assert!(!s.contains('/'), "{s}");
+                    assert!(
+                        super::is_write_paths_name(std::ffi::OsStr::new(&s), &opt),
+                        "{s}"
+                    );
+                    l.push(s);
+                }
+            }
+            if !matches!(
+                name,
+                super::WritePathsName::Hash | super::WritePathsName::Uuid
+            ) {
+                let n = l.len();
+                l.sort();
+                l.dedup();
+                assert_eq!(l.len(), n, "{name:?}");
+            }
+        }
+
+        let opt = crate::Opt {
+            write_paths_name: super::WritePathsName::Pad,
+            ..Default::default()
+        };
+        let n = super::get_write_paths_name(0, 0, &dir, &opt).len();
+        assert_eq!(
+            super::get_write_paths_name(0xfff, 0xfffff, &dir, &opt).len(),
+            n
+        );
+    }
+
+    #[test]
+    fn test_get_write_paths_depth() {
+        let opt = crate::Opt::default();
+        let path_list = [
+            ("/path/to/dir", 0),
+            ("/path/to/dirload_x", 1),
+            ("/path/to/dirload_x_0/dir", 1),
+            ("/path/dirload_x_0/to/dirload_x_1", 2),
+            ("/path/to/dirload_y_0/dirload_x_1", 1),
+        ];
+        for x in &path_list {
+            let d = std::path::Path::new(x.0);
+            assert_eq!(super::get_write_paths_depth(d, &opt), x.1, "{x:?}");
+        }
+    }
+
+    #[test]
+    fn test_write_file_max_depth() {
+        let d = std::env::temp_dir().join(format!("dirload_test_max_depth_{}", std::process::id()));
+        let d1 = d.join("dirload_x_0");
+        let d2 = d1.join("dirload_x_1");
+        std::fs::create_dir_all(&d2).unwrap();
+        let opt = crate::Opt {
+            num_writer: 1,
+            write_paths_type: vec![super::WritePathsType::Dir],
+            write_paths_max_depth: 1,
+            ..Default::default()
+        };
+        let dir = super::Dir::new(&opt);
+        let mut thr = crate::worker::Thread::default();
+        thr.stat = crate::stat::ThreadStat::newwrite();
+
+        // nested in 1 write path directory at max depth, but not 2
+        super::write_file(&d1, &d1, &mut thr, &dir, &opt).unwrap();
+        assert_eq!(thr.dir.write_paths.len(), 1);
+        assert_eq!(
+            super::get_write_paths_depth(&thr.dir.write_paths[0], &opt),
+            2
+        );
+        super::write_file(&d2, &d2, &mut thr, &dir, &opt).unwrap();
+        assert_eq!(thr.dir.write_paths.len(), 1);
+        std::fs::remove_dir_all(&d).unwrap();
+    }
+
+    #[test]
+    fn test_get_read_path_mmap() {
+        let d = std::env::temp_dir().join(format!("dirload_test_read_mmap_{}", std::process::id()));
+        let f1 = d.join("a");
+        let f2 = d.join("dirload_x");
+        std::fs::create_dir(&d).unwrap();
+        std::fs::write(&f1, b"xxx").unwrap();
+        std::fs::write(&f2, b"xxx").unwrap();
+
+        let mut opt = crate::Opt {
+            num_reader: 1,
+            num_writer: 1,
+            ..Default::default()
+        };
+        let mut thr = crate::worker::Thread::default();
+        for (x, skip) in [(super::IoEngine::Std, false), (super::IoEngine::Mmap, true)] {
+            opt.io_engine = x;
+            let dir = super::Dir::new(&opt);
+            let t = crate::util::FileType::Reg;
+            let r1 = super::get_read_path(&f1, t, &mut thr, &dir, &opt).unwrap();
+            let r2 = super::get_read_path(&f2, t, &mut thr, &dir, &opt).unwrap();
+            assert_eq!(r1, Some(f1.clone()), "{x:?}");
+            assert_eq!(r2.is_none(), skip, "{x:?}");
+        }
+
+        std::fs::remove_dir_all(&d).unwrap();
+    }
+
+    #[test]
+    fn test_get_freed_space() {
+        let d = std::env::temp_dir().join(format!("dirload_test_freed_{}", std::process::id()));
+        let f = d.join("a");
+        std::fs::create_dir(&d).unwrap();
+        std::fs::write(&f, b"xxx").unwrap();
+
+        let mut opt = crate::Opt::default();
+        let e = crate::engine::new_engine(&opt);
+        assert_eq!(super::get_freed_space(&f, e.as_ref()), (3, 1));
+        assert_eq!(super::get_freed_space(&d, e.as_ref()), (0, 1));
+        assert_eq!(super::get_freed_space(&d.join("b"), e.as_ref()), (0, 0));
+        std::fs::hard_link(&f, d.join("b")).unwrap();
+        assert_eq!(super::get_freed_space(&f, e.as_ref()), (0, 0));
+
+        // nothing is freed in dry run
+        opt.dry_run = true;
+        let e = crate::engine::new_engine(&opt);
+        assert_eq!(super::get_freed_space(&d, e.as_ref()), (0, 0));
+
+        std::fs::remove_dir_all(&d).unwrap();
+    }
+
+    #[test]
+    fn test_get_ring_locked_bytes() {
+        let page = crate::util::get_page_size();
+        let mut opt = crate::Opt {
+            num_reader: 2,
+            num_writer: 1,
+            read_buffer_size: 1 << 16,
+            write_buffer_size: 0,
+            io_depth: 4,
+            ..Default::default()
+        };
+        assert_eq!(super::get_ring_locked_bytes(&opt), 2 * ((1 << 18) + page));
+        opt.write_buffer_size = 1;
+        assert_eq!(
+            super::get_ring_locked_bytes(&opt),
+            2 * ((1 << 18) + page) + 2 * page
+        );
+        opt.num_reader = 0;
+        opt.num_writer = 0;
+        assert_eq!(super::get_ring_locked_bytes(&opt), 0);
+    }
+
+    #[test]
+    fn test_age_entry() {
+        let d = std::env::temp_dir().join(format!("dirload_test_age_{}", std::process::id()));
+        let f = d.join("a");
+        std::fs::create_dir(&d).unwrap();
+        std::fs::write(&f, b"xxx").unwrap();
+
+        let opt = crate::Opt {
+            num_writer: 1,
+            num_write_paths: -1,
+            write_paths_type: vec![super::WritePathsType::Reg, super::WritePathsType::Link],
+            age_bytes: 1 << 20,
+            ..Default::default()
+        };
+        let dir = super::Dir::new(&opt);
+        let mut thr = crate::worker::Thread::default();
+        thr.dir = super::ThreadDir::newwrite(opt.write_buffer_size);
+        thr.stat = crate::stat::ThreadStat::newwrite();
+        thr.dir.age_bytes = opt.age_bytes;
+        for _ in 0..10000 {
+            if super::is_write_done(&thr, &opt) {
+                break;
+            }
+            super::age_entry(&f, &mut thr, &dir, &opt).unwrap();
+        }
+        assert!(super::is_write_done(&thr, &opt));
+        assert!(thr.stat.get_num_write_bytes() >= opt.age_bytes);
+
+        // write paths left behind, and hardlinked input file is not resized
+        assert_eq!(
+            std::fs::read_dir(&d).unwrap().count(),
+            thr.dir.write_paths.len() + 1
+        );
+        assert_eq!(std::fs::read(&f).unwrap(), b"xxx");
+        super::age_entry(&f, &mut thr, &dir, &opt).unwrap(); // aged
+        assert_eq!(
+            std::fs::read_dir(&d).unwrap().count(),
+            thr.dir.write_paths.len() + 1
+        );
+
+        super::unlink_write_paths(&mut thr.dir.write_paths, -1, dir.engine.as_ref()).unwrap();
+
+        // missing input entry is not ignored
+        let mut thr = crate::worker::Thread::default();
+        thr.dir = super::ThreadDir::newwrite(opt.write_buffer_size);
+        thr.stat = crate::stat::ThreadStat::newwrite();
+        thr.dir.age_bytes = opt.age_bytes;
+        assert_eq!(
+            super::age_entry(&d.join("b"), &mut thr, &dir, &opt)
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::NotFound
+        );
+
+        std::fs::remove_file(&f).unwrap();
+        std::fs::remove_dir(&d).unwrap();
+    }
 }
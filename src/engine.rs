@@ -0,0 +1,1428 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::io::Read;
+use std::io::Seek;
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::os::fd::FromRawFd;
+use std::os::fd::IntoRawFd;
+use std::os::fd::RawFd;
+use std::os::unix::fs::FileExt;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::dir;
+use crate::fault;
+use crate::uring;
+use crate::util;
+use crate::worker;
+use crate::Opt;
+
+const MAX_PLAN_SAMPLE: usize = 8;
+const MAX_DIRFD: usize = 64; // per thread, well below RLIMIT_NOFILE
+
+thread_local! {
+    static DIRFD: RefCell<DirfdCache> = RefCell::new(DirfdCache::default());
+}
+
+// open parent directories of entries looked up relative to them,
+// least recently used one is closed when full
+#[derive(Debug, Default)]
+struct DirfdCache {
+    m: HashMap<PathBuf, (std::fs::File, u64)>,
+    seq: u64,
+}
+
+impl DirfdCache {
+    fn get(&mut self, d: &Path) -> std::io::Result<RawFd> {
+        self.seq += 1;
+        if let Some(x) = self.m.get_mut(d) {
+            x.1 = self.seq;
+            return Ok(x.0.as_raw_fd());
+        }
+        if self.m.len() >= MAX_DIRFD {
+            if let Some(k) = self.m.iter().min_by_key(|x| x.1 .1).map(|x| x.0.clone()) {
+                self.m.remove(&k);
+            }
+        }
+        let fp = std::fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_PATH | libc::O_DIRECTORY)
+            .open(d)?;
+        let fd = fp.as_raw_fd();
+        self.m.insert(d.to_path_buf(), (fp, self.seq));
+        Ok(fd)
+    }
+
+    fn remove(&mut self, d: &Path) {
+        self.m.remove(d);
+    }
+
+    // true if cached directory has been removed since opened
+    fn remove_stale(&mut self, d: &Path) -> bool {
+        let Some(x) = self.m.get(d) else {
+            return false;
+        };
+        if x.0.metadata().is_ok_and(|m| m.nlink() > 0) {
+            return false;
+        }
+        self.m.remove(d);
+        true
+    }
+}
+
+// run op with fd of open parent directory and base name of entry,
+// "/" has neither, so it is looked up by full path
+fn with_dirfd<T>(f: &Path, op: impl Fn(RawFd, &Path) -> std::io::Result<T>) -> std::io::Result<T> {
+    let (Some(d), Some(b)) = (f.parent(), f.file_name()) else {
+        return op(libc::AT_FDCWD, f);
+    };
+    let b = Path::new(b);
+    match DIRFD.with_borrow_mut(|x| op(x.get(d)?, b)) {
+        // retry only if cached directory is gone, e.g. replaced by a new one
+        Err(e)
+            if e.kind() == std::io::ErrorKind::NotFound
+                && DIRFD.with_borrow_mut(|x| x.remove_stale(d)) =>
+        {
+            DIRFD.with_borrow_mut(|x| op(x.get(d)?, b))
+        }
+        x => x,
+    }
+}
+
+// open(2) flags without access mode are passed as is
+fn get_open_options(flags: i32) -> std::fs::OpenOptions {
+    let mut o = std::fs::OpenOptions::new();
+    match flags & libc::O_ACCMODE {
+        libc::O_RDONLY => o.read(true),
+        libc::O_WRONLY => o.write(true),
+        _ => o.read(true).write(true),
+    };
+    o.custom_flags(flags & !libc::O_ACCMODE);
+    o
+}
+
+// shared mapping of file from offset 0, unmapped on drop
+#[derive(Debug)]
+pub(crate) struct Mmap {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+impl Mmap {
+    fn new(fp: &std::fs::File, len: usize, writable: bool) -> std::io::Result<Self> {
+        assert!(len > 0);
+        let prot = if writable {
+            libc::PROT_READ | libc::PROT_WRITE
+        } else {
+            libc::PROT_READ
+        };
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                prot,
+                libc::MAP_SHARED,
+                fp.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Self { ptr, len })
+    }
+
+    fn advise(&self, advice: libc::c_int) -> std::io::Result<()> {
+        if unsafe { libc::madvise(self.ptr, self.len, advice) } == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn sync(&self) -> std::io::Result<()> {
+        if unsafe { libc::msync(self.ptr, self.len, libc::MS_SYNC) } == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr.cast(), self.len) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.cast(), self.len) }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr, self.len) };
+    }
+}
+
+// offsets of chunks of mapping in order of access
+fn get_mmap_offsets(len: usize, chunk: usize, access: dir::MmapAccess) -> Vec<usize> {
+    assert!(chunk > 0);
+    let mut l: Vec<usize> = (0..len).step_by(chunk).collect();
+    if access.is_random() {
+        util::shuffle(&mut l);
+    }
+    l
+}
+
+// how lstat and stat are issued
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Stat {
+    engine: dir::StatEngine,
+    mask: dir::StatxMask,
+    sync: dir::StatxSync,
+}
+
+impl Default for Stat {
+    fn default() -> Self {
+        Self {
+            engine: dir::StatEngine::Std,
+            mask: dir::StatxMask::Basic,
+            sync: dir::StatxSync::AsStat,
+        }
+    }
+}
+
+impl Stat {
+    pub(crate) fn new(opt: &Opt) -> Self {
+        Self {
+            engine: opt.stat_engine,
+            mask: opt.statx_mask,
+            sync: opt.statx_sync,
+        }
+    }
+
+    fn get_type(&self, f: &Path, follow: bool) -> std::io::Result<util::FileType> {
+        match self.engine {
+            dir::StatEngine::Std => {
+                if follow {
+                    util::get_file_type(f)
+                } else {
+                    util::get_raw_file_type(f)
+                }
+            }
+            dir::StatEngine::Statx => self.get_type_at(libc::AT_FDCWD, f, follow),
+        }
+    }
+
+    // f is relative to dirfd, std becomes fstatat(2)
+    fn get_type_at(&self, dirfd: RawFd, f: &Path, follow: bool) -> std::io::Result<util::FileType> {
+        let flags = if follow { 0 } else { libc::AT_SYMLINK_NOFOLLOW };
+        match self.engine {
+            dir::StatEngine::Std => util::fstatat(dirfd, f, flags),
+            dir::StatEngine::Statx => {
+                let flags = flags | self.sync.get_flags();
+                let buf = util::statx(dirfd, f, flags, self.mask.get_mask())?;
+                Ok(util::get_statx_type(&buf))
+            }
+        }
+    }
+}
+
+// filesystem operations issued by workers, default methods use std::fs,
+// file data goes through read and write unless mapped
+pub(crate) trait Engine: std::fmt::Debug + Send + Sync {
+    fn get_name(&self) -> &'static str;
+
+    fn get_stat(&self) -> Stat {
+        Stat::default()
+    }
+
+    // type of entry itself
+    fn lstat(&self, f: &Path) -> std::io::Result<util::FileType> {
+        self.get_stat().get_type(f, false)
+    }
+
+    // type of symlink target
+    fn stat(&self, f: &Path) -> std::io::Result<util::FileType> {
+        self.get_stat().get_type(f, true)
+    }
+
+    fn readlink(&self, f: &Path) -> std::io::Result<PathBuf> {
+        util::read_link(f)
+    }
+
+    // attributes of path itself, not a benchmarked operation
+    fn lmetadata(&self, f: &Path) -> std::io::Result<std::fs::Metadata> {
+        std::fs::symlink_metadata(f)
+    }
+
+    fn readdir(&self, d: &Path) -> std::io::Result<Vec<PathBuf>> {
+        let mut l = vec![];
+        for x in std::fs::read_dir(d)? {
+            l.push(x?.path());
+        }
+        Ok(l)
+    }
+
+    fn open(&self, f: &Path, flags: i32) -> std::io::Result<std::fs::File> {
+        get_open_options(flags).open(f)
+    }
+
+    // offset is where file position is expected to be
+    fn read(&self, fp: &mut std::fs::File, b: &mut [u8], _off: u64) -> std::io::Result<usize> {
+        fp.read(b)
+    }
+
+    fn write(&self, fp: &mut std::fs::File, b: &[u8], _off: u64) -> std::io::Result<usize> {
+        fp.write(b)
+    }
+
+    fn truncate(&self, fp: &std::fs::File, len: u64) -> std::io::Result<()> {
+        fp.set_len(len)
+    }
+
+    fn fsync(&self, fp: &std::fs::File) -> std::io::Result<()> {
+        if nix::unistd::fsync(fp.as_raw_fd()).is_err() {
+            return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
+        }
+        Ok(())
+    }
+
+    fn create(&self, oldf: &Path, newf: &Path, t: dir::WritePathsType) -> std::io::Result<()> {
+        match t {
+            dir::WritePathsType::Dir => std::fs::create_dir(newf),
+            dir::WritePathsType::Reg => std::fs::File::create(newf).map(|_| ()),
+            dir::WritePathsType::Symlink => std::os::unix::fs::symlink(oldf, newf),
+            dir::WritePathsType::Link => std::fs::hard_link(oldf, newf),
+        }
+    }
+
+    fn unlink(&self, f: &Path, t: util::FileType) -> std::io::Result<()> {
+        if t.is_dir() {
+            std::fs::remove_dir(f)
+        } else {
+            std::fs::remove_file(f)
+        }
+    }
+
+    fn listxattr(&self, f: &Path) -> std::io::Result<Vec<std::ffi::OsString>> {
+        util::list_xattr(f)
+    }
+
+    fn getxattr(&self, f: &Path, name: &std::ffi::OsStr, b: &mut [u8]) -> std::io::Result<usize> {
+        util::get_xattr(f, name, b)
+    }
+
+    fn setxattr(&self, f: &Path, name: &std::ffi::OsStr, b: &[u8]) -> std::io::Result<()> {
+        util::set_xattr(f, name, b)
+    }
+
+    // entries per batch of lstat_batch and read_files
+    fn get_depth(&self) -> usize {
+        1
+    }
+
+    // per thread state, e.g. ring of uring engine
+    fn init_thread(&self, _thr: &mut worker::Thread) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn lstat_batch(
+        &self,
+        l: &[PathBuf],
+        _thr: &mut worker::Thread,
+    ) -> std::io::Result<Vec<util::FileType>> {
+        l.iter().map(|f| self.lstat(f)).collect()
+    }
+
+    // file data is copied from and to mapping instead of read and write
+    fn is_mmap(&self) -> bool {
+        false
+    }
+
+    fn mmap(&self, fp: &std::fs::File, len: usize, writable: bool) -> std::io::Result<Mmap> {
+        Mmap::new(fp, len, writable)
+    }
+
+    // negative residual means read until EOF
+    fn read_files(
+        &self,
+        fl: &[(PathBuf, isize)],
+        thr: &mut worker::Thread,
+        opt: &Opt,
+    ) -> std::io::Result<()> {
+        for (f, resid) in fl {
+            if self.is_mmap() {
+                read_file_mmap(self, f, *resid, thr, opt)?;
+            } else {
+                read_file_std(self, f, *resid, thr, opt)?;
+            }
+        }
+        Ok(())
+    }
+
+    // starts at offset, which is the file size for aging,
+    // data is doubled buffer size of random write data if any
+    fn write_file(
+        &self,
+        f: &Path,
+        off: u64,
+        resid: isize,
+        thr: &mut worker::Thread,
+        data: &[u8],
+        opt: &Opt,
+    ) -> std::io::Result<()> {
+        if self.is_mmap() {
+            write_file_mmap(self, f, off, resid, thr, data, opt)
+        } else {
+            write_file_std(self, f, off, resid, thr, data, opt)
+        }
+    }
+
+    // operations which would have been issued if dry run
+    fn get_plan(&self) -> Option<Plan> {
+        None
+    }
+
+    // counts of injected faults if fault injection
+    fn get_faults(&self) -> Option<BTreeMap<String, usize>> {
+        None
+    }
+}
+
+// copy random write data at random offset, data is doubled buffer size
+fn copy_random_data(b: &mut [u8], data: &[u8]) {
+    let i = util::get_random(0..data.len() / 2);
+    b.clone_from_slice(&data[i..i + b.len()]);
+}
+
+fn read_file_std<E: Engine + ?Sized>(
+    e: &E,
+    f: &Path,
+    resid: isize,
+    thr: &mut worker::Thread,
+    opt: &Opt,
+) -> std::io::Result<()> {
+    let mut fp = e.open(f, libc::O_RDONLY)?;
+    let mut b: &mut [u8] = &mut thr.dir.read_buffer;
+    let mut resid = resid;
+    let mut off = 0;
+
+    loop {
+        // cut slice size if > positive residual
+        if resid > 0 && b.len() > resid.try_into().unwrap() {
+            b = &mut b[..resid.try_into().unwrap()];
+        }
+
+        let siz = e.read(&mut fp, b, off)?;
+        thr.stat.inc_num_read();
+        thr.stat.add_num_read_bytes(siz);
+        if siz == 0 {
+            break;
+        }
+        off += u64::try_from(siz).unwrap();
+
+        // end if positive residual becomes <= 0
+        if resid > 0 {
+            resid -= isize::try_from(siz).unwrap();
+            if resid <= 0 {
+                if opt.debug {
+                    assert_eq!(resid, 0);
+                }
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+// copy pages of mapping to read buffer, one read per buffer size
+fn read_file_mmap<E: Engine + ?Sized>(
+    e: &E,
+    f: &Path,
+    resid: isize,
+    thr: &mut worker::Thread,
+    opt: &Opt,
+) -> std::io::Result<()> {
+    let fp = e.open(f, libc::O_RDONLY)?;
+    let mut len = usize::try_from(fp.metadata()?.len()).unwrap();
+    if resid > 0 {
+        len = std::cmp::min(len, resid.try_into().unwrap());
+    }
+    let b: &mut [u8] = &mut thr.dir.read_buffer;
+    if len == 0 || b.is_empty() {
+        thr.stat.inc_num_read(); // as in EOF
+        return Ok(());
+    }
+
+    let m = e.mmap(&fp, len, false)?;
+    m.advise(opt.mmap_access.get_advice())?;
+    for i in get_mmap_offsets(len, b.len(), opt.mmap_access) {
+        let x = &m.as_slice()[i..std::cmp::min(i + b.len(), len)];
+        b[..x.len()].copy_from_slice(x);
+        thr.stat.inc_num_read();
+        thr.stat.add_num_read_bytes(x.len());
+    }
+    Ok(())
+}
+
+fn write_file_std<E: Engine + ?Sized>(
+    e: &E,
+    f: &Path,
+    off: u64,
+    resid: isize,
+    thr: &mut worker::Thread,
+    data: &[u8],
+    opt: &Opt,
+) -> std::io::Result<()> {
+    // not O_APPEND which ignores offset of pwrite(2)
+    let mut fp = e.open(f, libc::O_WRONLY)?;
+    let mut b: &mut [u8] = &mut thr.dir.write_buffer;
+    let mut resid = resid;
+    let mut off = off;
+    if off > 0 {
+        fp.seek(std::io::SeekFrom::Start(off))?; // file position for write
+    }
+
+    if opt.truncate_write_paths {
+        e.truncate(&fp, off + u64::try_from(resid).unwrap())?;
+        thr.stat.inc_num_write();
+    } else {
+        loop {
+            // cut slice size if > residual
+            if resid > 0 && b.len() > resid.try_into().unwrap() {
+                b = &mut b[..resid.try_into().unwrap()];
+            }
+            if opt.random_write_data {
+                copy_random_data(b, data);
+            }
+
+            let siz = e.write(&mut fp, b, off)?;
+            thr.stat.inc_num_write();
+            thr.stat.add_num_write_bytes(siz);
+            off += u64::try_from(siz).unwrap();
+
+            // end if residual becomes <= 0
+            resid -= isize::try_from(siz).unwrap();
+            if resid <= 0 {
+                if opt.debug {
+                    assert_eq!(resid, 0);
+                }
+                break;
+            }
+        }
+    }
+
+    if opt.fsync_write_paths {
+        e.fsync(&fp)?;
+    }
+    Ok(())
+}
+
+// extend file, then copy write buffer to pages of mapping past offset
+// and msync(2), mapping starts at 0 as offset may not be page aligned
+fn write_file_mmap<E: Engine + ?Sized>(
+    e: &E,
+    f: &Path,
+    off: u64,
+    resid: isize,
+    thr: &mut worker::Thread,
+    data: &[u8],
+    opt: &Opt,
+) -> std::io::Result<()> {
+    let fp = e.open(f, libc::O_RDWR)?;
+    let off = usize::try_from(off).unwrap();
+    let len = usize::try_from(resid).unwrap();
+    e.truncate(&fp, (off + len).try_into().unwrap())?;
+    let b: &mut [u8] = &mut thr.dir.write_buffer;
+    if opt.truncate_write_paths || b.is_empty() {
+        thr.stat.inc_num_write();
+    } else {
+        let mut m = e.mmap(&fp, off + len, true)?;
+        m.advise(opt.mmap_access.get_advice())?;
+        for i in get_mmap_offsets(len, b.len(), opt.mmap_access) {
+            let n = std::cmp::min(b.len(), len - i);
+            if opt.random_write_data {
+                copy_random_data(&mut b[..n], data);
+            }
+            m.as_mut_slice()[off + i..off + i + n].copy_from_slice(&b[..n]);
+            thr.stat.inc_num_write();
+            thr.stat.add_num_write_bytes(n);
+        }
+        m.sync()?;
+    }
+
+    if opt.fsync_write_paths {
+        e.fsync(&fp)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct StdEngine {
+    stat: Stat,
+}
+
+impl Engine for StdEngine {
+    fn get_name(&self) -> &'static str {
+        "std"
+    }
+
+    fn get_stat(&self) -> Stat {
+        self.stat
+    }
+}
+
+// pread(2) and pwrite(2) at explicit offsets instead of file position
+#[derive(Debug, Default)]
+pub(crate) struct PreadEngine {
+    stat: Stat,
+}
+
+impl Engine for PreadEngine {
+    fn get_name(&self) -> &'static str {
+        "pread"
+    }
+
+    fn get_stat(&self) -> Stat {
+        self.stat
+    }
+
+    fn read(&self, fp: &mut std::fs::File, b: &mut [u8], off: u64) -> std::io::Result<usize> {
+        fp.read_at(b, off)
+    }
+
+    fn write(&self, fp: &mut std::fs::File, b: &[u8], off: u64) -> std::io::Result<usize> {
+        fp.write_at(b, off)
+    }
+}
+
+// shared mappings of files instead of read and write
+#[derive(Debug, Default)]
+pub(crate) struct MmapEngine {
+    stat: Stat,
+}
+
+impl Engine for MmapEngine {
+    fn get_name(&self) -> &'static str {
+        "mmap"
+    }
+
+    fn get_stat(&self) -> Stat {
+        self.stat
+    }
+
+    fn is_mmap(&self) -> bool {
+        true
+    }
+}
+
+// statx, open, read, write, fsync and close of file data submitted to
+// io_uring(7) per thread, each queued operation uses a slot of buffer
+// registered to ring, other operations use std::fs
+#[derive(Debug, Default)]
+pub(crate) struct UringEngine {
+    stat: Stat,
+    depth: usize,
+}
+
+impl Engine for UringEngine {
+    fn get_name(&self) -> &'static str {
+        "uring"
+    }
+
+    fn get_stat(&self) -> Stat {
+        self.stat
+    }
+
+    fn get_depth(&self) -> usize {
+        self.depth
+    }
+
+    fn init_thread(&self, thr: &mut worker::Thread) -> std::io::Result<()> {
+        thr.dir.init_ring(self.depth)
+    }
+
+    fn lstat_batch(
+        &self,
+        l: &[PathBuf],
+        thr: &mut worker::Thread,
+    ) -> std::io::Result<Vec<util::FileType>> {
+        let mut cv = vec![];
+        for x in l {
+            cv.push(util::get_cstring(x.as_os_str())?);
+        }
+        let mut stxv: Vec<libc::statx> = vec![unsafe { std::mem::zeroed() }; l.len()];
+        let ev = cv
+            .iter()
+            .zip(stxv.iter_mut())
+            .map(|(c, b)| uring::new_statx(c, b, self.stat.mask, self.stat.sync))
+            .collect();
+        let resv = thr.dir.ring.as_mut().unwrap().submit_all(ev)?;
+
+        let mut tv = vec![];
+        for (i, res) in resv.into_iter().enumerate() {
+            uring::get_result(res)?;
+            tv.push(util::get_statx_type(&stxv[i]));
+        }
+        Ok(tv)
+    }
+
+    // open, read and close files in a batch, each file uses a slot of buffer
+    fn read_files(
+        &self,
+        fl: &[(PathBuf, isize)],
+        thr: &mut worker::Thread,
+        _opt: &Opt,
+    ) -> std::io::Result<()> {
+        if fl.is_empty() {
+            return Ok(());
+        }
+        let slot = dir::get_slot_size(&thr.dir.read_buffer, thr);
+        let ring = thr.dir.ring.as_mut().unwrap();
+        assert!(fl.len() <= ring.get_depth());
+
+        let mut cv = vec![];
+        for x in fl {
+            cv.push(util::get_cstring(x.0.as_os_str())?);
+        }
+        let ev = cv
+            .iter()
+            .map(|c| uring::new_openat(c, libc::O_RDONLY))
+            .collect();
+        let mut fdv = vec![];
+        let mut err = None;
+        for res in ring.submit_all(ev)? {
+            match uring::get_result(res) {
+                Ok(v) => {
+                    fdv.push(unsafe { std::os::fd::OwnedFd::from_raw_fd(v.try_into().unwrap()) })
+                }
+                Err(e) => err = Some(e),
+            }
+        }
+        if let Some(e) = err {
+            return Err(e); // opened files are closed on drop
+        }
+
+        // read until EOF or residual becomes <= 0, as in read_file_std
+        let mut offv = vec![0; fl.len()];
+        let mut residv: Vec<isize> = fl.iter().map(|x| x.1).collect();
+        let mut donev = vec![false; fl.len()];
+        if slot == 0 {
+            for _ in fl {
+                thr.stat.inc_num_read(); // as in EOF
+            }
+            donev.fill(true);
+        }
+        loop {
+            let mut ev = vec![];
+            let mut iv = vec![];
+            for i in 0..fl.len() {
+                if donev[i] {
+                    continue;
+                }
+                let mut n = slot;
+                if residv[i] > 0 {
+                    n = std::cmp::min(n, residv[i].try_into().unwrap());
+                }
+                let b = &mut thr.dir.read_buffer[i * slot..i * slot + n];
+                ev.push(uring::new_read_fixed(fdv[i].as_raw_fd(), b, offv[i]));
+                iv.push(i);
+            }
+            if ev.is_empty() {
+                break;
+            }
+            for (j, res) in ring.submit_all(ev)?.into_iter().enumerate() {
+                let i = iv[j];
+                let siz = uring::get_result(res)?;
+                thr.stat.inc_num_read();
+                thr.stat.add_num_read_bytes(siz);
+                offv[i] += u64::try_from(siz).unwrap();
+                if siz == 0 {
+                    donev[i] = true;
+                } else if residv[i] > 0 {
+                    residv[i] -= isize::try_from(siz).unwrap();
+                    donev[i] = residv[i] <= 0;
+                }
+            }
+        }
+
+        let ev = fdv
+            .into_iter()
+            .map(|x| uring::new_close(x.into_raw_fd()))
+            .collect();
+        for res in ring.submit_all(ev)? {
+            uring::get_result(res)?;
+        }
+        Ok(())
+    }
+
+    // open, write up to queue depth slots of buffer at a time, fsync and close
+    fn write_file(
+        &self,
+        f: &Path,
+        off: u64,
+        resid: isize,
+        thr: &mut worker::Thread,
+        data: &[u8],
+        opt: &Opt,
+    ) -> std::io::Result<()> {
+        let slot = dir::get_slot_size(&thr.dir.write_buffer, thr);
+        let ring = thr.dir.ring.as_mut().unwrap();
+        let c = util::get_cstring(f.as_os_str())?;
+        let res = ring.submit_all(vec![uring::new_openat(&c, libc::O_WRONLY)])?[0];
+        let fd = unsafe {
+            std::os::fd::OwnedFd::from_raw_fd(uring::get_result(res)?.try_into().unwrap())
+        };
+        let len = u64::try_from(resid).unwrap();
+
+        if opt.truncate_write_paths {
+            nix::unistd::ftruncate(&fd, (off + len).try_into().unwrap())?;
+            thr.stat.inc_num_write();
+        } else if slot == 0 {
+            thr.stat.inc_num_write();
+        } else {
+            let offv: Vec<u64> = (off..off + len).step_by(slot).collect();
+            for x in offv.chunks(ring.get_depth()) {
+                // slot index, file offset, bytes written and bytes to write
+                let mut l = vec![];
+                for (i, o) in x.iter().enumerate() {
+                    let n = std::cmp::min(slot, usize::try_from(off + len - o).unwrap());
+                    let b = &mut thr.dir.write_buffer[i * slot..i * slot + n];
+                    if opt.random_write_data {
+                        copy_random_data(b, data);
+                    }
+                    l.push((i, *o, 0, n));
+                }
+                // resubmit remaining range of short writes
+                while !l.is_empty() {
+                    let ev = l
+                        .iter()
+                        .map(|(i, o, done, n)| {
+                            let b = &thr.dir.write_buffer[i * slot + done..i * slot + n];
+                            uring::new_write_fixed(
+                                fd.as_raw_fd(),
+                                b,
+                                o + u64::try_from(*done).unwrap(),
+                            )
+                        })
+                        .collect();
+                    let mut next = vec![];
+                    for ((i, o, done, n), res) in l.into_iter().zip(ring.submit_all(ev)?) {
+                        let siz = uring::get_result(res)?;
+                        thr.stat.inc_num_write();
+                        thr.stat.add_num_write_bytes(siz);
+                        if siz == 0 {
+                            return Err(std::io::Error::from(std::io::ErrorKind::WriteZero));
+                        } else if done + siz < n {
+                            next.push((i, o, done + siz, n));
+                        }
+                    }
+                    l = next;
+                }
+            }
+        }
+
+        if opt.fsync_write_paths {
+            let res = ring.submit_all(vec![uring::new_fsync(fd.as_raw_fd())])?[0];
+            uring::get_result(res)?;
+        }
+        let res = ring.submit_all(vec![uring::new_close(fd.into_raw_fd())])?[0];
+        uring::get_result(res)?;
+        Ok(())
+    }
+}
+
+// looks up entries by base name relative to open parent directory per thread
+// instead of full path, file data and xattr go to inner engine
+#[derive(Debug)]
+pub(crate) struct DirfdEngine {
+    inner: Box<dyn Engine>,
+}
+
+impl DirfdEngine {
+    pub(crate) fn new(inner: Box<dyn Engine>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Engine for DirfdEngine {
+    fn get_name(&self) -> &'static str {
+        "dirfd"
+    }
+
+    fn get_stat(&self) -> Stat {
+        self.inner.get_stat()
+    }
+
+    fn lstat(&self, f: &Path) -> std::io::Result<util::FileType> {
+        let stat = self.get_stat();
+        with_dirfd(f, |d, b| stat.get_type_at(d, b, false))
+    }
+
+    fn stat(&self, f: &Path) -> std::io::Result<util::FileType> {
+        let stat = self.get_stat();
+        with_dirfd(f, |d, b| stat.get_type_at(d, b, true))
+    }
+
+    fn readlink(&self, f: &Path) -> std::io::Result<PathBuf> {
+        with_dirfd(f, util::read_link_at)
+    }
+
+    fn readdir(&self, d: &Path) -> std::io::Result<Vec<PathBuf>> {
+        self.inner.readdir(d)
+    }
+
+    fn open(&self, f: &Path, flags: i32) -> std::io::Result<std::fs::File> {
+        with_dirfd(f, |d, b| util::open_at(d, b, flags, 0))
+    }
+
+    fn read(&self, fp: &mut std::fs::File, b: &mut [u8], off: u64) -> std::io::Result<usize> {
+        self.inner.read(fp, b, off)
+    }
+
+    fn write(&self, fp: &mut std::fs::File, b: &[u8], off: u64) -> std::io::Result<usize> {
+        self.inner.write(fp, b, off)
+    }
+
+    fn is_mmap(&self) -> bool {
+        self.inner.is_mmap()
+    }
+
+    fn mmap(&self, fp: &std::fs::File, len: usize, writable: bool) -> std::io::Result<Mmap> {
+        self.inner.mmap(fp, len, writable)
+    }
+
+    fn truncate(&self, fp: &std::fs::File, len: u64) -> std::io::Result<()> {
+        self.inner.truncate(fp, len)
+    }
+
+    fn fsync(&self, fp: &std::fs::File) -> std::io::Result<()> {
+        self.inner.fsync(fp)
+    }
+
+    // link source is an input file looked up by full path
+    fn create(&self, oldf: &Path, newf: &Path, t: dir::WritePathsType) -> std::io::Result<()> {
+        with_dirfd(newf, |d, b| match t {
+            dir::WritePathsType::Dir => util::mkdir_at(d, b, 0o777),
+            dir::WritePathsType::Reg => {
+                let flags = libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC;
+                util::open_at(d, b, flags, 0o666).map(|_| ())
+            }
+            dir::WritePathsType::Symlink => util::symlink_at(oldf, d, b),
+            dir::WritePathsType::Link => util::link_at(libc::AT_FDCWD, oldf, d, b),
+        })
+    }
+
+    fn unlink(&self, f: &Path, t: util::FileType) -> std::io::Result<()> {
+        let flags = if t.is_dir() { libc::AT_REMOVEDIR } else { 0 };
+        with_dirfd(f, |d, b| util::unlink_at(d, b, flags))?;
+        if t.is_dir() {
+            DIRFD.with_borrow_mut(|x| x.remove(f));
+        }
+        Ok(())
+    }
+
+    fn listxattr(&self, f: &Path) -> std::io::Result<Vec<std::ffi::OsString>> {
+        self.inner.listxattr(f)
+    }
+
+    fn getxattr(&self, f: &Path, name: &std::ffi::OsStr, b: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.getxattr(f, name, b)
+    }
+
+    fn setxattr(&self, f: &Path, name: &std::ffi::OsStr, b: &[u8]) -> std::io::Result<()> {
+        self.inner.setxattr(f, name, b)
+    }
+}
+
+// counts of operations by name, and first paths of them
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Plan {
+    pub(crate) counts: BTreeMap<&'static str, usize>,
+    pub(crate) samples: BTreeMap<&'static str, Vec<PathBuf>>,
+    pub(crate) num_write_bytes: usize,
+    created: HashMap<PathBuf, util::FileType>, // entries as if created
+}
+
+impl Plan {
+    fn add(&mut self, op: &'static str, f: Option<&Path>) {
+        *self.counts.entry(op).or_insert(0) += 1;
+        if let Some(f) = f {
+            let l = self.samples.entry(op).or_default();
+            if l.len() < MAX_PLAN_SAMPLE {
+                l.push(f.to_path_buf());
+            }
+        }
+    }
+
+    pub(crate) fn print(&self) {
+        println!("Dry run plan");
+        if self.counts.is_empty() {
+            println!("  none");
+        }
+        for (op, n) in &self.counts {
+            if *op == "write" {
+                println!("  {op} {n} ({} bytes)", self.num_write_bytes);
+            } else {
+                println!("  {op} {n}");
+            }
+            for f in self.samples.get(op).into_iter().flatten() {
+                println!("    {}", f.display());
+            }
+            if *n > MAX_PLAN_SAMPLE && self.samples.contains_key(op) {
+                println!("    ...");
+            }
+        }
+    }
+}
+
+// records operations which read file data or modify filesystem instead of
+// issuing them, lookups are issued, files are opened as /dev/null,
+// file data of mmap and uring engines is recorded as read and write
+#[derive(Debug)]
+pub(crate) struct DryRunEngine {
+    inner: Box<dyn Engine>,
+    plan: std::sync::Mutex<Plan>,
+}
+
+impl DryRunEngine {
+    pub(crate) fn new(inner: Box<dyn Engine>) -> Self {
+        Self {
+            inner,
+            plan: std::sync::Mutex::new(Plan::default()),
+        }
+    }
+
+    fn add(&self, op: &'static str, f: Option<&Path>) {
+        self.plan.lock().unwrap().add(op, f);
+    }
+
+    fn get_created(&self, f: &Path) -> Option<util::FileType> {
+        self.plan.lock().unwrap().created.get(f).copied()
+    }
+}
+
+impl Engine for DryRunEngine {
+    fn get_name(&self) -> &'static str {
+        "dry run"
+    }
+
+    fn get_stat(&self) -> Stat {
+        self.inner.get_stat()
+    }
+
+    fn lstat(&self, f: &Path) -> std::io::Result<util::FileType> {
+        match self.get_created(f) {
+            Some(v) => Ok(v),
+            None => self.inner.lstat(f),
+        }
+    }
+
+    fn stat(&self, f: &Path) -> std::io::Result<util::FileType> {
+        match self.get_created(f) {
+            Some(v) => Ok(v),
+            None => self.inner.stat(f),
+        }
+    }
+
+    fn readlink(&self, f: &Path) -> std::io::Result<PathBuf> {
+        self.inner.readlink(f)
+    }
+
+    fn readdir(&self, d: &Path) -> std::io::Result<Vec<PathBuf>> {
+        self.inner.readdir(d)
+    }
+
+    fn open(&self, f: &Path, flags: i32) -> std::io::Result<std::fs::File> {
+        if self.get_created(f).is_none() {
+            self.add("open", Some(f));
+        }
+        get_open_options(flags).open("/dev/null")
+    }
+
+    fn read(&self, _fp: &mut std::fs::File, _b: &mut [u8], _off: u64) -> std::io::Result<usize> {
+        Ok(0)
+    }
+
+    fn write(&self, _fp: &mut std::fs::File, b: &[u8], _off: u64) -> std::io::Result<usize> {
+        let mut plan = self.plan.lock().unwrap();
+        plan.add("write", None);
+        plan.num_write_bytes += b.len();
+        Ok(b.len())
+    }
+
+    fn truncate(&self, _fp: &std::fs::File, _len: u64) -> std::io::Result<()> {
+        self.add("truncate", None);
+        Ok(())
+    }
+
+    fn fsync(&self, _fp: &std::fs::File) -> std::io::Result<()> {
+        self.add("fsync", None);
+        Ok(())
+    }
+
+    fn create(&self, _oldf: &Path, newf: &Path, t: dir::WritePathsType) -> std::io::Result<()> {
+        if self.lstat(newf).is_ok() {
+            return Err(std::io::Error::from(std::io::ErrorKind::AlreadyExists));
+        }
+        let (op, x) = match t {
+            dir::WritePathsType::Dir => ("create dir", util::FileType::Dir),
+            dir::WritePathsType::Reg => ("create reg", util::FileType::Reg),
+            dir::WritePathsType::Symlink => ("create symlink", util::FileType::Symlink),
+            dir::WritePathsType::Link => ("create link", util::FileType::Reg),
+        };
+        let mut plan = self.plan.lock().unwrap();
+        plan.add(op, Some(newf));
+        plan.created.insert(newf.to_path_buf(), x);
+        Ok(())
+    }
+
+    fn unlink(&self, f: &Path, _t: util::FileType) -> std::io::Result<()> {
+        let mut plan = self.plan.lock().unwrap();
+        plan.add("unlink", Some(f));
+        plan.created.remove(f);
+        Ok(())
+    }
+
+    // nothing is actually created or unlinked
+    fn lmetadata(&self, _f: &Path) -> std::io::Result<std::fs::Metadata> {
+        Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+    }
+
+    // planned entries have no attributes
+    fn listxattr(&self, f: &Path) -> std::io::Result<Vec<std::ffi::OsString>> {
+        match self.get_created(f) {
+            Some(_) => Ok(vec![]),
+            None => self.inner.listxattr(f),
+        }
+    }
+
+    fn getxattr(&self, f: &Path, name: &std::ffi::OsStr, b: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.getxattr(f, name, b)
+    }
+
+    fn setxattr(&self, f: &Path, _name: &std::ffi::OsStr, _b: &[u8]) -> std::io::Result<()> {
+        self.add("setxattr", Some(f));
+        Ok(())
+    }
+
+    fn get_plan(&self) -> Option<Plan> {
+        Some(self.plan.lock().unwrap().clone())
+    }
+}
+
+pub(crate) fn new_engine(opt: &Opt) -> Box<dyn Engine> {
+    let mut engine: Box<dyn Engine> = match opt.io_engine {
+        dir::IoEngine::Pread => Box::new(PreadEngine {
+            stat: Stat::new(opt),
+        }),
+        dir::IoEngine::Std => Box::new(StdEngine {
+            stat: Stat::new(opt),
+        }),
+        dir::IoEngine::Mmap => Box::new(MmapEngine {
+            stat: Stat::new(opt),
+        }),
+        dir::IoEngine::Uring => Box::new(UringEngine {
+            stat: Stat::new(opt),
+            depth: opt.io_depth,
+        }),
+    };
+    if opt.dirfd {
+        engine = Box::new(DirfdEngine::new(engine));
+    }
+    if !opt.fault.is_empty() {
+        engine = Box::new(fault::FaultEngine::new(engine, &opt.fault, opt.fault_seed));
+    }
+    if opt.dry_run {
+        Box::new(DryRunEngine::new(engine))
+    } else {
+        engine
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Engine;
+
+    #[test]
+    fn test_new_engine() {
+        let mut opt = crate::Opt {
+            ..Default::default()
+        };
+        assert_eq!(super::new_engine(&opt).get_name(), "std");
+        opt.io_engine = crate::dir::IoEngine::Mmap;
+        assert_eq!(super::new_engine(&opt).get_name(), "mmap");
+        assert!(super::new_engine(&opt).is_mmap());
+        opt.io_engine = crate::dir::IoEngine::Uring;
+        assert_eq!(super::new_engine(&opt).get_name(), "uring");
+        assert_eq!(super::new_engine(&opt).get_depth(), opt.io_depth);
+        opt.io_engine = crate::dir::IoEngine::Pread;
+        assert_eq!(super::new_engine(&opt).get_name(), "pread");
+        assert!(super::new_engine(&opt).get_plan().is_none());
+        opt.dry_run = true;
+        assert_eq!(super::new_engine(&opt).get_name(), "dry run");
+        assert!(super::new_engine(&opt).get_plan().is_some());
+    }
+
+    #[test]
+    fn test_get_mmap_offsets() {
+        let l = super::get_mmap_offsets(10, 4, crate::dir::MmapAccess::Sequential);
+        assert_eq!(l, vec![0, 4, 8]);
+        let l = super::get_mmap_offsets(8, 4, crate::dir::MmapAccess::Sequential);
+        assert_eq!(l, vec![0, 4]);
+        let l = super::get_mmap_offsets(1, 4, crate::dir::MmapAccess::Sequential);
+        assert_eq!(l, vec![0]);
+
+        let mut l = super::get_mmap_offsets(4096 * 64, 4096, crate::dir::MmapAccess::Random);
+        assert_eq!(l.len(), 64);
+        l.sort_unstable();
+        assert_eq!(
+            l,
+            super::get_mmap_offsets(4096 * 64, 4096, crate::dir::MmapAccess::Sequential)
+        );
+    }
+
+    #[test]
+    fn test_mmap() {
+        let f = std::env::temp_dir().join(format!("dirload_test_mmap_{}", std::process::id()));
+        let fp = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&f)
+            .unwrap();
+        fp.set_len(10000).unwrap();
+        {
+            let mut m = super::Mmap::new(&fp, 10000, true).unwrap();
+            m.advise(crate::dir::MmapAccess::Random.get_advice())
+                .unwrap();
+            m.as_mut_slice()[9999] = 0x41;
+            m.sync().unwrap();
+        }
+        let m = super::Mmap::new(&fp, 10000, false).unwrap();
+        assert_eq!(m.as_slice()[0], 0);
+        assert_eq!(m.as_slice()[9999], 0x41);
+        drop(m);
+        assert_eq!(std::fs::read(&f).unwrap()[9999], 0x41);
+        std::fs::remove_file(&f).unwrap();
+    }
+
+    #[test]
+    fn test_dry_run_engine() {
+        let d = std::env::temp_dir();
+        let a = d.join(format!("dirload_test_dry_run_{}", std::process::id()));
+        let b = a.join("b");
+        let x = super::DryRunEngine::new(Box::new(super::StdEngine::default()));
+        x.create(&d, &a, crate::dir::WritePathsType::Dir).unwrap();
+        x.create(&d, &b, crate::dir::WritePathsType::Reg).unwrap();
+        assert!(x.lstat(&a).unwrap().is_dir());
+        assert!(x.lstat(&d).unwrap().is_dir());
+        assert_eq!(
+            x.create(&d, &a, crate::dir::WritePathsType::Reg)
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::AlreadyExists
+        );
+        assert!(x.lstat(&d.join("..")).is_ok()); // existing one
+        assert!(crate::util::get_raw_file_type(&a).is_err()); // not created
+
+        let mut fp = x.open(&b, libc::O_WRONLY).unwrap();
+        assert_eq!(x.write(&mut fp, &[0; 100], 0).unwrap(), 100);
+        assert_eq!(x.write(&mut fp, &[0; 100], 100).unwrap(), 100);
+        x.fsync(&fp).unwrap();
+        let mut fp = x.open(&d, libc::O_RDONLY).unwrap();
+        assert_eq!(x.read(&mut fp, &mut [0; 100], 0).unwrap(), 0);
+        let name = std::ffi::OsStr::new("user.dirload.0");
+        x.setxattr(&b, name, &[0x41; 10]).unwrap();
+        assert!(x.listxattr(&b).unwrap().is_empty());
+        x.unlink(&b, crate::util::FileType::Reg).unwrap();
+        x.unlink(&a, crate::util::FileType::Dir).unwrap();
+        assert!(x.lstat(&a).is_err());
+
+        let plan = x.get_plan().unwrap();
+        let l: Vec<(&str, usize)> = plan.counts.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(
+            l,
+            vec![
+                ("create dir", 1),
+                ("create reg", 1),
+                ("fsync", 1),
+                ("open", 1),
+                ("setxattr", 1),
+                ("unlink", 2),
+                ("write", 2)
+            ]
+        );
+        assert_eq!(plan.num_write_bytes, 200);
+        assert_eq!(plan.samples["open"], vec![d.clone()]);
+        assert_eq!(plan.samples["unlink"], vec![b, a]);
+        assert!(!plan.samples.contains_key("write"));
+    }
+
+    #[test]
+    fn test_stat() {
+        let d = std::env::temp_dir().join(format!("dirload_test_stat_{}", std::process::id()));
+        let (a, b) = (d.join("a"), d.join("b"));
+        std::fs::create_dir(&d).unwrap();
+        std::fs::write(&a, b"").unwrap();
+        std::os::unix::fs::symlink(&a, &b).unwrap();
+        let l = [
+            (crate::dir::StatEngine::Std, crate::dir::StatxMask::Basic),
+            (crate::dir::StatEngine::Statx, crate::dir::StatxMask::Type),
+            (crate::dir::StatEngine::Statx, crate::dir::StatxMask::All),
+        ];
+        for (engine, mask) in l {
+            for sync in [
+                crate::dir::StatxSync::AsStat,
+                crate::dir::StatxSync::Force,
+                crate::dir::StatxSync::Dont,
+            ] {
+                let x = super::StdEngine {
+                    stat: super::Stat { engine, mask, sync },
+                };
+                let s = format!("{engine:?} {mask:?} {sync:?}");
+                assert!(x.lstat(&d).unwrap().is_dir(), "{s}");
+                assert!(x.lstat(&a).unwrap().is_reg(), "{s}");
+                assert!(x.lstat(&b).unwrap().is_symlink(), "{s}");
+                assert!(x.stat(&b).unwrap().is_reg(), "{s}");
+                assert!(x.lstat(std::path::Path::new("/")).unwrap().is_dir(), "{s}");
+                assert_eq!(
+                    x.lstat(&d.join("c")).unwrap_err().kind(),
+                    std::io::ErrorKind::NotFound,
+                    "{s}"
+                );
+            }
+        }
+
+        std::fs::remove_dir_all(&d).unwrap();
+    }
+
+    #[test]
+    fn test_dirfd() {
+        let d = std::env::temp_dir().join(format!("dirload_test_dirfd_{}", std::process::id()));
+        let a = d.join("a");
+        std::fs::create_dir(&d).unwrap();
+        std::fs::create_dir(&a).unwrap();
+        let x = super::DirfdEngine::new(Box::new(super::StdEngine::default()));
+        assert!(x.lstat(&a).unwrap().is_dir());
+
+        // cached one is kept on ENOENT of entry
+        let fd = super::DIRFD.with_borrow_mut(|x| x.get(&d).unwrap());
+        assert!(x.lstat(&d.join("b")).is_err());
+        assert_eq!(super::DIRFD.with_borrow_mut(|x| x.get(&d).unwrap()), fd);
+
+        // cached parent directory replaced
+        std::fs::remove_dir_all(&d).unwrap();
+        std::fs::create_dir(&d).unwrap();
+        std::fs::create_dir(&a).unwrap();
+        assert!(x.lstat(&a).unwrap().is_dir());
+        std::fs::remove_dir_all(&d).unwrap();
+        assert!(x.lstat(&a).is_err());
+
+        // least recently used one is closed when full
+        let mut c = super::DirfdCache::default();
+        let e = std::env::temp_dir();
+        c.get(&e).unwrap();
+        for i in 0..super::MAX_DIRFD * 2 {
+            std::fs::create_dir_all(d.join(i.to_string())).unwrap();
+            c.get(&d.join(i.to_string())).unwrap();
+            c.get(&e).unwrap();
+            assert!(c.m.len() <= super::MAX_DIRFD);
+        }
+        assert!(c.m.contains_key(&e));
+        assert!(!c.m.contains_key(&d.join("0")));
+        std::fs::remove_dir_all(&d).unwrap();
+    }
+
+    #[test]
+    fn test_engine() {
+        let d = std::env::temp_dir().join(format!("dirload_test_engine_{}", std::process::id()));
+        let (a, b, c) = (d.join("a"), d.join("b"), d.join("c"));
+        for x in [
+            &super::StdEngine::default() as &dyn Engine,
+            &super::PreadEngine::default(),
+            &super::DirfdEngine::new(Box::new(super::StdEngine::default())),
+        ] {
+            x.create(&d, &d, crate::dir::WritePathsType::Dir).unwrap();
+            x.create(&d, &a, crate::dir::WritePathsType::Reg).unwrap();
+            x.create(&a, &b, crate::dir::WritePathsType::Symlink)
+                .unwrap();
+            x.create(&a, &c, crate::dir::WritePathsType::Link).unwrap();
+            assert!(x.lstat(&d).unwrap().is_dir());
+            assert!(x.lstat(&b).unwrap().is_symlink());
+            assert!(x.stat(&b).unwrap().is_reg());
+            assert_eq!(x.readlink(&b).unwrap(), a);
+            let mut l = x.readdir(&d).unwrap();
+            l.sort();
+            assert_eq!(l, vec![a.clone(), b.clone(), c.clone()]);
+
+            // write and read at offsets of file position
+            let mut fp = x.open(&a, libc::O_RDWR).unwrap();
+            assert_eq!(x.write(&mut fp, b"abc", 0).unwrap(), 3);
+            assert_eq!(x.write(&mut fp, b"def", 3).unwrap(), 3);
+            x.fsync(&fp).unwrap();
+            let mut fp = x.open(&c, libc::O_RDONLY).unwrap();
+            let mut buf = [0; 4];
+            assert_eq!(x.read(&mut fp, &mut buf, 0).unwrap(), 4);
+            assert_eq!(&buf, b"abcd");
+            assert_eq!(x.read(&mut fp, &mut buf, 4).unwrap(), 2);
+            assert_eq!(&buf[..2], b"ef");
+            x.truncate(&fp, 1).unwrap_err(); // read only
+            drop(fp);
+
+            for f in [&a, &b, &c] {
+                x.unlink(f, x.lstat(f).unwrap()).unwrap();
+            }
+            x.unlink(&d, crate::util::FileType::Dir).unwrap();
+            assert!(x.lstat(&d).is_err());
+        }
+    }
+
+    #[test]
+    fn test_read_write_file() {
+        let f = std::env::temp_dir().join(format!(
+            "dirload_test_read_write_file_{}",
+            std::process::id()
+        ));
+        let mut opt = crate::Opt {
+            io_depth: 2,
+            ..Default::default()
+        };
+        for x in [
+            crate::dir::IoEngine::Std,
+            crate::dir::IoEngine::Mmap,
+            crate::dir::IoEngine::Uring,
+        ] {
+            opt.io_engine = x;
+            let e = super::new_engine(&opt);
+            std::fs::File::create(&f).unwrap();
+
+            // write, then append as in aging
+            let mut thr = crate::worker::Thread::default();
+            thr.dir = crate::dir::ThreadDir::newwrite(4);
+            if let Err(err) = e.init_thread(&mut thr) {
+                // io_uring may be disabled, e.g. by seccomp
+                println!("{err}");
+                continue;
+            }
+            e.write_file(&f, 0, 10, &mut thr, &[], &opt).unwrap();
+            e.write_file(&f, 10, 5, &mut thr, &[], &opt).unwrap();
+            assert_eq!(std::fs::read(&f).unwrap(), vec![0x41; 15]);
+            assert_eq!(thr.stat.get_num_write_bytes(), 15);
+
+            // until EOF and up to residual in a batch
+            let mut thr = crate::worker::Thread::default();
+            thr.dir = crate::dir::ThreadDir::newread(4);
+            e.init_thread(&mut thr).unwrap();
+            let l = e.lstat_batch(std::slice::from_ref(&f), &mut thr).unwrap();
+            assert!(l[0].is_reg());
+            e.read_files(&[(f.clone(), -1), (f.clone(), 6)], &mut thr, &opt)
+                .unwrap();
+            assert_eq!(thr.stat.get_num_read_bytes(), 21);
+        }
+        std::fs::remove_file(&f).unwrap();
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use rand::Rng;
+use rand::SeedableRng;
+
+use crate::dir;
+use crate::engine;
+use crate::pace;
+use crate::util;
+
+// operations faults can be injected into, "all" matches any of them
+const FAULT_OPS: [&str; 13] = [
+    "all", "lstat", "stat", "readlink", "readdir", "open", "read", "write", "truncate", "fsync",
+    "create", "unlink", "xattr",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) enum FaultKind {
+    Eio,
+    Enospc,
+    Eintr,
+    Delay(std::time::Duration), // then issue the operation
+    Partial,                    // write less than requested
+}
+
+impl FaultKind {
+    fn get_name(&self) -> &'static str {
+        match self {
+            FaultKind::Eio => "eio",
+            FaultKind::Enospc => "enospc",
+            FaultKind::Eintr => "eintr",
+            FaultKind::Delay(..) => "delay",
+            FaultKind::Partial => "partial",
+        }
+    }
+
+    fn get_errno(&self) -> Option<i32> {
+        match self {
+            FaultKind::Eio => Some(libc::EIO),
+            FaultKind::Enospc => Some(libc::ENOSPC),
+            FaultKind::Eintr => Some(libc::EINTR),
+            FaultKind::Delay(..) | FaultKind::Partial => None,
+        }
+    }
+}
+
+// e.g. "write:enospc:0.01", "read:delay=5ms:0.5", "write:partial:1"
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Fault {
+    pub(crate) op: String,
+    pub(crate) kind: FaultKind,
+    pub(crate) probability: f64,
+}
+
+impl Fault {
+    fn is_target(&self, op: &str) -> bool {
+        self.op == "all" || self.op == op
+    }
+}
+
+pub(crate) fn parse_fault(s: &str) -> std::io::Result<Fault> {
+    let l: Vec<&str> = s.split(':').collect();
+    if l.len() != 3 || !FAULT_OPS.contains(&l[0]) {
+        return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
+    }
+    let kind = match l[1] {
+        "eio" => FaultKind::Eio,
+        "enospc" => FaultKind::Enospc,
+        "eintr" => FaultKind::Eintr,
+        "partial" if l[0] == "write" => FaultKind::Partial,
+        x => match x.strip_prefix("delay=") {
+            Some(v) => FaultKind::Delay(pace::parse_duration(v)?),
+            None => return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+        },
+    };
+    let probability = match l[2].parse::<f64>() {
+        Ok(v) if (0.0..=1.0).contains(&v) => v,
+        _ => return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+    };
+    Ok(Fault {
+        op: l[0].to_string(),
+        kind,
+        probability,
+    })
+}
+
+pub(crate) fn print_faults(m: &BTreeMap<String, usize>) {
+    println!("Injected faults");
+    if m.is_empty() {
+        println!("  none");
+    }
+    for (k, n) in m {
+        println!("  {k} {n}");
+    }
+}
+
+// issues operations of inner engine unless a fault fires, random numbers
+// come from one generator, so a seed reproduces faults of a single thread
+#[derive(Debug)]
+pub(crate) struct FaultEngine {
+    inner: Box<dyn engine::Engine>,
+    faults: Vec<Fault>,
+    rng: std::sync::Mutex<rand::rngs::StdRng>,
+    counts: std::sync::Mutex<BTreeMap<String, usize>>, // by "<op> <kind>"
+}
+
+impl FaultEngine {
+    pub(crate) fn new(inner: Box<dyn engine::Engine>, faults: &[Fault], seed: u64) -> Self {
+        Self {
+            inner,
+            faults: faults.to_vec(),
+            rng: std::sync::Mutex::new(rand::rngs::StdRng::seed_from_u64(seed)),
+            counts: std::sync::Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    // returns error of a fired fault, or whether to write partially
+    fn inject(&self, op: &'static str) -> std::io::Result<bool> {
+        let mut partial = false;
+        for x in self.faults.iter().filter(|x| x.is_target(op)) {
+            if !self.rng.lock().unwrap().gen_bool(x.probability) {
+                continue;
+            }
+            *self
+                .counts
+                .lock()
+                .unwrap()
+                .entry(format!("{op} {}", x.kind.get_name()))
+                .or_insert(0) += 1;
+            if let Some(v) = x.kind.get_errno() {
+                return Err(std::io::Error::from_raw_os_error(v));
+            }
+            match x.kind {
+                FaultKind::Delay(d) => std::thread::sleep(d),
+                FaultKind::Partial => partial = true,
+                _ => unreachable!(),
+            }
+        }
+        Ok(partial)
+    }
+}
+
+impl engine::Engine for FaultEngine {
+    fn get_name(&self) -> &'static str {
+        "fault"
+    }
+
+    fn get_stat(&self) -> engine::Stat {
+        self.inner.get_stat()
+    }
+
+    fn lstat(&self, f: &Path) -> std::io::Result<util::FileType> {
+        self.inject("lstat")?;
+        self.inner.lstat(f)
+    }
+
+    fn stat(&self, f: &Path) -> std::io::Result<util::FileType> {
+        self.inject("stat")?;
+        self.inner.stat(f)
+    }
+
+    fn lmetadata(&self, f: &Path) -> std::io::Result<std::fs::Metadata> {
+        self.inject("lstat")?;
+        self.inner.lmetadata(f)
+    }
+
+    fn readlink(&self, f: &Path) -> std::io::Result<PathBuf> {
+        self.inject("readlink")?;
+        self.inner.readlink(f)
+    }
+
+    fn readdir(&self, d: &Path) -> std::io::Result<Vec<PathBuf>> {
+        self.inject("readdir")?;
+        self.inner.readdir(d)
+    }
+
+    fn open(&self, f: &Path, flags: i32) -> std::io::Result<std::fs::File> {
+        self.inject("open")?;
+        self.inner.open(f, flags)
+    }
+
+    fn read(&self, fp: &mut std::fs::File, b: &mut [u8], off: u64) -> std::io::Result<usize> {
+        self.inject("read")?;
+        self.inner.read(fp, b, off)
+    }
+
+    // at least 1 byte, so writers make progress
+    fn write(&self, fp: &mut std::fs::File, b: &[u8], off: u64) -> std::io::Result<usize> {
+        if self.inject("write")? && b.len() > 1 {
+            let n = self.rng.lock().unwrap().gen_range(1..b.len());
+            return self.inner.write(fp, &b[..n], off);
+        }
+        self.inner.write(fp, b, off)
+    }
+
+    fn is_mmap(&self) -> bool {
+        self.inner.is_mmap()
+    }
+
+    // mapping counts as a read or write, partial is ignored
+    fn mmap(
+        &self,
+        fp: &std::fs::File,
+        len: usize,
+        writable: bool,
+    ) -> std::io::Result<engine::Mmap> {
+        self.inject(if writable { "write" } else { "read" })?;
+        self.inner.mmap(fp, len, writable)
+    }
+
+    fn truncate(&self, fp: &std::fs::File, len: u64) -> std::io::Result<()> {
+        self.inject("truncate")?;
+        self.inner.truncate(fp, len)
+    }
+
+    fn fsync(&self, fp: &std::fs::File) -> std::io::Result<()> {
+        self.inject("fsync")?;
+        self.inner.fsync(fp)
+    }
+
+    fn create(&self, oldf: &Path, newf: &Path, t: dir::WritePathsType) -> std::io::Result<()> {
+        self.inject("create")?;
+        self.inner.create(oldf, newf, t)
+    }
+
+    fn unlink(&self, f: &Path, t: util::FileType) -> std::io::Result<()> {
+        self.inject("unlink")?;
+        self.inner.unlink(f, t)
+    }
+
+    fn listxattr(&self, f: &Path) -> std::io::Result<Vec<std::ffi::OsString>> {
+        self.inject("xattr")?;
+        self.inner.listxattr(f)
+    }
+
+    fn getxattr(&self, f: &Path, name: &std::ffi::OsStr, b: &mut [u8]) -> std::io::Result<usize> {
+        self.inject("xattr")?;
+        self.inner.getxattr(f, name, b)
+    }
+
+    fn setxattr(&self, f: &Path, name: &std::ffi::OsStr, b: &[u8]) -> std::io::Result<()> {
+        self.inject("xattr")?;
+        self.inner.setxattr(f, name, b)
+    }
+
+    fn get_faults(&self) -> Option<BTreeMap<String, usize>> {
+        Some(self.counts.lock().unwrap().clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::Engine;
+
+    #[test]
+    fn test_parse_fault() {
+        let fault_list = [
+            ("write:enospc:0.01", "write", super::FaultKind::Enospc, 0.01),
+            ("all:eio:1", "all", super::FaultKind::Eio, 1.0),
+            ("unlink:eintr:0", "unlink", super::FaultKind::Eintr, 0.0),
+            (
+                "read:delay=5ms:0.5",
+                "read",
+                super::FaultKind::Delay(std::time::Duration::from_millis(5)),
+                0.5,
+            ),
+            ("write:partial:1", "write", super::FaultKind::Partial, 1.0),
+        ];
+        for x in &fault_list {
+            match super::parse_fault(x.0) {
+                Ok(v) => {
+                    assert_eq!(v.op, x.1, "{x:?}");
+                    assert_eq!(v.kind, x.2, "{x:?}");
+                    assert_eq!(v.probability, x.3, "{x:?}");
+                }
+                Err(e) => panic!("{e} {x:?}"),
+            }
+        }
+        let invalid_list = [
+            "",
+            "write",
+            "write:eio",
+            "write:eio:",
+            "write:eio:1.5",
+            "write:eio:-1",
+            "write:eio:1:1",
+            "xxx:eio:1",
+            "write:xxx:1",
+            "read:partial:1",
+            "all:partial:1",
+            "read:delay=5:1",
+        ];
+        for s in &invalid_list {
+            assert!(super::parse_fault(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_fault_engine() {
+        let d = std::env::temp_dir().join(format!("dirload_test_fault_{}", std::process::id()));
+        let f = d.join("a");
+        let faults = [
+            super::parse_fault("create:enospc:1").unwrap(),
+            super::parse_fault("unlink:eintr:0").unwrap(),
+            super::parse_fault("write:partial:1").unwrap(),
+            super::parse_fault("fsync:delay=1ms:1").unwrap(),
+        ];
+        let x = super::FaultEngine::new(Box::new(crate::engine::StdEngine::default()), &faults, 0);
+        assert_eq!(
+            x.create(&d, &d, crate::dir::WritePathsType::Dir)
+                .unwrap_err()
+                .raw_os_error(),
+            Some(libc::ENOSPC)
+        );
+        assert!(x.lstat(&d).is_err()); // not created
+
+        let y = crate::engine::StdEngine::default();
+        y.create(&d, &d, crate::dir::WritePathsType::Dir).unwrap();
+        y.create(&d, &f, crate::dir::WritePathsType::Reg).unwrap();
+        let mut fp = x.open(&f, libc::O_WRONLY).unwrap();
+        let n = x.write(&mut fp, &[0x41; 100], 0).unwrap();
+        assert!(n > 0 && n < 100, "{n}");
+        assert_eq!(x.write(&mut fp, &[0x41], 0).unwrap(), 1); // no progress otherwise
+        let t = std::time::Instant::now();
+        x.fsync(&fp).unwrap();
+        assert!(t.elapsed() >= std::time::Duration::from_millis(1));
+        x.unlink(&f, crate::util::FileType::Reg).unwrap();
+        x.unlink(&d, crate::util::FileType::Dir).unwrap();
+
+        let m = x.get_faults().unwrap();
+        let l: Vec<(&str, usize)> = m.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        assert_eq!(
+            l,
+            vec![
+                ("create enospc", 1),
+                ("fsync delay", 1),
+                ("write partial", 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fault_engine_seed() {
+        let faults = [super::parse_fault("all:eio:0.5").unwrap()];
+        let f = std::env::temp_dir();
+        let mut prev = None;
+        for _ in 0..2 {
+            let x = super::FaultEngine::new(
+                Box::new(crate::engine::StdEngine::default()),
+                &faults,
+                123,
+            );
+            let l: Vec<bool> = (0..64).map(|_| x.lstat(&f).is_ok()).collect();
+            assert!(l.contains(&true) && l.contains(&false), "{l:?}");
+            if let Some(prev) = prev {
+                assert_eq!(l, prev); // same seed, same faults
+            }
+            prev = Some(l);
+        }
+    }
+}
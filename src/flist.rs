@@ -1,15 +1,19 @@
 use std::io::BufRead;
 use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::ffi::OsStringExt;
+use std::path::Path;
+use std::path::PathBuf;
 
 use crate::util;
 
-pub(crate) fn init_flist(input: &str, ignore_dot: bool) -> std::io::Result<Vec<String>> {
+pub(crate) fn init_flist(input: &Path, ignore_dot: bool) -> std::io::Result<Vec<PathBuf>> {
     let mut l = vec![];
     for entry in walkdir::WalkDir::new(input)
         .into_iter()
         .filter_map(std::result::Result::ok)
     {
-        let f = util::parse_walkdir_entry(&entry)?;
+        let f = entry.path();
         let t = util::get_raw_file_type(f)?;
 
         // ignore . entries if specified
@@ -17,62 +21,194 @@ pub(crate) fn init_flist(input: &str, ignore_dot: bool) -> std::io::Result<Vec<S
             continue;
         }
         match t {
-            util::FileType::Reg | util::FileType::Symlink => l.push(f.to_string()),
+            util::FileType::Reg | util::FileType::Symlink => l.push(f.to_path_buf()),
             util::FileType::Dir | util::FileType::Device | util::FileType::Unsupported => (),
         }
     }
     Ok(l)
 }
 
-pub(crate) fn load_flist_file(flist_file: &str) -> std::io::Result<Vec<String>> {
+// Paths are stored one per line, with '\', '\n', '\r' and bytes which are
+// not part of valid UTF-8 sequences escaped, so that any path round-trips.
+pub(crate) fn encode_flist_path(f: &Path) -> String {
+    let mut s = String::new();
+    for chunk in f.as_os_str().as_bytes().utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\\' => s.push_str("\\\\"),
+                '\n' => s.push_str("\\n"),
+                '\r' => s.push_str("\\r"),
+                c => s.push(c),
+            }
+        }
+        for x in chunk.invalid() {
+            s.push_str(&format!("\\x{x:02x}"));
+        }
+    }
+    s
+}
+
+pub(crate) fn decode_flist_path(s: &str) -> std::io::Result<PathBuf> {
+    let mut b = vec![];
+    let mut l = s.as_bytes().iter();
+    while let Some(&x) = l.next() {
+        if x != b'\\' {
+            b.push(x);
+            continue;
+        }
+        match l.next() {
+            Some(b'\\') => b.push(b'\\'),
+            Some(b'n') => b.push(b'\n'),
+            Some(b'r') => b.push(b'\r'),
+            Some(b'x') => {
+                let h = [
+                    *l.next().ok_or_else(invalid_flist_line)?,
+                    *l.next().ok_or_else(invalid_flist_line)?,
+                ];
+                let h = std::str::from_utf8(&h).map_err(|_| invalid_flist_line())?;
+                b.push(u8::from_str_radix(h, 16).map_err(|_| invalid_flist_line())?);
+            }
+            _ => return Err(invalid_flist_line()),
+        }
+    }
+    Ok(PathBuf::from(std::ffi::OsString::from_vec(b)))
+}
+
+fn invalid_flist_line() -> std::io::Error {
+    std::io::Error::from(std::io::ErrorKind::InvalidData)
+}
+
+pub(crate) fn load_flist_file(flist_file: &Path) -> std::io::Result<Vec<PathBuf>> {
     let mut fl = vec![];
     let fp = std::fs::File::open(flist_file)?;
     for s in std::io::BufReader::new(fp).lines() {
         match s {
-            Ok(v) => fl.push(v),
+            Ok(v) => fl.push(decode_flist_path(&v)?),
             Err(e) => return Err(e),
         }
     }
     Ok(fl)
 }
 
-pub(crate) fn create_flist_file(
-    input: &[String],
-    flist_file: &str,
-    ignore_dot: bool,
-    force: bool,
-) -> std::io::Result<()> {
+// existing flist file is removed only if forced
+pub(crate) fn remove_flist_file(flist_file: &Path, force: bool) -> std::io::Result<()> {
     if util::path_exists_or_error(flist_file).is_ok() {
         if force {
             match std::fs::remove_file(flist_file) {
-                Ok(()) => println!("Removed {flist_file}"),
+                Ok(()) => println!("Removed {}", flist_file.display()),
                 Err(e) => return Err(e),
             }
         } else {
             return Err(std::io::Error::from(std::io::ErrorKind::AlreadyExists));
         }
     }
+    Ok(())
+}
+
+pub(crate) fn create_flist_file(
+    input: &[PathBuf],
+    flist_file: &Path,
+    ignore_dot: bool,
+    force: bool,
+) -> std::io::Result<()> {
+    remove_flist_file(flist_file, force)?;
 
     let mut fl = vec![];
     for f in input {
         match init_flist(f, ignore_dot) {
             Ok(v) => {
-                println!("{} files scanned from {}", v.len(), f);
-                for s in &v {
-                    fl.push(s.to_string());
+                println!("{} files scanned from {}", v.len(), f.display());
+                for s in v {
+                    fl.push(s);
                 }
             }
             Err(e) => return Err(e),
         }
     }
     fl.sort();
+    write_flist_file(&fl, flist_file)
+}
 
-    let fp = std::fs::File::create(flist_file)?;
+pub(crate) fn write_flist_file(fl: &[PathBuf], flist_file: &Path) -> std::io::Result<()> {
+    write_flist(fl, std::fs::File::create(flist_file)?)
+}
+
+pub(crate) fn append_flist_file(fl: &[PathBuf], flist_file: &Path) -> std::io::Result<()> {
+    let fp = std::fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(flist_file)?;
+    write_flist(fl, fp)
+}
+
+fn write_flist(fl: &[PathBuf], fp: std::fs::File) -> std::io::Result<()> {
     let mut writer = std::io::BufWriter::new(fp);
-    for s in &fl {
+    for s in fl {
         assert!(util::is_abspath(s));
-        writeln!(writer, "{s}")?;
+        writeln!(writer, "{}", encode_flist_path(s))?;
     }
     writer.flush()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::ffi::OsStrExt;
+
+    #[test]
+    fn test_flist_path_round_trip() {
+        let path_list: [&[u8]; 8] = [
+            b"/path/to/xxx",
+            b"/path/to/x x",
+            b"/path/to/\\xxx",
+            b"/path/to/\\x41",
+            b"/path/to/x\nx",
+            b"/path/to/x\rx\n",
+            b"/path/to/\xff\xfe",
+            b"/path/to/\xe3\x81\x82\xe3\x81",
+        ];
+        for b in &path_list {
+            let f = std::path::Path::new(std::ffi::OsStr::from_bytes(b));
+            let s = super::encode_flist_path(f);
+            assert!(!s.contains('\n'), "{s}");
+            assert!(!s.contains('\r'), "{s}");
+            match super::decode_flist_path(&s) {
+                Ok(v) => assert_eq!(v, f, "{s}"),
+                Err(e) => panic!("{e} {s}"),
+            }
+        }
+
+        let invalid_list = ["\\", "/path/to/\\", "/path/to/\\y", "/path/to/\\x4"];
+        for s in &invalid_list {
+            assert!(super::decode_flist_path(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_write_flist_file() {
+        let f = std::env::temp_dir().join(format!("dirload_test_flist_{}", std::process::id()));
+        let fl = vec![
+            std::path::PathBuf::from("/path/to/a"),
+            std::path::PathBuf::from("/path/to/b\nb"),
+            std::path::PathBuf::from(std::ffi::OsStr::from_bytes(b"/path/to/\xff")),
+        ];
+        super::write_flist_file(&fl, &f).unwrap();
+        assert_eq!(super::load_flist_file(&f).unwrap(), fl);
+        super::append_flist_file(&fl[..1], &f).unwrap();
+        assert_eq!(
+            super::load_flist_file(&f).unwrap(),
+            [&fl[..], &fl[..1]].concat()
+        );
+        super::write_flist_file(&[], &f).unwrap(); // overwritten
+        assert!(super::load_flist_file(&f).unwrap().is_empty());
+
+        // existing file is removed only if forced
+        assert_eq!(
+            super::remove_flist_file(&f, false).unwrap_err().kind(),
+            std::io::ErrorKind::AlreadyExists
+        );
+        super::remove_flist_file(&f, true).unwrap();
+        assert!(crate::util::path_exists_or_error(&f).is_err());
+        super::remove_flist_file(&f, false).unwrap();
+    }
+}
@@ -1,42 +1,99 @@
+mod capacity;
+mod compare;
 mod dir;
+mod engine;
+mod fault;
 mod flist;
+mod metrics;
+mod pace;
+mod param;
+mod phase;
+mod remote;
+mod report;
+mod sched;
 mod stat;
+mod tui;
+mod uring;
 mod util;
 mod worker;
 
+use std::os::unix::ffi::OsStrExt;
+
 const VERSION: [i32; 3] = [0, 4, 0];
 
 const DIRLOAD_HOME: &str = "DIRLOAD_HOME";
 
-#[derive(Debug)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 struct Opt {
     num_set: usize,
+    warmup_set: bool,
+    set_param: Option<param::ParamList>,
+    #[serde(default)]
+    set_index: usize, // of current set including warm-up, not an option
     num_reader: usize,
     num_writer: usize,
+    num_process: usize,
     num_repeat: isize,
     time_minute: u64,
     time_second: u64,
+    phase: Vec<phase::Phase>,
+    phase_loop: bool,
+    think_time: Option<pace::ThinkTime>,
+    burst: Option<pace::Burst>,
+    arrival_rate: f64,
+    max_backlog: isize,
     monitor_int_minute: u64,
-    monitor_int_second: u64,
+    monitor_int_second: f64,
+    monitor_log: String,
+    metrics_address: String,
+    tui: bool,
     stat_only: bool,
     ignore_dot: bool,
     follow_symlink: bool,
+    xattr_read: bool,
     read_buffer_size: usize,
     read_size: isize,
     write_buffer_size: usize,
     write_size: isize,
     random_write_data: bool,
+    num_xattr: usize,
+    xattr_size: usize,
+    io_engine: dir::IoEngine,
+    mmap_access: dir::MmapAccess,
+    io_depth: usize,
+    stat_engine: dir::StatEngine,
+    statx_mask: dir::StatxMask,
+    statx_sync: dir::StatxSync,
+    dirfd: bool,
     num_write_paths: isize,
     truncate_write_paths: bool,
     fsync_write_paths: bool,
     dirsync_write_paths: bool,
+    min_free_space: f64,
+    min_free_inodes: f64,
+    capacity_action: capacity::CapacityAction,
+    fill_utilization: f64,
+    age_bytes: usize,
+    age_factor: f64,
+    age_flist_file: String,
     keep_write_paths: bool,
     clean_write_paths: bool,
     write_paths_base: String,
     write_paths_type: Vec<dir::WritePathsType>,
+    write_paths_name: dir::WritePathsName,
+    write_paths_max_depth: isize,
+    write_paths_shape: dir::WritePathsShape,
     path_iter: worker::PathIter,
+    reader_sched: sched::ThreadSched,
+    writer_sched: sched::ThreadSched,
     flist_file: String,
     flist_file_create: bool,
+    result_file: String,
+    server: String,
+    controller: Vec<String>,
+    dry_run: bool,
+    fault: Vec<fault::Fault>,
+    fault_seed: u64,
     force: bool,
     verbose: bool,
     debug: bool,
@@ -46,32 +103,73 @@ impl Default for Opt {
     fn default() -> Opt {
         Opt {
             num_set: 1,
+            warmup_set: false,
+            set_param: None,
+            set_index: 0,
             num_reader: 0,
             num_writer: 0,
+            num_process: 1,
             num_repeat: -1,
             time_minute: 0,
             time_second: 0,
+            phase: vec![],
+            phase_loop: false,
+            think_time: None,
+            burst: None,
+            arrival_rate: 0.0,
+            max_backlog: -1,
             monitor_int_minute: 0,
-            monitor_int_second: 0,
+            monitor_int_second: 0.0,
+            monitor_log: String::new(),
+            metrics_address: String::new(),
+            tui: false,
             stat_only: false,
             ignore_dot: false,
             follow_symlink: false,
+            xattr_read: false,
             read_buffer_size: 1 << 16,
             read_size: -1,
             write_buffer_size: 1 << 16,
             write_size: -1,
             random_write_data: false,
+            num_xattr: 0,
+            xattr_size: 64,
+            io_engine: dir::IoEngine::Std,
+            mmap_access: dir::MmapAccess::Sequential,
+            io_depth: 1,
+            stat_engine: dir::StatEngine::Std,
+            statx_mask: dir::StatxMask::Basic,
+            statx_sync: dir::StatxSync::AsStat,
+            dirfd: false,
             num_write_paths: 1 << 10,
             truncate_write_paths: false,
             fsync_write_paths: false,
             dirsync_write_paths: false,
+            min_free_space: 0.0,
+            min_free_inodes: 0.0,
+            capacity_action: capacity::CapacityAction::Stop,
+            fill_utilization: 0.0,
+            age_bytes: 0,
+            age_factor: 0.0,
+            age_flist_file: String::new(),
             keep_write_paths: false,
             clean_write_paths: false,
             write_paths_base: "x".to_string(),
             write_paths_type: vec![dir::WritePathsType::Dir, dir::WritePathsType::Reg],
+            write_paths_name: dir::WritePathsName::Default,
+            write_paths_max_depth: -1,
+            write_paths_shape: dir::WritePathsShape::Mirror,
             path_iter: worker::PathIter::Ordered,
+            reader_sched: sched::ThreadSched::default(),
+            writer_sched: sched::ThreadSched::default(),
             flist_file: String::new(),
             flist_file_create: false,
+            result_file: String::new(),
+            server: String::new(),
+            controller: vec![],
+            dry_run: false,
+            fault: vec![],
+            fault_seed: 0,
             force: false,
             verbose: false,
             debug: false,
@@ -90,23 +188,35 @@ fn print_version() {
 fn usage(progname: &str, opts: &getopts::Options) {
     print!(
         "{}",
-        opts.usage(&format!("usage: {progname} [<options>] <paths>"))
+        opts.usage(&format!(
+            "usage: {progname} [<options>] <paths>\n       {progname} compare [<options>] <result_files>"
+        ))
     );
 }
 
 fn init_file_logger(progname: &str) -> Result<(), log::SetLoggerError> {
     let home = util::get_home_path();
-    let name = format!(".{}.log", util::get_basename(progname).unwrap());
-    let f = match std::env::var(DIRLOAD_HOME) {
-        Ok(v) => {
-            if util::is_dir(&v) {
-                util::join_path(&v, &name)
+    let name = format!(
+        ".{}.log",
+        util::get_basename(std::path::Path::new(progname))
+            .unwrap()
+            .to_string_lossy()
+    );
+    let name = std::path::Path::new(&name);
+    let f = match std::env::var_os(DIRLOAD_HOME) {
+        Some(v) => {
+            let v = std::path::Path::new(&v);
+            if util::is_dir(v) {
+                util::join_path(v, name)
             } else {
-                println!("{DIRLOAD_HOME} not a directory, using {home} instead");
-                util::join_path(&home, &name)
+                println!(
+                    "{DIRLOAD_HOME} not a directory, using {} instead",
+                    home.display()
+                );
+                util::join_path(&home, name)
             }
         }
-        Err(_) => util::join_path(&home, &name),
+        None => util::join_path(&home, name),
     };
     simplelog::CombinedLogger::init(vec![simplelog::WriteLogger::new(
         simplelog::LevelFilter::Trace,
@@ -128,14 +238,60 @@ fn is_interrupted() -> bool {
     unsafe { INTERRUPTED }
 }
 
+fn set_interrupted(v: bool) {
+    unsafe {
+        INTERRUPTED = v;
+    }
+}
+
+static mut PAUSED: bool = false;
+
+fn is_paused() -> bool {
+    unsafe { PAUSED }
+}
+
+fn set_paused(v: bool) {
+    unsafe {
+        PAUSED = v;
+    }
+}
+
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    // getopts takes UTF-8 only, so other arguments are passed as placeholders
+    // which can't appear in argv, and restored if input directories
+    let args_os: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    let args: Vec<String> = args_os
+        .iter()
+        .enumerate()
+        .map(|(i, x)| x.to_str().map_or_else(|| format!("\0{i}"), str::to_string))
+        .collect();
     let progname = &args[0];
 
+    if args.len() > 1 && args[1] == "compare" {
+        compare::run(progname, &args[2..]);
+    }
+
     let mut opts = getopts::Options::new();
     opts.optopt("", "num_set", "Number of sets to run (default 1)", "<uint>");
+    opts.optflag(
+        "",
+        "warmup_set",
+        "Run an extra set first and exclude it from statistics across sets",
+    );
+    opts.optopt(
+        "",
+        "set_param",
+        "Vary parameter across sets, one value per set unless repeated, e.g. num_reader:1,2,4,8 or num_reader:1..64*2:3 for 3 sets per value",
+        "<name>:<values>",
+    );
     opts.optopt("", "num_reader", "Number of reader threads", "<uint>");
     opts.optopt("", "num_writer", "Number of writer threads", "<uint>");
+    opts.optopt(
+        "",
+        "num_process",
+        "Number of worker processes to distribute threads if > 1 (default 1)",
+        "<uint>",
+    );
     opts.optopt(
         "",
         "num_repeat",
@@ -154,6 +310,41 @@ fn main() {
         "Exit threads after sum of this and --time_minute option if > 0",
         "<uint>",
     );
+    opts.optmulti(
+        "",
+        "phase",
+        "Add phase of specified seconds with thread counts and parameters, e.g. 60:num_writer=2,fsync_write_paths=1",
+        "<sec>[:<name>=<value>,...]",
+    );
+    opts.optflag(
+        "",
+        "phase_loop",
+        "Repeat phases until threads exit, otherwise exit after last phase",
+    );
+    opts.optopt(
+        "",
+        "think_time",
+        "Sleep between operations of each thread, e.g. 5ms, exp:5ms (mean), uniform:1ms-10ms",
+        "[fixed:|exp:|uniform:]<duration>[-<duration>]",
+    );
+    opts.optopt(
+        "",
+        "burst",
+        "Alternate threads between active and idle periods, e.g. 100ms:900ms",
+        "<on>:<off>",
+    );
+    opts.optopt(
+        "",
+        "arrival_rate",
+        "Process paths of each thread at fixed rate per second regardless of completion if > 0, latency includes delay from scheduled start",
+        "<float>",
+    );
+    opts.optopt(
+        "",
+        "max_backlog",
+        "Drop paths overdue beyond this number with --arrival_rate if >= 0 (default -1)",
+        "<int>",
+    );
     opts.optopt(
         "",
         "monitor_interval_minute",
@@ -163,8 +354,25 @@ fn main() {
     opts.optopt(
         "",
         "monitor_interval_second",
-        "Monitor threads every sum of this and --monitor_interval_minute option if > 0",
-        "<uint>",
+        "Monitor threads every sum of this and --monitor_interval_minute option if > 0, fractional seconds allowed",
+        "<float>",
+    );
+    opts.optopt(
+        "",
+        "monitor_log",
+        "Write per interval ops/sec and MiB/sec of threads of all sets to specified CSV file",
+        "<string>",
+    );
+    opts.optopt(
+        "",
+        "metrics_address",
+        "Serve OpenMetrics of threads over HTTP on specified address, e.g. 127.0.0.1:9100",
+        "<string>",
+    );
+    opts.optflag(
+        "",
+        "tui",
+        "Show live dashboard every monitor interval, [p] to pause/resume, [s] to save snapshot, [q] to interrupt",
     );
     opts.optflag("", "stat_only", "Do not read file data");
     opts.optflag("", "ignore_dot", "Ignore entries start with .");
@@ -173,6 +381,11 @@ fn main() {
         "follow_symlink",
         "Follow symbolic links for read unless directory",
     );
+    opts.optflag(
+        "",
+        "xattr_read",
+        "List and get extended attributes of entries for read",
+    );
     opts.optopt(
         "",
         "read_buffer_size",
@@ -198,6 +411,59 @@ fn main() {
         "<int>",
     );
     opts.optflag("", "random_write_data", "Use pseudo random write data");
+    opts.optopt(
+        "",
+        "num_xattr",
+        "Number of extended attributes writers set on each write path except symlink (default 0)",
+        "<uint>",
+    );
+    opts.optopt(
+        "",
+        "xattr_size",
+        "Extended attribute value size (default 64)",
+        "<uint>",
+    );
+    opts.optopt(
+        "",
+        "io_engine",
+        "I/O engine for file data [std|pread|mmap|uring] (default std)",
+        "<string>",
+    );
+    opts.optopt(
+        "",
+        "mmap_access",
+        "Page access order for mmap I/O engine [sequential|random] (default sequential)",
+        "<string>",
+    );
+    opts.optopt(
+        "",
+        "io_depth",
+        "Queue depth per thread for uring I/O engine, readers batch this many paths (default 1)",
+        "<uint>",
+    );
+    opts.optopt(
+        "",
+        "stat_engine",
+        "System call to look up entries [std|statx] (default std)",
+        "<string>",
+    );
+    opts.optopt(
+        "",
+        "statx_mask",
+        "Attributes requested by statx [type|basic|all] (default basic)",
+        "<string>",
+    );
+    opts.optopt(
+        "",
+        "statx_sync",
+        "Attribute synchronization of statx [as_stat|force|dont] (default as_stat)",
+        "<string>",
+    );
+    opts.optflag(
+        "",
+        "dirfd",
+        "Look up entries by name relative to open parent directory fd instead of full path, std stat engine uses fstatat(2)",
+    );
     opts.optopt(
         "",
         "num_write_paths",
@@ -215,6 +481,48 @@ fn main() {
         "dirsync_write_paths",
         "fsync(2) parent directories of write paths",
     );
+    opts.optopt(
+        "",
+        "min_free_space",
+        "Writers stop or throttle below specified free space of input filesystem in percent (default 0)",
+        "<percent>",
+    );
+    opts.optopt(
+        "",
+        "min_free_inodes",
+        "Writers stop or throttle below specified free inodes of input filesystem in percent (default 0)",
+        "<percent>",
+    );
+    opts.optopt(
+        "",
+        "capacity_action",
+        "Action of writers below free thresholds or on ENOSPC [stop|throttle] (default stop)",
+        "<string>",
+    );
+    opts.optopt(
+        "",
+        "fill_utilization",
+        "Writers fill input filesystem up to specified utilization in percent, then unlink a write path per write",
+        "<percent>",
+    );
+    opts.optopt(
+        "",
+        "age_bytes",
+        "Writers age input filesystem by creating, growing, shrinking and unlinking write paths until each writes specified bytes (default 0)",
+        "<uint>",
+    );
+    opts.optopt(
+        "",
+        "age_factor",
+        "Writers age input filesystem until bytes written reach specified multiple of its size (default 0)",
+        "<float>",
+    );
+    opts.optopt(
+        "",
+        "age_flist_file",
+        "Record files left by aging to specified flist file",
+        "<string>",
+    );
     opts.optflag(
         "",
         "keep_write_paths",
@@ -237,14 +545,93 @@ fn main() {
         "File types for write paths [d|r|s|l] (default dr)",
         "<string>",
     );
+    opts.optopt(
+        "",
+        "write_paths_name",
+        "Name scheme for write paths [default|hex|hash|uuid|pad], or template with {gid}, {seq} and {ts} (default default)",
+        "<string>",
+    );
+    opts.optopt(
+        "",
+        "write_paths_max_depth",
+        "Do not create write paths nested in more than specified write path directories if > 0 (default -1)",
+        "<int>",
+    );
+    opts.optopt(
+        "",
+        "write_paths_shape",
+        "Shape of write paths [mirror|deep|wide|spread] (default mirror)",
+        "<string>",
+    );
     opts.optopt(
         "",
         "path_iter",
         "<paths> iteration type [walk|ordered|reverse|random] (default ordered)",
         "<string>",
     );
+    opts.optopt(
+        "",
+        "reader_cpus",
+        "CPU list to pin reader threads to, e.g. 0-3,8",
+        "<string>",
+    );
+    opts.optopt("", "reader_nice", "Nice value of reader threads", "<int>");
+    opts.optopt(
+        "",
+        "reader_ioprio",
+        "I/O scheduling class of reader threads [idle|be[:<level>]|rt[:<level>]]",
+        "<string>",
+    );
+    opts.optopt(
+        "",
+        "writer_cpus",
+        "CPU list to pin writer threads to, e.g. 0-3,8",
+        "<string>",
+    );
+    opts.optopt("", "writer_nice", "Nice value of writer threads", "<int>");
+    opts.optopt(
+        "",
+        "writer_ioprio",
+        "I/O scheduling class of writer threads [idle|be[:<level>]|rt[:<level>]]",
+        "<string>",
+    );
     opts.optopt("", "flist_file", "Path to flist file", "<string>");
     opts.optflag("", "flist_file_create", "Create flist file and exit");
+    opts.optopt(
+        "",
+        "result_file",
+        "Save results as JSON to specified file for compare",
+        "<string>",
+    );
+    opts.optopt(
+        "",
+        "server",
+        "Run as server which receives jobs on specified address, or port on 127.0.0.1, e.g. 7000, jobs are unauthenticated so bind other addresses only on trusted networks",
+        "<string>",
+    );
+    opts.optopt(
+        "",
+        "controller",
+        "Run as controller which runs the job on comma separated list of servers",
+        "<string>",
+    );
+    opts.optflag(
+        "",
+        "dry_run",
+        "Print files readers would open and write paths writers would create without reading or modifying files",
+    );
+    opts.optmulti(
+        "",
+        "fault",
+        "Inject fault into operation with probability, e.g. write:enospc:0.01, read:delay=5ms:0.5, write:partial:1",
+        "<op>:<eio|enospc|eintr|delay=<duration>|partial>:<probability>",
+    );
+    opts.optopt(
+        "",
+        "fault_seed",
+        "Seed of random numbers for fault injection (default random)",
+        "<uint>",
+    );
     opts.optflag("", "force", "Enable force mode");
     opts.optflag("", "verbose", "Enable verbose print");
     opts.optflag("", "debug", "Create debug log file under home directory");
@@ -280,6 +667,24 @@ fn main() {
             }
         };
     }
+    opt.warmup_set = matches.opt_present("warmup_set");
+    if let Some(v) = matches.opt_str("set_param") {
+        let x = match param::parse_param_list(&v) {
+            Ok(v) => v,
+            Err(e) => {
+                println!("Invalid set parameter {v}: {e}");
+                println!("parameters: {}", param::Param::get_names().join(", "));
+                std::process::exit(1);
+            }
+        };
+        // number of sets follows number of values unless specified
+        if matches.opt_present("num_set") && opt.num_set != x.values.len() {
+            println!("{} values for {} sets", x.values.len(), opt.num_set);
+            std::process::exit(1);
+        }
+        opt.num_set = x.values.len();
+        opt.set_param = Some(x);
+    }
     if let Some(v) = matches.opt_str("num_reader") {
         opt.num_reader = match v.parse() {
             Ok(v) => v,
@@ -298,6 +703,18 @@ fn main() {
             }
         };
     }
+    if let Some(v) = matches.opt_str("num_process") {
+        opt.num_process = match v.parse() {
+            Ok(v) => v,
+            Err(e) => {
+                println!("{v}: {e}");
+                std::process::exit(1);
+            }
+        };
+        if opt.num_process == 0 {
+            opt.num_process = 1;
+        }
+    }
     if let Some(v) = matches.opt_str("num_repeat") {
         opt.num_repeat = match v.parse() {
             Ok(v) => v,
@@ -347,12 +764,31 @@ fn main() {
                 std::process::exit(1);
             }
         };
+        if !opt.monitor_int_second.is_finite() || opt.monitor_int_second < 0.0 {
+            println!("Invalid monitor interval {}", opt.monitor_int_second);
+            std::process::exit(1);
+        }
     }
-    opt.monitor_int_second += opt.monitor_int_minute * 60;
+    opt.monitor_int_second += (opt.monitor_int_minute * 60) as f64;
     opt.monitor_int_minute = 0;
+    if let Some(v) = matches.opt_str("monitor_log") {
+        opt.monitor_log = v;
+    }
+    if let Some(v) = matches.opt_str("metrics_address") {
+        opt.metrics_address = v;
+    }
+    opt.tui = matches.opt_present("tui");
+    // dashboard, log and metrics are updated by monitor
+    if (opt.tui || !opt.monitor_log.is_empty() || !opt.metrics_address.is_empty())
+        && opt.monitor_int_second <= 0.0
+    {
+        opt.monitor_int_second = 1.0;
+        println!("Using monitor interval 1 second");
+    }
     opt.stat_only = matches.opt_present("stat_only");
     opt.ignore_dot = matches.opt_present("ignore_dot");
     opt.follow_symlink = matches.opt_present("follow_symlink");
+    opt.xattr_read = matches.opt_present("xattr_read");
     if let Some(v) = matches.opt_str("read_buffer_size") {
         opt.read_buffer_size = match v.parse() {
             Ok(v) => v,
@@ -410,6 +846,116 @@ fn main() {
         }
     }
     opt.random_write_data = matches.opt_present("random_write_data");
+    if let Some(v) = matches.opt_str("num_xattr") {
+        opt.num_xattr = match v.parse() {
+            Ok(v) => v,
+            Err(e) => {
+                println!("{v}: {e}");
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(v) = matches.opt_str("xattr_size") {
+        opt.xattr_size = match v.parse() {
+            Ok(v) => v,
+            Err(e) => {
+                println!("{v}: {e}");
+                std::process::exit(1);
+            }
+        };
+        if opt.xattr_size > util::MAX_XATTR_SIZE {
+            println!("Invalid xattr size {}", opt.xattr_size);
+            std::process::exit(1);
+        }
+    }
+    if let Some(v) = matches.opt_str("io_engine") {
+        opt.io_engine = match v.as_str() {
+            "std" => dir::IoEngine::Std,
+            "pread" => dir::IoEngine::Pread,
+            "mmap" => dir::IoEngine::Mmap,
+            "uring" => dir::IoEngine::Uring,
+            v => {
+                println!("Invalid I/O engine {v}");
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(v) = matches.opt_str("mmap_access") {
+        opt.mmap_access = match v.as_str() {
+            "sequential" => dir::MmapAccess::Sequential,
+            "random" => dir::MmapAccess::Random,
+            v => {
+                println!("Invalid mmap access {v}");
+                std::process::exit(1);
+            }
+        };
+        if !opt.io_engine.is_mmap() {
+            println!("--mmap_access requires --io_engine mmap");
+            std::process::exit(1);
+        }
+    }
+    if let Some(v) = matches.opt_str("io_depth") {
+        opt.io_depth = match v.parse() {
+            Ok(v) => v,
+            Err(e) => {
+                println!("{v}: {e}");
+                std::process::exit(1);
+            }
+        };
+        if opt.io_depth == 0 || opt.io_depth > uring::MAX_IO_DEPTH {
+            println!("Invalid I/O depth {}", opt.io_depth);
+            std::process::exit(1);
+        }
+        if !opt.io_engine.is_uring() {
+            println!("--io_depth requires --io_engine uring");
+            std::process::exit(1);
+        }
+    }
+    if let Some(v) = matches.opt_str("stat_engine") {
+        opt.stat_engine = match v.as_str() {
+            "std" => dir::StatEngine::Std,
+            "statx" => dir::StatEngine::Statx,
+            v => {
+                println!("Invalid stat engine {v}");
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(v) = matches.opt_str("statx_mask") {
+        opt.statx_mask = match v.as_str() {
+            "type" => dir::StatxMask::Type,
+            "basic" => dir::StatxMask::Basic,
+            "all" => dir::StatxMask::All,
+            v => {
+                println!("Invalid statx mask {v}");
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(v) = matches.opt_str("statx_sync") {
+        opt.statx_sync = match v.as_str() {
+            "as_stat" => dir::StatxSync::AsStat,
+            "force" => dir::StatxSync::Force,
+            "dont" => dir::StatxSync::Dont,
+            v => {
+                println!("Invalid statx sync {v}");
+                std::process::exit(1);
+            }
+        };
+    }
+    if (matches.opt_present("statx_mask") || matches.opt_present("statx_sync"))
+        && !opt.stat_engine.is_statx()
+        && !opt.io_engine.is_uring()
+    {
+        println!("--statx_mask and --statx_sync require --stat_engine statx");
+        std::process::exit(1);
+    }
+    opt.dirfd = matches.opt_present("dirfd");
+    // uring I/O engine looks up paths on its own
+    if opt.dirfd && opt.io_engine.is_uring() {
+        println!("--dirfd unsupported with --io_engine uring");
+        std::process::exit(1);
+    }
     if let Some(v) = matches.opt_str("num_write_paths") {
         opt.num_write_paths = match v.parse() {
             Ok(v) => v,
@@ -425,8 +971,85 @@ fn main() {
     opt.truncate_write_paths = matches.opt_present("truncate_write_paths");
     opt.fsync_write_paths = matches.opt_present("fsync_write_paths");
     opt.dirsync_write_paths = matches.opt_present("dirsync_write_paths");
+    for (k, x) in [
+        ("min_free_space", &mut opt.min_free_space),
+        ("min_free_inodes", &mut opt.min_free_inodes),
+        ("fill_utilization", &mut opt.fill_utilization),
+    ] {
+        if let Some(v) = matches.opt_str(k) {
+            *x = match v.parse() {
+                Ok(v) if (0.0..=100.0).contains(&v) => v,
+                Ok(_) => {
+                    println!("Invalid {k} {v}");
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    println!("{v}: {e}");
+                    std::process::exit(1);
+                }
+            };
+        }
+    }
+    if let Some(v) = matches.opt_str("capacity_action") {
+        opt.capacity_action = match v.as_str() {
+            "stop" => capacity::CapacityAction::Stop,
+            "throttle" => capacity::CapacityAction::Throttle,
+            v => {
+                println!("Invalid capacity action {v}");
+                std::process::exit(1);
+            }
+        };
+    }
+    // fill is limited by utilization rather than count unless specified
+    if opt.fill_utilization > 0.0 && !matches.opt_present("num_write_paths") {
+        opt.num_write_paths = -1;
+        println!("Using unlimited write paths for fill");
+    }
     opt.keep_write_paths = matches.opt_present("keep_write_paths");
     opt.clean_write_paths = matches.opt_present("clean_write_paths");
+    if let Some(v) = matches.opt_str("age_bytes") {
+        opt.age_bytes = match v.parse() {
+            Ok(v) => v,
+            Err(e) => {
+                println!("{v}: {e}");
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(v) = matches.opt_str("age_factor") {
+        opt.age_factor = match v.parse() {
+            Ok(v) if v >= 0.0 => v,
+            Ok(_) => {
+                println!("Invalid age factor {v}");
+                std::process::exit(1);
+            }
+            Err(e) => {
+                println!("{v}: {e}");
+                std::process::exit(1);
+            }
+        };
+    }
+    if opt.age_bytes > 0 && opt.age_factor > 0.0 {
+        println!("--age_bytes and --age_factor are exclusive");
+        std::process::exit(1);
+    }
+    if let Some(v) = matches.opt_str("age_flist_file") {
+        opt.age_flist_file = v;
+    }
+    // aged tree is left behind for subsequent runs
+    if dir::is_aging(&opt) {
+        if !opt.keep_write_paths {
+            opt.keep_write_paths = true;
+            println!("Using aging, force --keep_write_paths");
+        }
+        if opt.num_write_paths != -1 && !matches.opt_present("num_write_paths") {
+            opt.num_write_paths = -1;
+            println!("Using unlimited write paths for aging");
+        }
+    } else if !opt.age_flist_file.is_empty() {
+        println!("--age_flist_file requires --age_bytes or --age_factor");
+        std::process::exit(1);
+    }
     if let Some(v) = matches.opt_str("write_paths_base") {
         opt.write_paths_base = match v.parse() {
             Ok(v) => v,
@@ -463,6 +1086,54 @@ fn main() {
             });
         }
     }
+    if let Some(v) = matches.opt_str("write_paths_name") {
+        opt.write_paths_name = match v.as_str() {
+            "default" => dir::WritePathsName::Default,
+            "hex" => dir::WritePathsName::Hex,
+            "hash" => dir::WritePathsName::Hash,
+            "uuid" => dir::WritePathsName::Uuid,
+            "pad" => dir::WritePathsName::Pad,
+            v => match dir::WritePathsName::new_template(v) {
+                Ok(v) => v,
+                Err(e) => {
+                    println!("Invalid write paths name {v}: {e}");
+                    std::process::exit(1);
+                }
+            },
+        };
+    }
+    if let Some(v) = matches.opt_str("write_paths_max_depth") {
+        opt.write_paths_max_depth = match v.parse() {
+            Ok(v) => v,
+            Err(e) => {
+                println!("{v}: {e}");
+                std::process::exit(1);
+            }
+        };
+        if opt.write_paths_max_depth == 0 || opt.write_paths_max_depth < -1 {
+            opt.write_paths_max_depth = -1;
+        }
+    }
+    if let Some(v) = matches.opt_str("write_paths_shape") {
+        opt.write_paths_shape = match v.as_str() {
+            "mirror" => dir::WritePathsShape::Mirror,
+            "deep" => dir::WritePathsShape::Deep,
+            "wide" => dir::WritePathsShape::Wide,
+            "spread" => dir::WritePathsShape::Spread,
+            v => {
+                println!("Invalid write paths shape {v}");
+                std::process::exit(1);
+            }
+        };
+    }
+    // deep shape descends until max depth
+    if opt.write_paths_shape.is_deep() && opt.write_paths_max_depth <= 0 {
+        opt.write_paths_max_depth = dir::DEFAULT_WRITE_PATHS_DEEP_DEPTH;
+        println!(
+            "Using max depth {} for deep write paths",
+            opt.write_paths_max_depth
+        );
+    }
     if let Some(v) = matches.opt_str("path_iter") {
         opt.path_iter = match v.as_str() {
             "walk" => worker::PathIter::Walk,
@@ -475,6 +1146,60 @@ fn main() {
             }
         };
     }
+    if let Some(v) = matches.opt_str("reader_cpus") {
+        opt.reader_sched.cpus = match sched::parse_cpu_list(&v) {
+            Ok(v) => v,
+            Err(e) => {
+                println!("Invalid CPU list {v}: {e}");
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(v) = matches.opt_str("reader_nice") {
+        opt.reader_sched.nice = match v.parse() {
+            Ok(v) => Some(v),
+            Err(e) => {
+                println!("{v}: {e}");
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(v) = matches.opt_str("reader_ioprio") {
+        opt.reader_sched.ioprio = match sched::parse_ioprio(&v) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                println!("Invalid I/O priority {v}: {e}");
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(v) = matches.opt_str("writer_cpus") {
+        opt.writer_sched.cpus = match sched::parse_cpu_list(&v) {
+            Ok(v) => v,
+            Err(e) => {
+                println!("Invalid CPU list {v}: {e}");
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(v) = matches.opt_str("writer_nice") {
+        opt.writer_sched.nice = match v.parse() {
+            Ok(v) => Some(v),
+            Err(e) => {
+                println!("{v}: {e}");
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(v) = matches.opt_str("writer_ioprio") {
+        opt.writer_sched.ioprio = match sched::parse_ioprio(&v) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                println!("Invalid I/O priority {v}: {e}");
+                std::process::exit(1);
+            }
+        };
+    }
     if let Some(v) = matches.opt_str("flist_file") {
         opt.flist_file = v;
     }
@@ -484,6 +1209,187 @@ fn main() {
         println!("Using flist, force --path_iter=ordered");
     }
     opt.flist_file_create = matches.opt_present("flist_file_create");
+    // unspecified parameters in phases follow options
+    for v in matches.opt_strs("phase") {
+        match phase::parse_phase(&v, &opt) {
+            Ok(x) => opt.phase.push(x),
+            Err(e) => {
+                println!("Invalid phase {v}: {e}");
+                println!(
+                    "parameters: {}",
+                    param::Param::get_names()
+                        .into_iter()
+                        .filter(|x| param::Param::new(x).unwrap().is_phase_param())
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+    // threads are created for max of phases, and idle when not in use
+    if !opt.phase.is_empty() {
+        opt.num_reader = opt.phase.iter().map(|x| x.num_reader).max().unwrap();
+        opt.num_writer = opt.phase.iter().map(|x| x.num_writer).max().unwrap();
+        if opt
+            .set_param
+            .iter()
+            .any(|x| matches!(x.param, param::Param::NumReader | param::Param::NumWriter))
+        {
+            println!("--phase unsupported with varying thread counts");
+            std::process::exit(1);
+        }
+    }
+    opt.phase_loop = matches.opt_present("phase_loop");
+    if let Some(v) = matches.opt_str("think_time") {
+        opt.think_time = match pace::parse_think_time(&v) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                println!("Invalid think time {v}: {e}");
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(v) = matches.opt_str("burst") {
+        opt.burst = match pace::parse_burst(&v) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                println!("Invalid burst {v}: {e}");
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(v) = matches.opt_str("arrival_rate") {
+        opt.arrival_rate = match v.parse::<f64>() {
+            Ok(v) if v.is_finite() && v >= 0.0 => v,
+            Ok(_) => {
+                println!("Invalid arrival rate {v}");
+                std::process::exit(1);
+            }
+            Err(e) => {
+                println!("{v}: {e}");
+                std::process::exit(1);
+            }
+        };
+    }
+    // open-loop schedules start times by itself
+    if opt.arrival_rate > 0.0 && (opt.think_time.is_some() || opt.burst.is_some()) {
+        println!("--arrival_rate and --think_time or --burst are exclusive");
+        std::process::exit(1);
+    }
+    if let Some(v) = matches.opt_str("max_backlog") {
+        opt.max_backlog = match v.parse() {
+            Ok(v) => v,
+            Err(e) => {
+                println!("{v}: {e}");
+                std::process::exit(1);
+            }
+        };
+    }
+    if opt.phase_loop && opt.phase.is_empty() {
+        println!("--phase_loop requires --phase");
+        std::process::exit(1);
+    }
+    if let Some(v) = matches.opt_str("result_file") {
+        opt.result_file = v;
+    }
+    if let Some(v) = matches.opt_str("server") {
+        opt.server = remote::get_server_address(&v);
+    }
+    if let Some(v) = matches.opt_str("controller") {
+        for x in v.split(',') {
+            if x.is_empty() {
+                println!("Empty server address");
+                std::process::exit(1);
+            }
+            opt.controller.push(x.to_string());
+        }
+    }
+    if !opt.server.is_empty() && !opt.controller.is_empty() {
+        println!("--server and --controller are exclusive");
+        std::process::exit(1);
+    }
+    // controller runs no monitor
+    if !opt.metrics_address.is_empty() && !opt.controller.is_empty() {
+        println!("--metrics_address unsupported with --controller");
+        std::process::exit(1);
+    }
+    // servers run monitor without terminal
+    if opt.tui && (!opt.server.is_empty() || !opt.controller.is_empty()) {
+        println!("--tui unsupported with --server or --controller");
+        std::process::exit(1);
+    }
+    // buffers registered by uring I/O engine are locked memory of the user
+    if opt.io_engine.is_uring() && opt.controller.is_empty() {
+        if let Some(limit) = util::get_memlock_limit() {
+            let mut l = vec![opt.clone()];
+            if let Some(x) = &opt.set_param {
+                for v in &x.values {
+                    let mut set_opt = opt.clone();
+                    if x.param.apply(&mut set_opt, *v).is_ok() {
+                        l.push(set_opt);
+                    }
+                }
+            }
+            let n = l.iter().map(dir::get_ring_locked_bytes).max().unwrap();
+            if u64::try_from(n).unwrap() > limit {
+                println!(
+                    "{n} bytes of I/O buffers exceed RLIMIT_MEMLOCK {limit} bytes for --io_engine uring"
+                );
+                println!("Lower --io_depth or buffer sizes, or raise the limit (ulimit -l)");
+                std::process::exit(1);
+            }
+        }
+    }
+    opt.dry_run = matches.opt_present("dry_run");
+    if opt.dry_run {
+        // plan is recorded in each process
+        if opt.num_process > 1 {
+            println!("--dry_run and --num_process are exclusive");
+            std::process::exit(1);
+        }
+        if dir::is_aging(&opt) {
+            println!("--dry_run and aging are exclusive");
+            std::process::exit(1);
+        }
+        // one pass over paths unless specified
+        if opt.num_repeat <= 0 {
+            opt.num_repeat = 1;
+            println!("Using 1 repeat for dry run");
+        }
+    }
+    for v in matches.opt_strs("fault") {
+        match fault::parse_fault(&v) {
+            Ok(x) => opt.fault.push(x),
+            Err(e) => {
+                println!("Invalid fault {v}: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(v) = matches.opt_str("fault_seed") {
+        opt.fault_seed = match v.parse() {
+            Ok(v) => v,
+            Err(e) => {
+                println!("{v}: {e}");
+                std::process::exit(1);
+            }
+        };
+    } else if !opt.fault.is_empty() {
+        opt.fault_seed = util::get_random(0..u64::MAX);
+        println!("Using fault seed {}", opt.fault_seed);
+    }
+    if !opt.fault.is_empty() {
+        // uring I/O engine submits its own operations for file data
+        if opt.io_engine.is_uring() {
+            println!("--fault unsupported with --io_engine uring");
+            std::process::exit(1);
+        }
+        if opt.dry_run {
+            println!("--fault and --dry_run are exclusive");
+            std::process::exit(1);
+        }
+    }
     opt.force = matches.opt_present("force");
     opt.verbose = matches.opt_present("verbose");
     opt.debug = matches.opt_present("debug");
@@ -500,7 +1406,7 @@ fn main() {
         std::process::exit(1);
     }
 
-    if matches.free.is_empty() {
+    if matches.free.is_empty() && opt.server.is_empty() {
         usage(progname, &opts);
         std::process::exit(1);
     }
@@ -513,37 +1419,68 @@ fn main() {
         log::info!("{opt:?}");
     }
 
+    // metrics endpoint lives across sets and jobs
+    if !opt.metrics_address.is_empty() {
+        if let Err(e) = metrics::start(&opt.metrics_address) {
+            println!("{}: {e}", opt.metrics_address);
+            std::process::exit(1);
+        }
+    }
+
+    // run jobs from controller until interrupted
+    if !opt.server.is_empty() {
+        unsafe {
+            libc::signal(libc::SIGINT, sigint_handler as *const () as usize);
+        }
+        if let Err(e) = remote::run_server(&opt.server) {
+            println!("{e}");
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+
     // only allow directories since now that write is supported
     let args = matches.free;
+    if args.iter().filter(|x| x.starts_with('\0')).count()
+        != args_os.iter().filter(|x| x.to_str().is_none()).count()
+    {
+        println!("Non UTF-8 option value unsupported");
+        std::process::exit(1);
+    }
     let mut input = vec![];
     for v in &args {
-        let absf = util::get_abspath(v).unwrap();
-        assert!(!absf.ends_with('/'));
-        if !util::get_raw_file_type(&absf).unwrap().is_dir() {
-            println!("{absf} not directory");
+        let f = match v.strip_prefix('\0') {
+            Some(i) => std::path::PathBuf::from(&args_os[i.parse::<usize>().unwrap()]),
+            None => std::path::PathBuf::from(v),
+        };
+        let absf = util::get_abspath(&f).unwrap();
+        let b = absf.as_os_str().as_bytes();
+        assert!(!b.ends_with(b"/"));
+        // servers check their own directories
+        if opt.controller.is_empty() && !util::get_raw_file_type(&absf).is_ok_and(|t| t.is_dir()) {
+            println!("{} not directory", absf.display());
             std::process::exit(1);
         }
-        if !opt.force {
-            let mut count = 0;
-            for x in absf.chars() {
-                if x == '/' {
-                    count += 1;
-                }
-            }
-            // /path/to/dir is allowed, but /path/to is not
-            if count < 3 {
-                println!("{absf} not allowed, use --force option to proceed");
-                std::process::exit(1);
-            }
+        // /path/to/dir is allowed, but /path/to is not
+        if !opt.force && b.iter().filter(|x| **x == b'/').count() < 3 {
+            println!(
+                "{} not allowed, use --force option to proceed",
+                absf.display()
+            );
+            std::process::exit(1);
         }
         input.push(absf);
     }
     log::info!("input {input:?}");
 
     // and the directories should be writable
-    if opt.debug && opt.num_writer > 0 {
+    if opt.debug && opt.num_writer > 0 && opt.controller.is_empty() && !opt.dry_run {
         for f in &input {
-            log::info!("{} writable {}", f, util::is_dir_writable(f).unwrap());
+            log::info!(
+                "{} writable {}",
+                f.display(),
+                util::is_dir_writable(f).unwrap()
+            );
         }
     }
 
@@ -553,15 +1490,24 @@ fn main() {
             println!("Empty flist file path");
             std::process::exit(1);
         }
-        flist::create_flist_file(&input, &opt.flist_file, opt.ignore_dot, opt.force).unwrap();
-        println!("{:?}", util::path_exists_or_error(&opt.flist_file).unwrap());
+        let flist_file = std::path::Path::new(&opt.flist_file);
+        flist::create_flist_file(&input, flist_file, opt.ignore_dot, opt.force).unwrap();
+        println!("{:?}", util::path_exists_or_error(flist_file).unwrap());
         std::process::exit(0);
     }
     // clean write paths and exit
     if opt.clean_write_paths {
-        let mut l = dir::collect_write_paths(&input, &opt).unwrap();
+        let engine = engine::new_engine(&opt);
+        let mut l = dir::collect_write_paths(&input, engine.as_ref(), &opt).unwrap();
         let a = l.len();
-        dir::unlink_write_paths(&mut l, -1).unwrap();
+        dir::unlink_write_paths(&mut l, -1, engine.as_ref()).unwrap();
+        if let Some(x) = engine.get_plan() {
+            x.print();
+            std::process::exit(0);
+        }
+        if let Some(x) = engine.get_faults() {
+            fault::print_faults(&x);
+        }
         let b = l.len();
         assert!(a >= b);
         println!("Unlinked {} / {} write paths", a - b, a);
@@ -573,19 +1519,84 @@ fn main() {
     }
 
     unsafe {
-        libc::signal(libc::SIGINT, sigint_handler as usize);
+        libc::signal(libc::SIGINT, sigint_handler as *const () as usize);
     }
 
     // ready to dispatch workers
-    for i in 0..opt.num_set {
-        if opt.num_set != 1 {
+    let mut report = report::Report::new(&input, &opt);
+    let param = opt.set_param.as_ref();
+    // parameter value of each set
+    let mut values = match param {
+        Some(x) => x.values.iter().map(|v| Some(*v)).collect(),
+        None => vec![None; opt.num_set],
+    };
+    let num_warmup = usize::from(opt.warmup_set);
+    let num_total = values.len();
+    if opt.warmup_set {
+        // warm-up set uses the first set's parameter if any
+        values.insert(0, values.first().copied().flatten());
+    }
+    let num_set = values.len();
+    let set_opts: Vec<Opt> = values
+        .iter()
+        .enumerate()
+        .map(|(i, param_value)| {
+            let mut set_opt = opt.clone();
+            set_opt.set_index = i;
+            if let (Some(x), Some(v)) = (param, param_value) {
+                x.param.apply(&mut set_opt, *v).unwrap(); // validated
+            }
+            set_opt
+        })
+        .collect();
+
+    // aged files of all sets are recorded to one flist file
+    if !opt.age_flist_file.is_empty() && opt.controller.is_empty() {
+        let f = std::path::Path::new(&opt.age_flist_file);
+        if let Err(e) = flist::remove_flist_file(f, opt.force) {
+            println!("{}: {e}", opt.age_flist_file);
+            if e.kind() == std::io::ErrorKind::AlreadyExists {
+                println!("Use --force option to overwrite");
+            }
+            std::process::exit(1);
+        }
+    }
+
+    // one time series for all sets, columns for the most threads of them
+    if !opt.monitor_log.is_empty() && opt.controller.is_empty() {
+        let n = set_opts
+            .iter()
+            .map(|x| x.num_reader + x.num_writer)
+            .max()
+            .unwrap_or(0);
+        let s = format!("{}\n", stat::format_interval_header(n));
+        if let Err(e) = std::fs::write(&opt.monitor_log, s) {
+            println!("{}: {e}", opt.monitor_log);
+            std::process::exit(1);
+        }
+    }
+
+    for (i, (param_value, set_opt)) in values.into_iter().zip(set_opts).enumerate() {
+        let warmup = i < num_warmup;
+        if num_set != 1 {
             println!("{}", "=".repeat(80));
-            let s = format!("Set {}/{}", i + 1, opt.num_set);
+            let mut s = if warmup {
+                "Set warm-up".to_string()
+            } else {
+                format!("Set {}/{num_total}", i + 1 - num_warmup)
+            };
+            if let (Some(x), Some(v)) = (param, param_value) {
+                s.push_str(&format!(" {}={v}", x.param.get_name()));
+            }
             println!("{s}");
             log::info!("{s}");
         }
-        let (_, num_interrupted, num_error, num_remain, tsv) =
-            match worker::dispatch_worker(&input, &opt) {
+        let (num_complete, num_interrupted, num_error, num_remain, tsv) =
+            match if set_opt.controller.is_empty() {
+                worker::dispatch_worker(&input, &set_opt)
+            } else {
+                remote::dispatch_remote(&input, &set_opt)
+            } {
                 Ok(v) => v,
                 Err(e) => panic!("{e}"),
             };
@@ -611,11 +1622,55 @@ fn main() {
             println!("{num_remain} write path{s} remaining");
         }
         stat::print_stat(&tsv);
+        let summary = report::Summary::new(&tsv);
+        if !tsv.is_empty() {
+            report::print_summary(&summary);
+        }
+        let phases = report::get_phase_summary(&tsv, opt.phase.len());
+        if !tsv.is_empty() && !phases.is_empty() {
+            report::print_phase_table(&opt.phase, &phases);
+        }
+        report.sets.push(report::SetReport {
+            param_value,
+            warmup,
+            num_complete,
+            num_interrupted,
+            num_error,
+            num_remain,
+            summary,
+            phases,
+            threads: tsv,
+        });
         if num_interrupted > 0 {
             break;
         }
-        if opt.num_set != 1 && i != opt.num_set - 1 {
+        if num_set != 1 && i != num_set - 1 {
             println!();
         }
     }
+
+    // summarize sets other than warm-up
+    let l = report.get_sets();
+    if let Some(x) = param {
+        let v = report::get_sweep(x.param.get_name(), &l);
+        if l.len() > 1 {
+            println!("{}", "=".repeat(80));
+            println!("Summary of {} sets, {} values", l.len(), v.len());
+            report::print_set_table(&v);
+        }
+        report.sweep = v;
+    } else if l.len() > 1 {
+        println!("{}", "=".repeat(80));
+        println!("Summary of {} sets", l.len());
+        report::print_set_stats(&report::get_set_stats(&l));
+    }
+
+    if !opt.result_file.is_empty() {
+        let f = std::path::Path::new(&opt.result_file);
+        if let Err(e) = report::write_report_file(f, &report) {
+            println!("{}: {e}", opt.result_file);
+            std::process::exit(1);
+        }
+        println!("Saved results to {}", opt.result_file);
+    }
 }
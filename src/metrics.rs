@@ -0,0 +1,304 @@
+use std::fmt::Write as _;
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::is_interrupted;
+use crate::is_paused;
+use crate::stat;
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+type StatGetter = fn(&stat::ThreadStat) -> usize;
+
+// latest stats of threads, updated by monitor
+static STATE: std::sync::Mutex<Vec<stat::ThreadStat>> = std::sync::Mutex::new(vec![]);
+
+// called by monitor when workers start
+pub(crate) fn init(n: usize) {
+    *STATE.lock().unwrap() = vec![stat::ThreadStat::new(); n];
+}
+
+pub(crate) fn update(gid: usize, ts: &stat::ThreadStat) {
+    let mut tsv = STATE.lock().unwrap();
+    if gid < tsv.len() {
+        tsv[gid] = ts.clone();
+    }
+}
+
+fn get_state(tsv: &[stat::ThreadStat]) -> &'static str {
+    if is_interrupted() {
+        "interrupted"
+    } else if !tsv.is_empty() && tsv.iter().all(|x| x.done) {
+        "done"
+    } else if is_paused() {
+        "paused"
+    } else if tsv.is_empty() || tsv.iter().any(|x| !x.is_ready()) {
+        "starting"
+    } else {
+        "running"
+    }
+}
+
+fn get_type(ts: &stat::ThreadStat) -> &'static str {
+    if ts.is_reader() {
+        "reader"
+    } else {
+        "writer"
+    }
+}
+
+fn format_metrics(tsv: &[stat::ThreadStat], state: &str) -> String {
+    let mut s = String::new();
+
+    // per thread
+    let ops: [(&str, StatGetter); 5] = [
+        ("stat", stat::ThreadStat::get_num_stat),
+        ("read", stat::ThreadStat::get_num_read),
+        ("write", stat::ThreadStat::get_num_write),
+        ("xattr_read", stat::ThreadStat::get_num_xattr_read),
+        ("xattr_write", stat::ThreadStat::get_num_xattr_write),
+    ];
+    let bytes: [(&str, StatGetter); 2] = [
+        ("read", stat::ThreadStat::get_num_read_bytes),
+        ("write", stat::ThreadStat::get_num_write_bytes),
+    ];
+    writeln!(s, "# TYPE dirload_thread_ops counter").unwrap();
+    writeln!(s, "# HELP dirload_thread_ops Operations per thread.").unwrap();
+    for (i, ts) in tsv.iter().enumerate() {
+        for (op, f) in &ops {
+            let t = get_type(ts);
+            writeln!(
+                s,
+                "dirload_thread_ops_total{{gid=\"{i}\",type=\"{t}\",op=\"{op}\"}} {}",
+                f(ts)
+            )
+            .unwrap();
+        }
+    }
+    writeln!(s, "# TYPE dirload_thread_bytes counter").unwrap();
+    writeln!(s, "# UNIT dirload_thread_bytes bytes").unwrap();
+    writeln!(s, "# HELP dirload_thread_bytes Bytes per thread.").unwrap();
+    for (i, ts) in tsv.iter().enumerate() {
+        for (op, f) in &bytes {
+            let t = get_type(ts);
+            writeln!(
+                s,
+                "dirload_thread_bytes_total{{gid=\"{i}\",type=\"{t}\",op=\"{op}\"}} {}",
+                f(ts)
+            )
+            .unwrap();
+        }
+    }
+    writeln!(s, "# TYPE dirload_thread_errors counter").unwrap();
+    writeln!(s, "# HELP dirload_thread_errors Failed threads.").unwrap();
+    for (i, ts) in tsv.iter().enumerate() {
+        let t = get_type(ts);
+        writeln!(
+            s,
+            "dirload_thread_errors_total{{gid=\"{i}\",type=\"{t}\"}} {}",
+            usize::from(ts.failed)
+        )
+        .unwrap();
+    }
+    writeln!(s, "# TYPE dirload_thread_write_paths gauge").unwrap();
+    writeln!(
+        s,
+        "# HELP dirload_thread_write_paths Write paths not yet unlinked."
+    )
+    .unwrap();
+    for (i, ts) in tsv.iter().enumerate() {
+        let t = get_type(ts);
+        writeln!(
+            s,
+            "dirload_thread_write_paths{{gid=\"{i}\",type=\"{t}\"}} {}",
+            ts.get_num_write_paths()
+        )
+        .unwrap();
+    }
+    writeln!(s, "# TYPE dirload_thread_backlog gauge").unwrap();
+    writeln!(
+        s,
+        "# HELP dirload_thread_backlog Operations overdue in open-loop."
+    )
+    .unwrap();
+    for (i, ts) in tsv.iter().enumerate().filter(|x| x.1.is_open_loop()) {
+        let t = get_type(ts);
+        writeln!(
+            s,
+            "dirload_thread_backlog{{gid=\"{i}\",type=\"{t}\"}} {}",
+            ts.get_backlog()
+        )
+        .unwrap();
+    }
+    writeln!(s, "# TYPE dirload_thread_dropped counter").unwrap();
+    writeln!(
+        s,
+        "# HELP dirload_thread_dropped Operations dropped in open-loop."
+    )
+    .unwrap();
+    for (i, ts) in tsv.iter().enumerate().filter(|x| x.1.is_open_loop()) {
+        let t = get_type(ts);
+        writeln!(
+            s,
+            "dirload_thread_dropped_total{{gid=\"{i}\",type=\"{t}\"}} {}",
+            ts.get_num_dropped()
+        )
+        .unwrap();
+    }
+
+    // per type
+    writeln!(s, "# TYPE dirload_ops counter").unwrap();
+    writeln!(s, "# HELP dirload_ops Operations per thread type.").unwrap();
+    for t in ["reader", "writer"] {
+        for (op, f) in &ops {
+            let n: usize = tsv.iter().filter(|x| get_type(x) == t).map(f).sum();
+            writeln!(s, "dirload_ops_total{{type=\"{t}\",op=\"{op}\"}} {n}").unwrap();
+        }
+    }
+    writeln!(s, "# TYPE dirload_bytes counter").unwrap();
+    writeln!(s, "# UNIT dirload_bytes bytes").unwrap();
+    writeln!(s, "# HELP dirload_bytes Bytes per thread type.").unwrap();
+    for t in ["reader", "writer"] {
+        for (op, f) in &bytes {
+            let n: usize = tsv.iter().filter(|x| get_type(x) == t).map(f).sum();
+            writeln!(s, "dirload_bytes_total{{type=\"{t}\",op=\"{op}\"}} {n}").unwrap();
+        }
+    }
+    writeln!(s, "# TYPE dirload_errors counter").unwrap();
+    writeln!(s, "# HELP dirload_errors Failed threads per thread type.").unwrap();
+    for t in ["reader", "writer"] {
+        let n = tsv.iter().filter(|x| get_type(x) == t && x.failed).count();
+        writeln!(s, "dirload_errors_total{{type=\"{t}\"}} {n}").unwrap();
+    }
+    writeln!(s, "# TYPE dirload_write_paths gauge").unwrap();
+    writeln!(
+        s,
+        "# HELP dirload_write_paths Write paths not yet unlinked."
+    )
+    .unwrap();
+    let n: usize = tsv.iter().map(stat::ThreadStat::get_num_write_paths).sum();
+    writeln!(s, "dirload_write_paths {n}").unwrap();
+
+    // run state
+    writeln!(s, "# TYPE dirload_state stateset").unwrap();
+    writeln!(s, "# HELP dirload_state Run state.").unwrap();
+    for x in ["starting", "running", "paused", "interrupted", "done"] {
+        writeln!(
+            s,
+            "dirload_state{{dirload_state=\"{x}\"}} {}",
+            usize::from(x == state)
+        )
+        .unwrap();
+    }
+    writeln!(s, "# EOF").unwrap();
+    s
+}
+
+fn handle_request(mut stream: std::net::TcpStream) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(std::time::Duration::from_secs(1)))?;
+    let mut reader = std::io::BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    // ignore headers
+    loop {
+        let mut s = String::new();
+        if reader.read_line(&mut s)? == 0 || s == "\r\n" || s == "\n" {
+            break;
+        }
+    }
+
+    let l: Vec<&str> = line.split_whitespace().collect();
+    let (status, content_type, body) = match l.as_slice() {
+        ["GET", "/metrics", ..] => {
+            let tsv = STATE.lock().unwrap().clone();
+            (
+                "200 OK",
+                CONTENT_TYPE,
+                format_metrics(&tsv, get_state(&tsv)),
+            )
+        }
+        ["GET", ..] => ("404 Not Found", "text/plain", "Not Found\n".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "Method Not Allowed\n".to_string(),
+        ),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+// serve until process exits
+pub(crate) fn start(addr: &str) -> std::io::Result<()> {
+    let listener = std::net::TcpListener::bind(addr)?;
+    println!(
+        "Serving metrics on http://{}/metrics",
+        listener.local_addr()?
+    );
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(v) => {
+                    if let Err(e) = handle_request(v) {
+                        log::info!("{e}");
+                    }
+                }
+                Err(e) => log::info!("{e}"),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_format_metrics() {
+        let mut tsv = vec![
+            super::stat::ThreadStat::newread(),
+            super::stat::ThreadStat::newwrite(),
+        ];
+        tsv[0].inc_num_stat();
+        tsv[0].inc_num_read();
+        tsv[0].add_num_read_bytes(100);
+        tsv[1].inc_num_write();
+        tsv[1].add_num_write_bytes(10);
+        tsv[1].set_num_write_paths(1);
+        tsv[1].failed = true;
+        tsv[1].set_open_loop();
+        tsv[1].set_backlog(3);
+        tsv[1].add_num_dropped(2);
+
+        let s = super::format_metrics(&tsv, "running");
+        assert!(s.ends_with("# EOF\n"), "{s}");
+        for x in [
+            "dirload_thread_ops_total{gid=\"0\",type=\"reader\",op=\"stat\"} 1\n",
+            "dirload_thread_ops_total{gid=\"0\",type=\"reader\",op=\"write\"} 0\n",
+            "dirload_thread_ops_total{gid=\"1\",type=\"writer\",op=\"write\"} 1\n",
+            "dirload_thread_bytes_total{gid=\"0\",type=\"reader\",op=\"read\"} 100\n",
+            "dirload_thread_errors_total{gid=\"1\",type=\"writer\"} 1\n",
+            "dirload_thread_write_paths{gid=\"1\",type=\"writer\"} 1\n",
+            "dirload_ops_total{type=\"reader\",op=\"read\"} 1\n",
+            "dirload_bytes_total{type=\"writer\",op=\"write\"} 10\n",
+            "dirload_errors_total{type=\"reader\"} 0\n",
+            "dirload_errors_total{type=\"writer\"} 1\n",
+            "dirload_thread_backlog{gid=\"1\",type=\"writer\"} 3\n",
+            "dirload_thread_dropped_total{gid=\"1\",type=\"writer\"} 2\n",
+            "dirload_write_paths 1\n",
+            "dirload_state{dirload_state=\"running\"} 1\n",
+            "dirload_state{dirload_state=\"done\"} 0\n",
+        ] {
+            assert!(s.contains(x), "{x} {s}");
+        }
+        assert!(!s.contains("dirload_thread_backlog{gid=\"0\""), "{s}");
+        // each metric family declared once
+        for l in s.lines().filter(|x| x.starts_with("# TYPE")) {
+            assert_eq!(s.matches(&format!("{l}\n")).count(), 1, "{l}");
+        }
+    }
+}
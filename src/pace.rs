@@ -0,0 +1,320 @@
+use crate::util;
+
+// e.g. "500us", "5ms", "1s"
+pub(crate) fn parse_duration(s: &str) -> std::io::Result<std::time::Duration> {
+    let (x, n) = if let Some(x) = s.strip_suffix("us") {
+        (x, 1e-6)
+    } else if let Some(x) = s.strip_suffix("ms") {
+        (x, 1e-3)
+    } else if let Some(x) = s.strip_suffix('s') {
+        (x, 1.0)
+    } else {
+        return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
+    };
+    match x.parse::<f64>() {
+        Ok(v) if v.is_finite() && v >= 0.0 => std::time::Duration::try_from_secs_f64(v * n)
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+        _ => Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+    }
+}
+
+// time between operations of a thread
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) enum ThinkTime {
+    Fixed(std::time::Duration),
+    Exponential(std::time::Duration), // mean
+    Uniform(std::time::Duration, std::time::Duration),
+}
+
+impl ThinkTime {
+    #[allow(dead_code)]
+    pub(crate) fn is_fixed(&self) -> bool {
+        matches!(self, ThinkTime::Fixed(..))
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn is_exponential(&self) -> bool {
+        matches!(self, ThinkTime::Exponential(..))
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn is_uniform(&self) -> bool {
+        matches!(self, ThinkTime::Uniform(..))
+    }
+
+    pub(crate) fn get_duration(&self) -> std::time::Duration {
+        match self {
+            ThinkTime::Fixed(d) => *d,
+            ThinkTime::Exponential(d) => {
+                // inverse transform sampling, u in (0, 1]
+                let u = 1.0 - util::get_random(0.0..1.0);
+                std::time::Duration::try_from_secs_f64(d.as_secs_f64() * -f64::ln(u))
+                    .unwrap_or(std::time::Duration::MAX)
+            }
+            ThinkTime::Uniform(a, b) => {
+                if a == b {
+                    *a
+                } else {
+                    util::get_random(*a..=*b)
+                }
+            }
+        }
+    }
+}
+
+// e.g. "5ms", "fixed:5ms", "exp:5ms", "uniform:1ms-10ms"
+pub(crate) fn parse_think_time(s: &str) -> std::io::Result<ThinkTime> {
+    let (t, x) = s.split_once(':').unwrap_or(("fixed", s));
+    match t {
+        "fixed" => Ok(ThinkTime::Fixed(parse_duration(x)?)),
+        "exp" => Ok(ThinkTime::Exponential(parse_duration(x)?)),
+        "uniform" => {
+            let (a, b) = match x.split_once('-') {
+                Some(v) => v,
+                None => return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+            };
+            let (a, b) = (parse_duration(a)?, parse_duration(b)?);
+            if a > b {
+                return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
+            }
+            Ok(ThinkTime::Uniform(a, b))
+        }
+        _ => Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+    }
+}
+
+// threads alternate between on and off periods since start
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Burst {
+    pub(crate) on: std::time::Duration,
+    pub(crate) off: std::time::Duration,
+}
+
+impl Burst {
+    // time until next on period, zero if in on period
+    pub(crate) fn get_idle(&self, elapsed: std::time::Duration) -> std::time::Duration {
+        let cycle = self.on + self.off;
+        let t = std::time::Duration::from_nanos(
+            (elapsed.as_nanos() % cycle.as_nanos()).try_into().unwrap(),
+        );
+        if t < self.on {
+            std::time::Duration::ZERO
+        } else {
+            cycle - t
+        }
+    }
+}
+
+// e.g. "100ms:900ms"
+pub(crate) fn parse_burst(s: &str) -> std::io::Result<Burst> {
+    let (on, off) = match s.split_once(':') {
+        Some(v) => v,
+        None => return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+    };
+    let (on, off) = (parse_duration(on)?, parse_duration(off)?);
+    // cycle must be representable
+    if on.is_zero() || on.checked_add(off).is_none() {
+        return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
+    }
+    Ok(Burst { on, off })
+}
+
+// open-loop schedule of operations at fixed rate
+#[derive(Debug)]
+pub(crate) struct Arrival {
+    interval: std::time::Duration,
+    next: Option<std::time::Instant>, // intended start of next operation
+}
+
+impl Arrival {
+    pub(crate) fn new(rate: f64) -> Self {
+        assert!(rate > 0.0);
+        Self {
+            interval: std::time::Duration::from_secs_f64(1.0 / rate),
+            next: None,
+        }
+    }
+
+    // restart schedule, e.g. after pause
+    pub(crate) fn reset(&mut self) {
+        self.next = None;
+    }
+
+    // intended start of next operation, number of operations overdue after it,
+    // and number of operations dropped to keep backlog within max if >= 0
+    pub(crate) fn get_next(
+        &mut self,
+        now: std::time::Instant,
+        max_backlog: isize,
+    ) -> (std::time::Instant, usize, usize) {
+        let mut t = *self.next.get_or_insert(now);
+        let mut backlog = 0;
+        let mut dropped = 0;
+        if now > t {
+            backlog = usize::try_from((now - t).as_nanos() / self.interval.as_nanos())
+                .unwrap_or(usize::MAX);
+            if let Ok(n) = usize::try_from(max_backlog) {
+                if backlog > n {
+                    dropped = backlog - n;
+                    backlog = n;
+                    t += self
+                        .interval
+                        .saturating_mul(u32::try_from(dropped).unwrap_or(u32::MAX));
+                }
+            }
+        }
+        self.next = Some(t + self.interval);
+        (t, backlog, dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_duration() {
+        let duration_list = [
+            ("0s", Duration::ZERO),
+            ("500us", Duration::from_micros(500)),
+            ("5ms", Duration::from_millis(5)),
+            ("1.5ms", Duration::from_micros(1500)),
+            ("2s", Duration::from_secs(2)),
+        ];
+        for x in &duration_list {
+            match super::parse_duration(x.0) {
+                Ok(v) => assert_eq!(v, x.1, "{x:?}"),
+                Err(e) => panic!("{e} {x:?}"),
+            }
+        }
+        let invalid_list = [
+            "", "5", "ms", "-1ms", "5m", "5 ms", "infs", "NaNms", "1e20s",
+        ];
+        for s in &invalid_list {
+            assert!(super::parse_duration(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_parse_think_time() {
+        let think_time_list = [
+            ("5ms", super::ThinkTime::Fixed(Duration::from_millis(5))),
+            (
+                "fixed:5ms",
+                super::ThinkTime::Fixed(Duration::from_millis(5)),
+            ),
+            (
+                "exp:2ms",
+                super::ThinkTime::Exponential(Duration::from_millis(2)),
+            ),
+            (
+                "uniform:1ms-3ms",
+                super::ThinkTime::Uniform(Duration::from_millis(1), Duration::from_millis(3)),
+            ),
+        ];
+        for x in &think_time_list {
+            match super::parse_think_time(x.0) {
+                Ok(v) => assert_eq!(v, x.1, "{x:?}"),
+                Err(e) => panic!("{e} {x:?}"),
+            }
+        }
+        assert!(super::parse_think_time("5ms").unwrap().is_fixed());
+        assert!(super::parse_think_time("exp:5ms").unwrap().is_exponential());
+        assert!(super::parse_think_time("uniform:0s-1s")
+            .unwrap()
+            .is_uniform());
+
+        let invalid_list = [
+            "",
+            "fixed:",
+            "exp:5",
+            "normal:5ms",
+            "uniform:5ms",
+            "uniform:3ms-1ms",
+            "uniform:1ms-",
+        ];
+        for s in &invalid_list {
+            assert!(super::parse_think_time(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_think_time_get_duration() {
+        let d = Duration::from_millis(5);
+        assert_eq!(super::ThinkTime::Fixed(d).get_duration(), d);
+        assert_eq!(super::ThinkTime::Uniform(d, d).get_duration(), d);
+
+        let (a, b) = (Duration::from_millis(1), Duration::from_millis(3));
+        for _ in 0..100 {
+            let x = super::ThinkTime::Uniform(a, b).get_duration();
+            assert!(x >= a && x <= b, "{x:?}");
+        }
+
+        // mean of samples close to mean of distribution
+        let n = 10000;
+        let x = super::ThinkTime::Exponential(Duration::from_millis(10));
+        let sum: Duration = (0..n).map(|_| x.get_duration()).sum();
+        let mean = sum.as_secs_f64() / f64::from(n);
+        assert!((mean - 0.01).abs() < 0.001, "{mean}");
+    }
+
+    #[test]
+    fn test_burst() {
+        match super::parse_burst("100ms:900ms") {
+            Ok(v) => {
+                assert_eq!(v.on, Duration::from_millis(100));
+                assert_eq!(v.off, Duration::from_millis(900));
+            }
+            Err(e) => panic!("{e}"),
+        }
+        for s in ["", "100ms", "0s:1s", "100ms:", "x:1s", "1e19s:1e19s"] {
+            assert!(super::parse_burst(s).is_err(), "{s}");
+        }
+
+        let x = super::parse_burst("100ms:900ms").unwrap();
+        let idle_list = [
+            (0, 0),
+            (99, 0),
+            (100, 900),
+            (400, 600),
+            (999, 1),
+            (1000, 0),
+            (1050, 0),
+        ];
+        for (t, d) in idle_list {
+            assert_eq!(
+                x.get_idle(Duration::from_millis(t)),
+                Duration::from_millis(d),
+                "{t}"
+            );
+        }
+
+        // always on
+        let x = super::parse_burst("1s:0s").unwrap();
+        assert_eq!(x.get_idle(Duration::from_millis(1500)), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_arrival() {
+        let ms = Duration::from_millis;
+        let mut x = super::Arrival::new(100.0); // every 10ms
+        let t0 = std::time::Instant::now();
+
+        // on schedule, next start is in the future
+        assert_eq!(x.get_next(t0, -1), (t0, 0, 0));
+        assert_eq!(x.get_next(t0 + ms(1), -1), (t0 + ms(10), 0, 0));
+
+        // 35ms late for op at 20ms, 3 more ops overdue by then
+        assert_eq!(x.get_next(t0 + ms(55), -1), (t0 + ms(20), 3, 0));
+        assert_eq!(x.get_next(t0 + ms(55), -1), (t0 + ms(30), 2, 0));
+
+        // drop ops beyond max backlog
+        assert_eq!(x.get_next(t0 + ms(95), 1), (t0 + ms(80), 1, 4));
+        assert_eq!(x.get_next(t0 + ms(95), 0), (t0 + ms(90), 0, 0));
+
+        // restart from now
+        x.reset();
+        assert_eq!(x.get_next(t0 + ms(200), 0), (t0 + ms(200), 0, 0));
+        assert_eq!(x.get_next(t0 + ms(200), 0), (t0 + ms(210), 0, 0));
+    }
+}
@@ -0,0 +1,368 @@
+use crate::dir;
+use crate::Opt;
+
+// options which can vary across sets
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) enum Param {
+    NumReader,
+    NumWriter,
+    NumProcess,
+    NumRepeat,
+    TimeSecond,
+    ReadBufferSize,
+    ReadSize,
+    WriteBufferSize,
+    WriteSize,
+    NumWritePaths,
+    WritePathsMaxDepth,
+    StatOnly,
+    TruncateWritePaths,
+    FsyncWritePaths,
+    DirsyncWritePaths,
+}
+
+const PARAM_LIST: [Param; 15] = [
+    Param::NumReader,
+    Param::NumWriter,
+    Param::NumProcess,
+    Param::NumRepeat,
+    Param::TimeSecond,
+    Param::ReadBufferSize,
+    Param::ReadSize,
+    Param::WriteBufferSize,
+    Param::WriteSize,
+    Param::NumWritePaths,
+    Param::WritePathsMaxDepth,
+    Param::StatOnly,
+    Param::TruncateWritePaths,
+    Param::FsyncWritePaths,
+    Param::DirsyncWritePaths,
+];
+
+impl Param {
+    pub(crate) fn get_name(&self) -> &'static str {
+        match self {
+            Param::NumReader => "num_reader",
+            Param::NumWriter => "num_writer",
+            Param::NumProcess => "num_process",
+            Param::NumRepeat => "num_repeat",
+            Param::TimeSecond => "time_second",
+            Param::ReadBufferSize => "read_buffer_size",
+            Param::ReadSize => "read_size",
+            Param::WriteBufferSize => "write_buffer_size",
+            Param::WriteSize => "write_size",
+            Param::NumWritePaths => "num_write_paths",
+            Param::WritePathsMaxDepth => "write_paths_max_depth",
+            Param::StatOnly => "stat_only",
+            Param::TruncateWritePaths => "truncate_write_paths",
+            Param::FsyncWritePaths => "fsync_write_paths",
+            Param::DirsyncWritePaths => "dirsync_write_paths",
+        }
+    }
+
+    // parameters which can change while threads run
+    pub(crate) fn is_phase_param(&self) -> bool {
+        !matches!(
+            self,
+            Param::NumProcess
+                | Param::NumRepeat
+                | Param::TimeSecond
+                | Param::ReadBufferSize
+                | Param::WriteBufferSize
+                | Param::NumWritePaths
+        )
+    }
+
+    pub(crate) fn get_names() -> Vec<&'static str> {
+        PARAM_LIST.iter().map(Param::get_name).collect()
+    }
+
+    pub(crate) fn new(s: &str) -> std::io::Result<Self> {
+        match PARAM_LIST.iter().find(|x| x.get_name() == s) {
+            Some(v) => Ok(*v),
+            None => Err(std::io::Error::from(std::io::ErrorKind::NotFound)),
+        }
+    }
+
+    // set value to option, with the same checks as command line
+    pub(crate) fn apply(&self, opt: &mut Opt, x: i64) -> std::io::Result<()> {
+        let max_buffer_size = i64::try_from(dir::MAX_BUFFER_SIZE).unwrap();
+        let is_valid = match self {
+            Param::NumReader | Param::NumWriter | Param::TimeSecond => x >= 0,
+            Param::NumProcess => x > 0,
+            Param::NumRepeat | Param::WritePathsMaxDepth => x == -1 || x > 0,
+            Param::NumWritePaths => x >= -1,
+            Param::ReadSize | Param::WriteSize => (-1..=max_buffer_size).contains(&x),
+            Param::ReadBufferSize | Param::WriteBufferSize => (0..=max_buffer_size).contains(&x),
+            // flags
+            Param::StatOnly
+            | Param::TruncateWritePaths
+            | Param::FsyncWritePaths
+            | Param::DirsyncWritePaths => x == 0 || x == 1,
+        };
+        if !is_valid {
+            return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
+        }
+        let invalid = |_| std::io::Error::from(std::io::ErrorKind::InvalidInput);
+        match self {
+            Param::NumReader => opt.num_reader = x.try_into().map_err(invalid)?,
+            Param::NumWriter => opt.num_writer = x.try_into().map_err(invalid)?,
+            Param::NumProcess => opt.num_process = x.try_into().map_err(invalid)?,
+            Param::NumRepeat => opt.num_repeat = x.try_into().map_err(invalid)?,
+            Param::TimeSecond => opt.time_second = x.try_into().map_err(invalid)?,
+            Param::ReadBufferSize => opt.read_buffer_size = x.try_into().map_err(invalid)?,
+            Param::ReadSize => opt.read_size = x.try_into().map_err(invalid)?,
+            Param::WriteBufferSize => opt.write_buffer_size = x.try_into().map_err(invalid)?,
+            Param::WriteSize => opt.write_size = x.try_into().map_err(invalid)?,
+            Param::NumWritePaths => opt.num_write_paths = x.try_into().map_err(invalid)?,
+            Param::WritePathsMaxDepth => {
+                opt.write_paths_max_depth = x.try_into().map_err(invalid)?;
+            }
+            Param::StatOnly => opt.stat_only = x == 1,
+            Param::TruncateWritePaths => opt.truncate_write_paths = x == 1,
+            Param::FsyncWritePaths => opt.fsync_write_paths = x == 1,
+            Param::DirsyncWritePaths => opt.dirsync_write_paths = x == 1,
+        }
+        Ok(())
+    }
+}
+
+const MAX_VALUES: usize = 1 << 10;
+
+// parameter and its values, e.g. "num_reader:1,2,4,8" or "num_reader:1..64*2",
+// values are repeated if specified, e.g. "num_reader:1..64*2:3"
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct ParamList {
+    pub(crate) param: Param,
+    pub(crate) values: Vec<i64>,
+}
+
+// e.g. "4096", "4k", "1m", "-1"
+pub(crate) fn parse_value(s: &str) -> std::io::Result<i64> {
+    let (x, n) = match s.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&s[..i], 1 << 10),
+        Some((i, 'm' | 'M')) => (&s[..i], 1 << 20),
+        Some((i, 'g' | 'G')) => (&s[..i], 1 << 30),
+        _ => (s, 1),
+    };
+    match x.parse::<i64>() {
+        Ok(v) => v
+            .checked_mul(n)
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+        Err(_) => Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+    }
+}
+
+// inclusive range with optional step, e.g. "1..8", "0..100+10", "4k..128k*2"
+fn parse_range(s: &str) -> std::io::Result<Vec<i64>> {
+    let invalid = || std::io::Error::from(std::io::ErrorKind::InvalidInput);
+    let (a, x) = s.split_once("..").ok_or_else(invalid)?;
+    // skip sign of end value
+    let (b, step) = match x.char_indices().skip(1).find(|c| c.1 == '*' || c.1 == '+') {
+        Some((i, c)) => (&x[..i], Some((c, parse_value(&x[i + 1..])?))),
+        None => (x, None),
+    };
+    let (a, b) = (parse_value(a)?, parse_value(b)?);
+    if a > b {
+        return Err(invalid());
+    }
+    let mut l = vec![];
+    let mut v = a;
+    while v <= b {
+        l.push(v);
+        if l.len() > MAX_VALUES {
+            return Err(invalid());
+        }
+        v = match step {
+            None => v.checked_add(1),
+            Some(('+', n)) if n > 0 => v.checked_add(n),
+            Some(('*', n)) if n > 1 && v > 0 => v.checked_mul(n),
+            _ => return Err(invalid()),
+        }
+        .ok_or_else(invalid)?;
+    }
+    Ok(l)
+}
+
+// comma separated values or ranges
+pub(crate) fn parse_values(s: &str) -> std::io::Result<Vec<i64>> {
+    let mut l = vec![];
+    for x in s.split(',') {
+        if x.contains("..") {
+            l.extend(parse_range(x)?);
+        } else {
+            l.push(parse_value(x)?);
+        }
+        if l.len() > MAX_VALUES {
+            return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
+        }
+    }
+    Ok(l)
+}
+
+pub(crate) fn parse_param_list(s: &str) -> std::io::Result<ParamList> {
+    let invalid = || std::io::Error::from(std::io::ErrorKind::InvalidInput);
+    let (name, v) = s.split_once(':').ok_or_else(invalid)?;
+    let param = Param::new(name)?;
+    // consecutive sets per value
+    let (v, repeat) = match v.split_once(':') {
+        Some((v, n)) => (v, n.parse::<usize>().map_err(|_| invalid())?),
+        None => (v, 1),
+    };
+    if repeat == 0 || repeat > MAX_VALUES {
+        return Err(invalid());
+    }
+    let values: Vec<i64> = parse_values(v)?
+        .into_iter()
+        .flat_map(|x| std::iter::repeat_n(x, repeat))
+        .collect();
+    if values.len() > MAX_VALUES {
+        return Err(invalid());
+    }
+    // values are applied to default option to validate
+    let mut opt = Opt::default();
+    for x in &values {
+        param.apply(&mut opt, *x)?;
+    }
+    Ok(ParamList { param, values })
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_parse_value() {
+        let value_list = [
+            ("0", 0),
+            ("1", 1),
+            ("-1", -1),
+            ("4096", 4096),
+            ("4k", 4096),
+            ("4K", 4096),
+            ("1m", 1 << 20),
+            ("2g", 2 << 30),
+        ];
+        for x in &value_list {
+            match super::parse_value(x.0) {
+                Ok(v) => assert_eq!(v, x.1, "{x:?}"),
+                Err(e) => panic!("{e} {x:?}"),
+            }
+        }
+        let invalid_list = ["", "k", "x", "1x", "1.5", "1kk", "9999999999999999999g"];
+        for s in &invalid_list {
+            assert!(super::parse_value(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_parse_param_list() {
+        match super::parse_param_list("num_reader:1,2,4,8") {
+            Ok(v) => {
+                assert_eq!(v.param, super::Param::NumReader);
+                assert_eq!(v.values, vec![1, 2, 4, 8]);
+            }
+            Err(e) => panic!("{e}"),
+        }
+        match super::parse_param_list("read_buffer_size:4k,128k") {
+            Ok(v) => {
+                assert_eq!(v.param, super::Param::ReadBufferSize);
+                assert_eq!(v.values, vec![4096, 131_072]);
+            }
+            Err(e) => panic!("{e}"),
+        }
+        match super::parse_param_list("num_reader:1..4*2:2") {
+            Ok(v) => {
+                assert_eq!(v.param, super::Param::NumReader);
+                assert_eq!(v.values, vec![1, 1, 2, 2, 4, 4]);
+            }
+            Err(e) => panic!("{e}"),
+        }
+        let invalid_list = [
+            "",
+            "num_reader",
+            "num_reader:",
+            "num_reader:1:",
+            "num_reader:1:0",
+            "num_reader:1:x",
+            "num_reader:1..1000:2",
+            "num_reader:-1",
+            "num_process:0",
+            "num_repeat:0",
+            "read_buffer_size:1g",
+            "write_paths_max_depth:0",
+            "xxx:1",
+        ];
+        for s in &invalid_list {
+            assert!(super::parse_param_list(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_param_apply() {
+        for s in super::Param::get_names() {
+            let p = match super::Param::new(s) {
+                Ok(v) => v,
+                Err(e) => panic!("{e} {s}"),
+            };
+            assert_eq!(p.get_name(), s);
+            let mut opt = crate::Opt::default();
+            if let Err(e) = p.apply(&mut opt, 1) {
+                panic!("{e} {s}");
+            }
+        }
+        let mut opt = crate::Opt::default();
+        assert!(super::Param::NumWriter.apply(&mut opt, 5).is_ok());
+        assert_eq!(opt.num_writer, 5);
+        assert!(super::Param::ReadSize.apply(&mut opt, -1).is_ok());
+        assert_eq!(opt.read_size, -1);
+        assert!(super::Param::FsyncWritePaths.apply(&mut opt, 1).is_ok());
+        assert!(opt.fsync_write_paths);
+        assert!(super::Param::FsyncWritePaths.apply(&mut opt, 0).is_ok());
+        assert!(!opt.fsync_write_paths);
+        assert!(super::Param::FsyncWritePaths.apply(&mut opt, 2).is_err());
+
+        assert!(super::Param::NumReader.is_phase_param());
+        assert!(super::Param::FsyncWritePaths.is_phase_param());
+        assert!(!super::Param::ReadBufferSize.is_phase_param());
+        assert!(!super::Param::TimeSecond.is_phase_param());
+    }
+
+    #[test]
+    fn test_parse_values() {
+        let values_list = [
+            ("1", vec![1]),
+            ("1,2,4", vec![1, 2, 4]),
+            ("1..4", vec![1, 2, 3, 4]),
+            ("3..3", vec![3]),
+            ("-1..1", vec![-1, 0, 1]),
+            ("1..64*2", vec![1, 2, 4, 8, 16, 32, 64]),
+            ("1..100*4", vec![1, 4, 16, 64]),
+            ("0..30+10", vec![0, 10, 20, 30]),
+            ("4k..16k*2", vec![4096, 8192, 16384]),
+            ("1,4..6,10", vec![1, 4, 5, 6, 10]),
+            ("-3..-1", vec![-3, -2, -1]),
+        ];
+        for x in &values_list {
+            match super::parse_values(x.0) {
+                Ok(v) => assert_eq!(v, x.1, "{x:?}"),
+                Err(e) => panic!("{e} {x:?}"),
+            }
+        }
+        let invalid_list = [
+            "",
+            "..",
+            "1..",
+            "..1",
+            "2..1",
+            "1..8*1",
+            "0..8*2",
+            "1..8+0",
+            "1..8-1",
+            "1..8*x",
+            "0..9999999",
+            "1,,2",
+        ];
+        for s in &invalid_list {
+            assert!(super::parse_values(s).is_err(), "{s}");
+        }
+    }
+}
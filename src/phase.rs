@@ -0,0 +1,228 @@
+use crate::param;
+use crate::Opt;
+
+// one step of phase schedule, e.g. "60:num_writer=2,fsync_write_paths=1"
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Phase {
+    pub(crate) duration: u64,     // seconds
+    pub(crate) num_reader: usize, // active threads in phase
+    pub(crate) num_writer: usize,
+    pub(crate) params: Vec<(param::Param, i64)>,
+}
+
+impl Phase {
+    // parameters other than thread counts
+    pub(crate) fn format_params(&self) -> String {
+        self.params
+            .iter()
+            .filter(|x| !matches!(x.0, param::Param::NumReader | param::Param::NumWriter))
+            .map(|x| format!("{}={}", x.0.get_name(), x.1))
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+}
+
+// thread counts not specified in phase follow options
+pub(crate) fn parse_phase(s: &str, opt: &Opt) -> std::io::Result<Phase> {
+    let (d, x) = match s.split_once(':') {
+        Some((d, x)) => (d, Some(x)),
+        None => (s, None),
+    };
+    let duration = match d.parse::<u64>() {
+        Ok(v) if v > 0 => v,
+        _ => return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+    };
+    let mut phase_opt = opt.clone();
+    let mut params = vec![];
+    for x in x.into_iter().flat_map(|x| x.split(',')) {
+        let (k, v) = match x.split_once('=') {
+            Some(v) => v,
+            None => return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+        };
+        let p = param::Param::new(k)?;
+        if !p.is_phase_param() {
+            return Err(std::io::Error::from(std::io::ErrorKind::Unsupported));
+        }
+        let v = param::parse_value(v)?;
+        p.apply(&mut phase_opt, v)?;
+        params.push((p, v));
+    }
+    Ok(Phase {
+        duration,
+        num_reader: phase_opt.num_reader,
+        num_writer: phase_opt.num_writer,
+        params,
+    })
+}
+
+// phases shared by worker threads, starts when created
+#[derive(Debug)]
+pub(crate) struct Schedule {
+    phases: Vec<Phase>,
+    opts: Vec<Opt>, // options of each phase
+    time_begin: std::time::Instant,
+    looped: bool,
+}
+
+impl Schedule {
+    pub(crate) fn new(opt: &Opt) -> Option<Self> {
+        if opt.phase.is_empty() {
+            return None;
+        }
+        let mut opts = vec![];
+        for x in &opt.phase {
+            // thread counts are not options in phase, gid decides reader or writer
+            let mut phase_opt = opt.clone();
+            for (p, v) in &x.params {
+                if !matches!(p, param::Param::NumReader | param::Param::NumWriter) {
+                    p.apply(&mut phase_opt, *v).unwrap(); // validated
+                }
+            }
+            opts.push(phase_opt);
+        }
+        Some(Self {
+            phases: opt.phase.clone(),
+            opts,
+            time_begin: std::time::Instant::now(),
+            looped: opt.phase_loop,
+        })
+    }
+
+    fn get_index_at(&self, elapsed: std::time::Duration) -> Option<usize> {
+        let total: u64 = self.phases.iter().map(|x| x.duration).sum();
+        let total = std::time::Duration::from_secs(total);
+        if !self.looped && elapsed >= total {
+            return None;
+        }
+        let mut t = std::time::Duration::from_nanos(
+            (elapsed.as_nanos() % total.as_nanos()).try_into().unwrap(),
+        );
+        for (i, x) in self.phases.iter().enumerate() {
+            let d = std::time::Duration::from_secs(x.duration);
+            if t < d {
+                return Some(i);
+            }
+            t -= d;
+        }
+        unreachable!();
+    }
+
+    // current phase, none once schedule ended
+    pub(crate) fn get_index(&self) -> Option<usize> {
+        self.get_index_at(self.time_begin.elapsed())
+    }
+
+    pub(crate) fn get_opt(&self, i: usize) -> &Opt {
+        &self.opts[i]
+    }
+
+    // first threads of each type are active
+    pub(crate) fn is_active(&self, i: usize, gid: usize, opt: &Opt) -> bool {
+        if gid < opt.num_reader {
+            gid < self.phases[i].num_reader
+        } else {
+            gid - opt.num_reader < self.phases[i].num_writer
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_parse_phase() {
+        let opt = crate::Opt {
+            num_reader: 4,
+            ..Default::default()
+        };
+        match super::parse_phase("60", &opt) {
+            Ok(v) => {
+                assert_eq!(v.duration, 60);
+                assert_eq!((v.num_reader, v.num_writer), (4, 0));
+                assert!(v.params.is_empty());
+                assert_eq!(v.format_params(), "");
+            }
+            Err(e) => panic!("{e}"),
+        }
+        match super::parse_phase("10:num_writer=2,fsync_write_paths=1,write_size=4k", &opt) {
+            Ok(v) => {
+                assert_eq!(v.duration, 10);
+                assert_eq!((v.num_reader, v.num_writer), (4, 2));
+                assert_eq!(v.params.len(), 3);
+                assert_eq!(v.format_params(), "fsync_write_paths=1,write_size=4096");
+            }
+            Err(e) => panic!("{e}"),
+        }
+        match super::parse_phase("30:num_reader=0", &opt) {
+            Ok(v) => assert_eq!((v.num_reader, v.num_writer), (0, 0)),
+            Err(e) => panic!("{e}"),
+        }
+        let invalid_list = [
+            "",
+            "0",
+            "-1",
+            "1.5",
+            "60:",
+            "60:num_writer",
+            "60:num_writer=-1",
+            "60:read_buffer_size=4k",
+            "60:time_second=1",
+            "60:xxx=1",
+        ];
+        for s in &invalid_list {
+            assert!(super::parse_phase(s, &opt).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_schedule() {
+        let mut opt = crate::Opt {
+            num_reader: 2,
+            num_writer: 2,
+            ..Default::default()
+        };
+        assert!(super::Schedule::new(&opt).is_none());
+        for s in [
+            "10:num_writer=0",
+            "20:num_reader=1,fsync_write_paths=1",
+            "5",
+        ] {
+            opt.phase.push(super::parse_phase(s, &opt).unwrap());
+        }
+
+        let x = super::Schedule::new(&opt).unwrap();
+        for (t, i) in [
+            (0, Some(0)),
+            (9, Some(0)),
+            (10, Some(1)),
+            (34, Some(2)),
+            (35, None),
+        ] {
+            assert_eq!(x.get_index_at(std::time::Duration::from_secs(t)), i, "{t}");
+        }
+        assert!(!x.get_opt(0).fsync_write_paths);
+        assert!(x.get_opt(1).fsync_write_paths);
+        assert_eq!(x.get_opt(1).num_reader, 2);
+
+        // readers are gid 0 and 1, writers are gid 2 and 3
+        let l = [
+            (0, [true, true, false, false]),
+            (1, [true, false, true, true]),
+            (2, [true, true, true, true]),
+        ];
+        for (i, v) in l {
+            for (gid, b) in v.iter().enumerate() {
+                assert_eq!(x.is_active(i, gid, &opt), *b, "{i} {gid}");
+            }
+        }
+
+        opt.phase_loop = true;
+        let x = super::Schedule::new(&opt).unwrap();
+        for (t, i) in [(35, 0), (45, 1), (105, 0), (135, 2)] {
+            assert_eq!(
+                x.get_index_at(std::time::Duration::from_secs(t)),
+                Some(i),
+                "{t}"
+            );
+        }
+    }
+}
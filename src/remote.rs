@@ -0,0 +1,318 @@
+use std::io::BufRead;
+use std::io::Write;
+
+use crate::flist;
+use crate::is_interrupted;
+use crate::set_interrupted;
+use crate::stat;
+use crate::util;
+use crate::worker;
+use crate::Opt;
+
+const DEFAULT_SERVER_HOST: &str = "127.0.0.1";
+
+// messages exchanged between controller and servers, one JSON per line
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+enum RemoteMessage {
+    Job(Box<Opt>, Vec<String>), // input flist encoded
+    Ready,
+    Start,
+    Interrupt,
+    Result(usize, usize, usize, usize, Vec<stat::ThreadStat>),
+    Error(String),
+}
+
+fn send_message(stream: &mut std::net::TcpStream, m: &RemoteMessage) -> std::io::Result<()> {
+    let mut s = serde_json::to_string(m)?;
+    s.push('\n');
+    stream.write_all(s.as_bytes())
+}
+
+fn recv_message(
+    reader: &mut std::io::BufReader<std::net::TcpStream>,
+) -> std::io::Result<RemoteMessage> {
+    let mut s = String::new();
+    if reader.read_line(&mut s)? == 0 {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof));
+    }
+    Ok(serde_json::from_str(&s)?)
+}
+
+fn handle_job(stream: std::net::TcpStream) -> std::io::Result<()> {
+    let mut reader = std::io::BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    let (opt, input) = match recv_message(&mut reader)? {
+        RemoteMessage::Job(opt, input) => (opt, input),
+        m => {
+            println!("Unexpected message {m:?}");
+            return Err(std::io::Error::from(std::io::ErrorKind::InvalidData));
+        }
+    };
+    log::info!("{opt:?}");
+    log::info!("input {input:?}");
+
+    // paths were resolved by controller, but must exist on this host as well
+    let mut l = vec![];
+    for f in &input {
+        match flist::decode_flist_path(f) {
+            Ok(x)
+                if util::is_abspath(&x)
+                    && util::get_raw_file_type(&x).is_ok_and(|t| t.is_dir()) =>
+            {
+                l.push(x);
+            }
+            _ => {
+                let s = format!("{f} not directory");
+                println!("{s}");
+                return send_message(&mut writer, &RemoteMessage::Error(s));
+            }
+        }
+    }
+    let input = l;
+
+    let mut w = writer.try_clone()?;
+    let mut listener = None;
+    let ret = worker::dispatch_worker_barrier(&input, &opt, || {
+        send_message(&mut w, &RemoteMessage::Ready)?;
+        match recv_message(&mut reader)? {
+            RemoteMessage::Start => (),
+            // another server failed before start
+            RemoteMessage::Interrupt => {
+                println!("Aborted by controller");
+                return Err(std::io::Error::from(std::io::ErrorKind::Interrupted));
+            }
+            m => {
+                println!("Unexpected message {m:?}");
+                return Err(std::io::Error::from(std::io::ErrorKind::ConnectionAborted));
+            }
+        }
+        // controller may interrupt workers while running
+        listener = Some(std::thread::spawn(move || {
+            while let Ok(m) = recv_message(&mut reader) {
+                if let RemoteMessage::Interrupt = m {
+                    log::info!("interrupted by controller");
+                    REMOTE_INTERRUPTED.store(true, std::sync::atomic::Ordering::Relaxed);
+                    set_interrupted(true);
+                }
+            }
+        }));
+        Ok(())
+    });
+    let res = match ret {
+        Ok((num_complete, num_interrupted, num_error, num_remain, tsv)) => send_message(
+            &mut writer,
+            &RemoteMessage::Result(num_complete, num_interrupted, num_error, num_remain, tsv),
+        ),
+        Err(e) => send_message(&mut writer, &RemoteMessage::Error(e.to_string())),
+    };
+
+    // stop listener before next job, so it can't interrupt that one
+    if let Some(h) = listener {
+        let _ = writer.shutdown(std::net::Shutdown::Read);
+        h.join().unwrap();
+    }
+    res
+}
+
+// port alone is on localhost, as jobs are accepted from anyone who connects
+pub(crate) fn get_server_address(s: &str) -> String {
+    if s.parse::<u16>().is_ok() {
+        format!("{DEFAULT_SERVER_HOST}:{s}")
+    } else {
+        s.to_string()
+    }
+}
+
+pub(crate) fn run_server(addr: &str) -> std::io::Result<()> {
+    let listener = std::net::TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?; // to notice SIGINT
+    println!("Listening on {}", listener.local_addr()?);
+    loop {
+        let (stream, peer) = match listener.accept() {
+            Ok(v) => v,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                if is_interrupted() {
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        stream.set_nonblocking(false)?;
+        println!("Job from {peer}");
+        log::info!("job from {peer}");
+        if let Err(e) = handle_job(stream) {
+            log::info!("{peer} {e}");
+            println!("{peer}: {e}");
+        }
+        println!("Job from {peer} done");
+        // keep serving unless interrupted locally
+        if is_interrupted() && !is_remote_interrupted() {
+            break;
+        }
+        set_interrupted(false);
+    }
+    Ok(())
+}
+
+static REMOTE_INTERRUPTED: std::sync::atomic::AtomicBool =
+    std::sync::atomic::AtomicBool::new(false);
+
+fn is_remote_interrupted() -> bool {
+    REMOTE_INTERRUPTED.swap(false, std::sync::atomic::Ordering::Relaxed)
+}
+
+fn connect_server(
+    addr: &str,
+    job: &RemoteMessage,
+    streamv: &mut Vec<std::net::TcpStream>,
+    readerv: &mut Vec<std::io::BufReader<std::net::TcpStream>>,
+) -> std::io::Result<()> {
+    let mut stream = std::net::TcpStream::connect(addr)?;
+    send_message(&mut stream, job)?;
+    readerv.push(std::io::BufReader::new(stream.try_clone()?));
+    streamv.push(stream);
+    Ok(())
+}
+
+// servers waiting for start give up the job on interrupt
+fn abort_servers(streamv: &mut [std::net::TcpStream]) {
+    for stream in streamv {
+        let _ = send_message(stream, &RemoteMessage::Interrupt);
+    }
+}
+
+pub(crate) fn dispatch_remote(
+    input: &[std::path::PathBuf],
+    opt: &Opt,
+) -> std::io::Result<(usize, usize, usize, usize, Vec<stat::ThreadStat>)> {
+    // number of readers and writers are 0 by default
+    if opt.num_reader == 0 && opt.num_writer == 0 {
+        return Ok((0, 0, 0, 0, vec![]));
+    }
+
+    let mut streamv = vec![];
+    let mut readerv = vec![];
+    for (i, addr) in opt.controller.iter().enumerate() {
+        // servers run the same job as local workers would, except that
+        // write paths base is unique per server in case of shared filesystem
+        // (still collected by --clean_write_paths with the original base)
+        let mut job_opt = opt.clone();
+        job_opt.controller.clear();
+        job_opt.write_paths_base = format!("{}{i}", opt.write_paths_base);
+        let input = input.iter().map(|f| flist::encode_flist_path(f)).collect();
+        let job = RemoteMessage::Job(Box::new(job_opt), input);
+
+        if let Err(e) = connect_server(addr, &job, &mut streamv, &mut readerv) {
+            println!("{addr}: {e}");
+            abort_servers(&mut streamv);
+            return Err(e);
+        }
+    }
+
+    // start all servers at once after all are ready
+    for (i, reader) in readerv.iter_mut().enumerate() {
+        let e = match recv_message(reader) {
+            Ok(RemoteMessage::Ready) => {
+                println!("{} ready", opt.controller[i]);
+                continue;
+            }
+            Ok(RemoteMessage::Error(s)) => {
+                println!("{}: {s}", opt.controller[i]);
+                std::io::Error::from(std::io::ErrorKind::ConnectionAborted)
+            }
+            Ok(m) => {
+                println!("{}: unexpected message {m:?}", opt.controller[i]);
+                std::io::Error::from(std::io::ErrorKind::InvalidData)
+            }
+            Err(e) => e,
+        };
+        abort_servers(&mut streamv);
+        return Err(e);
+    }
+    for stream in &mut streamv {
+        send_message(stream, &RemoteMessage::Start)?;
+    }
+
+    let resultv = std::thread::scope(|s| {
+        let mut hv = vec![];
+        for reader in &mut readerv {
+            hv.push(s.spawn(|| recv_message(reader)));
+        }
+        // forward SIGINT to servers
+        let mut forwarded = false;
+        while !hv.iter().all(std::thread::ScopedJoinHandle::is_finished) {
+            if is_interrupted() && !forwarded {
+                for stream in &mut streamv {
+                    let _ = send_message(stream, &RemoteMessage::Interrupt);
+                }
+                forwarded = true;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+        hv.into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Vec<std::io::Result<RemoteMessage>>>()
+    });
+
+    // aggregate results, label each thread with its server
+    let mut num_complete = 0;
+    let mut num_interrupted = 0;
+    let mut num_error = 0;
+    let mut num_remain = 0;
+    let mut tsv = vec![];
+    for (i, m) in resultv.into_iter().enumerate() {
+        let addr = &opt.controller[i];
+        match m? {
+            RemoteMessage::Result(a, b, c, d, l) => {
+                num_complete += a;
+                num_interrupted += b;
+                num_error += c;
+                num_remain += d;
+                for mut ts in l {
+                    ts.set_input_path(&format!("{addr}:{}", ts.get_input_path()));
+                    tsv.push(ts);
+                }
+            }
+            RemoteMessage::Error(s) => {
+                println!("{addr}: {s}");
+                num_error += opt.num_reader + opt.num_writer;
+            }
+            m => {
+                println!("{addr}: unexpected message {m:?}");
+                return Err(std::io::Error::from(std::io::ErrorKind::InvalidData));
+            }
+        }
+    }
+    Ok((num_complete, num_interrupted, num_error, num_remain, tsv))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_remote_message() {
+        let opt = crate::Opt {
+            num_reader: 3,
+            path_iter: crate::worker::PathIter::Random,
+            ..Default::default()
+        };
+        let m = super::RemoteMessage::Job(Box::new(opt), vec!["/path/to/\\xff".to_string()]);
+        let s = match serde_json::to_string(&m) {
+            Ok(v) => v,
+            Err(e) => panic!("{e}"),
+        };
+        assert!(!s.contains('\n'), "{s}");
+        match serde_json::from_str::<super::RemoteMessage>(&s) {
+            Ok(v) => assert_eq!(format!("{v:?}"), format!("{m:?}")),
+            Err(e) => panic!("{e} {s}"),
+        }
+    }
+
+    #[test]
+    fn test_get_server_address() {
+        assert_eq!(super::get_server_address("7000"), "127.0.0.1:7000");
+        assert_eq!(super::get_server_address("0.0.0.0:7000"), "0.0.0.0:7000");
+        assert_eq!(super::get_server_address("[::1]:7000"), "[::1]:7000");
+    }
+}
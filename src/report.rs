@@ -0,0 +1,497 @@
+use std::io::Write;
+
+use crate::flist;
+use crate::phase;
+use crate::stat;
+use crate::Opt;
+
+// aggregated metrics of a set
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Summary {
+    pub(crate) num_stat: usize,
+    pub(crate) num_read: usize,
+    pub(crate) num_read_bytes: usize,
+    pub(crate) num_write: usize,
+    pub(crate) num_write_bytes: usize,
+    #[serde(default)]
+    pub(crate) num_xattr_read: usize,
+    #[serde(default)]
+    pub(crate) num_xattr_write: usize,
+    pub(crate) ops_per_sec: f64,
+    pub(crate) mib_per_sec: f64,
+    pub(crate) latency_mean_us: f64,
+    pub(crate) latency_p50_us: f64,
+    pub(crate) latency_p90_us: f64,
+    pub(crate) latency_p99_us: f64,
+    pub(crate) latency_p999_us: f64,
+}
+
+impl Summary {
+    pub(crate) fn new(tsv: &[stat::ThreadStat]) -> Self {
+        let mut x = Self::default();
+        let mut h = stat::Histogram::default();
+        for ts in tsv {
+            x.num_stat += ts.get_num_stat();
+            x.num_read += ts.get_num_read();
+            x.num_read_bytes += ts.get_num_read_bytes();
+            x.num_write += ts.get_num_write();
+            x.num_write_bytes += ts.get_num_write_bytes();
+            x.num_xattr_read += ts.get_num_xattr_read();
+            x.num_xattr_write += ts.get_num_xattr_write();
+            // threads run concurrently, so sum of per thread rates
+            let sec = ts.get_time_sec();
+            if sec > 0.0 {
+                x.ops_per_sec += ts.get_num_ops() as f64 / sec;
+                x.mib_per_sec += ts.get_num_bytes() as f64 / f64::from(1 << 20) / sec;
+            }
+            h.merge(ts.get_latency());
+        }
+        x.latency_mean_us = h.get_mean() / 1000.0;
+        x.latency_p50_us = h.get_percentile(50.0) as f64 / 1000.0;
+        x.latency_p90_us = h.get_percentile(90.0) as f64 / 1000.0;
+        x.latency_p99_us = h.get_percentile(99.0) as f64 / 1000.0;
+        x.latency_p999_us = h.get_percentile(99.9) as f64 / 1000.0;
+        x
+    }
+
+    // metrics which can be compared
+    pub(crate) fn get_metrics(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("num_stat", self.num_stat as f64),
+            ("num_read", self.num_read as f64),
+            ("num_read_bytes", self.num_read_bytes as f64),
+            ("num_write", self.num_write as f64),
+            ("num_write_bytes", self.num_write_bytes as f64),
+            ("num_xattr_read", self.num_xattr_read as f64),
+            ("num_xattr_write", self.num_xattr_write as f64),
+            ("ops_per_sec", self.ops_per_sec),
+            ("mib_per_sec", self.mib_per_sec),
+            ("latency_mean_us", self.latency_mean_us),
+            ("latency_p50_us", self.latency_p50_us),
+            ("latency_p90_us", self.latency_p90_us),
+            ("latency_p99_us", self.latency_p99_us),
+            ("latency_p999_us", self.latency_p999_us),
+        ]
+    }
+}
+
+pub(crate) fn print_summary(x: &Summary) {
+    println!(
+        "total {:.0} ops/sec {:.2} MiB/sec, latency[us] mean {:.1} p50 {:.1} p90 {:.1} p99 {:.1} p99.9 {:.1}",
+        x.ops_per_sec,
+        x.mib_per_sec,
+        x.latency_mean_us,
+        x.latency_p50_us,
+        x.latency_p90_us,
+        x.latency_p99_us,
+        x.latency_p999_us
+    );
+}
+
+// summary of each phase, accumulated across loops of schedule
+pub(crate) fn get_phase_summary(tsv: &[stat::ThreadStat], n: usize) -> Vec<Summary> {
+    (0..n)
+        .map(|i| Summary::new(&tsv.iter().map(|x| x.get_phase(i)).collect::<Vec<_>>()))
+        .collect()
+}
+
+pub(crate) fn print_phase_table(phases: &[phase::Phase], l: &[Summary]) {
+    println!(
+        "{:>5} {:>6} {:>6} {:>6} {:>12} {:>12} {:>12} {:>12} params",
+        "phase", "sec", "reader", "writer", "ops/sec", "MiB/sec", "p50[us]", "p99[us]"
+    );
+    println!("{}", "-".repeat(6 + 7 * 3 + 13 * 4 + 6));
+    for (i, (p, x)) in phases.iter().zip(l).enumerate() {
+        let mut params = p.format_params();
+        if params.is_empty() {
+            params = "-".to_string();
+        }
+        println!(
+            "{:>5} {:>6} {:>6} {:>6} {:>12.0} {:>12.2} {:>12.1} {:>12.1} {}",
+            i + 1,
+            p.duration,
+            p.num_reader,
+            p.num_writer,
+            x.ops_per_sec,
+            x.mib_per_sec,
+            x.latency_p50_us,
+            x.latency_p99_us,
+            params
+        );
+    }
+}
+
+// Student's t for two-sided 95% confidence interval by degrees of freedom
+const T_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+const Z_95: f64 = 1.960;
+
+// statistics of a metric across sets
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct MetricStat {
+    pub(crate) n: usize,
+    pub(crate) mean: f64,
+    pub(crate) stddev: f64, // sample
+    pub(crate) min: f64,
+    pub(crate) max: f64,
+    pub(crate) cv: f64,   // percent
+    pub(crate) ci95: f64, // half width
+}
+
+impl MetricStat {
+    pub(crate) fn new(l: &[f64]) -> Self {
+        let n = l.len();
+        if n == 0 {
+            return Self::default();
+        }
+        let mean = l.iter().sum::<f64>() / n as f64;
+        let (stddev, ci95) = if n > 1 {
+            let var = l.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+            let t = T_95.get(n - 2).copied().unwrap_or(Z_95);
+            (var.sqrt(), t * var.sqrt() / (n as f64).sqrt())
+        } else {
+            (0.0, 0.0)
+        };
+        Self {
+            n,
+            mean,
+            stddev,
+            min: l.iter().copied().fold(f64::INFINITY, f64::min),
+            max: l.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            cv: if mean == 0.0 {
+                0.0
+            } else {
+                stddev / mean.abs() * 100.0
+            },
+            ci95,
+        }
+    }
+}
+
+pub(crate) fn get_set_stats(l: &[&SetReport]) -> Vec<(&'static str, MetricStat)> {
+    let mut v = vec![];
+    for (i, (k, _)) in Summary::default().get_metrics().iter().enumerate() {
+        let x: Vec<f64> = l.iter().map(|x| x.summary.get_metrics()[i].1).collect();
+        v.push((*k, MetricStat::new(&x)));
+    }
+    v
+}
+
+pub(crate) fn print_set_stats(l: &[(&'static str, MetricStat)]) {
+    let width = l.iter().map(|x| x.0.len()).max().unwrap_or(0);
+    println!(
+        "{:<width$} {:>16} {:>16} {:>16} {:>16} {:>8} {:>16}",
+        "metric", "mean", "stddev", "min", "max", "cv[%]", "95% CI"
+    );
+    println!("{}", "-".repeat(width + 17 * 5 + 9));
+    for (k, x) in l {
+        println!(
+            "{k:<width$} {:>16.2} {:>16.2} {:>16.2} {:>16.2} {:>8.2} {:>16}",
+            x.mean,
+            x.stddev,
+            x.min,
+            x.max,
+            x.cv,
+            format!("±{:.2}", x.ci95)
+        );
+    }
+}
+
+// sets of a value of the varied parameter
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) struct SweepReport {
+    pub(crate) param: String,
+    pub(crate) value: i64,
+    pub(crate) num_set: usize,
+    pub(crate) metrics: std::collections::BTreeMap<String, MetricStat>,
+}
+
+impl SweepReport {
+    fn get_metric(&self, k: &str) -> MetricStat {
+        self.metrics.get(k).cloned().unwrap_or_default()
+    }
+}
+
+// consecutive sets with the same parameter value are grouped
+pub(crate) fn get_sweep(name: &str, l: &[&SetReport]) -> Vec<SweepReport> {
+    let mut v = vec![];
+    let mut i = 0;
+    while i < l.len() {
+        let Some(value) = l[i].param_value else {
+            i += 1;
+            continue;
+        };
+        let n = l[i..]
+            .iter()
+            .take_while(|x| x.param_value == Some(value))
+            .count();
+        v.push(SweepReport {
+            param: name.to_string(),
+            value,
+            num_set: n,
+            metrics: get_set_stats(&l[i..i + n])
+                .into_iter()
+                .map(|(k, x)| (k.to_string(), x))
+                .collect(),
+        });
+        i += n;
+    }
+    v
+}
+
+// one row per value of the varied parameter as key, speedup is ops/sec
+// relative to the first value
+pub(crate) fn print_set_table(l: &[SweepReport]) {
+    let Some(first) = l.first() else {
+        return;
+    };
+    let width = std::cmp::max(first.param.len(), 8);
+    println!(
+        "{:>width$} {:>4} {:>12} {:>12} {:>12} {:>12} {:>12} {:>8}",
+        first.param, "sets", "ops/sec", "95% CI", "MiB/sec", "95% CI", "p99[us]", "speedup"
+    );
+    println!("{}", "-".repeat(width + 5 + 13 * 5 + 9));
+    let base = first.get_metric("ops_per_sec").mean;
+    for x in l {
+        let ops = x.get_metric("ops_per_sec");
+        let mibs = x.get_metric("mib_per_sec");
+        let speedup = if base > 0.0 {
+            format!("{:.2}x", ops.mean / base)
+        } else {
+            "-".to_string()
+        };
+        println!(
+            "{:>width$} {:>4} {:>12.0} {:>12} {:>12.2} {:>12} {:>12.1} {speedup:>8}",
+            x.value,
+            x.num_set,
+            ops.mean,
+            format!("±{:.0}", ops.ci95),
+            mibs.mean,
+            format!("±{:.2}", mibs.ci95),
+            x.get_metric("latency_p99_us").mean
+        );
+    }
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) struct SetReport {
+    pub(crate) param_value: Option<i64>, // if parameter varies across sets
+    #[serde(default)]
+    pub(crate) warmup: bool,
+    pub(crate) num_complete: usize,
+    pub(crate) num_interrupted: usize,
+    pub(crate) num_error: usize,
+    pub(crate) num_remain: usize,
+    pub(crate) summary: Summary,
+    #[serde(default)]
+    pub(crate) phases: Vec<Summary>, // if phases specified
+    pub(crate) threads: Vec<stat::ThreadStat>,
+}
+
+// results of a run, saved as JSON
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Report {
+    pub(crate) version: String,
+    pub(crate) input: Vec<String>, // flist encoded
+    pub(crate) opt: Opt,
+    pub(crate) sets: Vec<SetReport>,
+    #[serde(default)]
+    pub(crate) sweep: Vec<SweepReport>, // if parameter varies across sets
+}
+
+impl Report {
+    pub(crate) fn new(input: &[std::path::PathBuf], opt: &Opt) -> Self {
+        Self {
+            version: crate::get_version_string(),
+            input: input.iter().map(|f| flist::encode_flist_path(f)).collect(),
+            opt: opt.clone(),
+            sets: vec![],
+            sweep: vec![],
+        }
+    }
+
+    pub(crate) fn get_sets(&self) -> Vec<&SetReport> {
+        self.sets.iter().filter(|x| !x.warmup).collect()
+    }
+
+    // mean of sets other than warm-up
+    pub(crate) fn get_metrics(&self) -> Vec<(&'static str, f64)> {
+        get_set_stats(&self.get_sets())
+            .into_iter()
+            .map(|(k, x)| (k, x.mean))
+            .collect()
+    }
+}
+
+pub(crate) fn write_report_file(f: &std::path::Path, x: &Report) -> std::io::Result<()> {
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(f)?);
+    serde_json::to_writer_pretty(&mut writer, x)?;
+    writeln!(writer)?;
+    writer.flush()
+}
+
+pub(crate) fn load_report_file(f: &std::path::Path) -> std::io::Result<Report> {
+    let reader = std::io::BufReader::new(std::fs::File::open(f)?);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_report_get_metrics() {
+        let opt = crate::Opt::default();
+        let mut r = super::Report::new(&[std::path::PathBuf::from("/path/to/dir")], &opt);
+        for (_, x) in r.get_metrics() {
+            assert_eq!(x, 0.0);
+        }
+
+        for (i, x) in [1000.0, 100.0, 200.0].into_iter().enumerate() {
+            r.sets.push(super::SetReport {
+                param_value: None,
+                warmup: i == 0,
+                num_complete: 1,
+                num_interrupted: 0,
+                num_error: 0,
+                num_remain: 0,
+                summary: super::Summary {
+                    num_stat: 10,
+                    mib_per_sec: x,
+                    latency_p99_us: x / 10.0,
+                    ..Default::default()
+                },
+                phases: vec![],
+                threads: vec![],
+            });
+        }
+        let l = r.get_metrics();
+        assert_eq!(l.len(), super::Summary::default().get_metrics().len());
+        for (k, x) in l {
+            match k {
+                "num_stat" => assert_eq!(x, 10.0),
+                "mib_per_sec" => assert_eq!(x, 150.0),
+                "latency_p99_us" => assert_eq!(x, 15.0),
+                _ => assert_eq!(x, 0.0, "{k}"),
+            }
+        }
+
+        // round trip
+        let s = match serde_json::to_string(&r) {
+            Ok(v) => v,
+            Err(e) => panic!("{e}"),
+        };
+        match serde_json::from_str::<super::Report>(&s) {
+            Ok(v) => {
+                assert_eq!(v.sets.len(), 3);
+                assert_eq!(v.sets[1].summary, r.sets[1].summary);
+                assert!(v.sets[0].warmup);
+            }
+            Err(e) => panic!("{e} {s}"),
+        }
+
+        // result files prior to warm-up sets
+        let mut x: serde_json::Value = serde_json::from_str(&s).unwrap();
+        for set in x["sets"].as_array_mut().unwrap() {
+            set.as_object_mut().unwrap().remove("warmup");
+        }
+        match serde_json::from_value::<super::Report>(x) {
+            Ok(v) => assert!(v.sets.iter().all(|x| !x.warmup)),
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    #[test]
+    fn test_metric_stat() {
+        let x = super::MetricStat::new(&[]);
+        assert_eq!(x, super::MetricStat::default());
+
+        let x = super::MetricStat::new(&[5.0]);
+        assert_eq!(x.n, 1);
+        assert_eq!(x.mean, 5.0);
+        assert_eq!(x.stddev, 0.0);
+        assert_eq!(x.ci95, 0.0);
+        assert_eq!((x.min, x.max), (5.0, 5.0));
+
+        let x = super::MetricStat::new(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        assert_eq!(x.n, 8);
+        assert_eq!(x.mean, 5.0);
+        assert!((x.stddev - 2.138).abs() < 0.001, "{x:?}");
+        assert_eq!((x.min, x.max), (2.0, 9.0));
+        assert!((x.cv - 42.76).abs() < 0.01, "{x:?}");
+        // t(7) = 2.365
+        assert!(
+            (x.ci95 - 2.365 * 2.138 / 8f64.sqrt()).abs() < 0.001,
+            "{x:?}"
+        );
+
+        let x = super::MetricStat::new(&[0.0, 0.0]);
+        assert_eq!(x.cv, 0.0);
+    }
+
+    #[test]
+    fn test_get_sweep() {
+        let mut l = vec![];
+        for (v, x) in [(1, 100.0), (1, 200.0), (2, 300.0), (4, 500.0), (4, 700.0)] {
+            l.push(super::SetReport {
+                param_value: Some(v),
+                warmup: false,
+                num_complete: 1,
+                num_interrupted: 0,
+                num_error: 0,
+                num_remain: 0,
+                summary: super::Summary {
+                    ops_per_sec: x,
+                    ..Default::default()
+                },
+                phases: vec![],
+                threads: vec![],
+            });
+        }
+        let l: Vec<&super::SetReport> = l.iter().collect();
+        let v = super::get_sweep("num_reader", &l);
+        assert_eq!(v.len(), 3);
+        for (x, (value, num_set, mean)) in
+            v.iter().zip([(1, 2, 150.0), (2, 1, 300.0), (4, 2, 600.0)])
+        {
+            assert_eq!(x.param, "num_reader");
+            assert_eq!(x.value, value);
+            assert_eq!(x.num_set, num_set);
+            assert_eq!(x.get_metric("ops_per_sec").mean, mean);
+            assert_eq!(x.get_metric("ops_per_sec").n, num_set);
+            assert_eq!(
+                x.metrics.len(),
+                super::Summary::default().get_metrics().len()
+            );
+        }
+
+        // sets without parameter are ignored
+        let mut x = l[0].clone();
+        x.param_value = None;
+        assert!(super::get_sweep("num_reader", &[&x]).is_empty());
+    }
+
+    #[test]
+    fn test_get_phase_summary() {
+        let mut tsv = vec![
+            crate::stat::ThreadStat::newread(),
+            crate::stat::ThreadStat::newwrite(),
+        ];
+        for (i, ts) in tsv.iter_mut().enumerate() {
+            ts.set_phase(Some(i));
+            ts.inc_num_stat();
+            ts.add_latency(std::time::Duration::from_micros(10));
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            ts.set_phase(None);
+        }
+        let l = super::get_phase_summary(&tsv, 3);
+        assert_eq!(l.len(), 3);
+        for (i, x) in l.iter().take(2).enumerate() {
+            assert_eq!(x.num_stat, 1, "{i}");
+            assert!(x.ops_per_sec > 0.0, "{i}");
+            assert!(x.latency_p50_us > 0.0, "{i}");
+        }
+        assert_eq!(l[2], super::Summary::default());
+    }
+}
@@ -0,0 +1,213 @@
+use crate::Opt;
+
+const IOPRIO_CLASS_SHIFT: u32 = 13;
+const IOPRIO_CLASS_RT: u32 = 1;
+const IOPRIO_CLASS_BE: u32 = 2;
+const IOPRIO_CLASS_IDLE: u32 = 3;
+const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+const MAX_IOPRIO_LEVEL: u32 = 7;
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) enum IoPrio {
+    Idle,
+    BestEffort(u32),
+    Realtime(u32),
+}
+
+impl IoPrio {
+    fn get_value(&self) -> u32 {
+        match self {
+            IoPrio::Idle => IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+            IoPrio::BestEffort(x) => (IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT) | x,
+            IoPrio::Realtime(x) => (IOPRIO_CLASS_RT << IOPRIO_CLASS_SHIFT) | x,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub(crate) struct ThreadSched {
+    pub(crate) cpus: Vec<usize>,
+    pub(crate) nice: Option<i32>,
+    pub(crate) ioprio: Option<IoPrio>,
+}
+
+// e.g. "0-3,8,10-11"
+pub(crate) fn parse_cpu_list(s: &str) -> std::io::Result<Vec<usize>> {
+    let mut l = vec![];
+    for x in s.split(',') {
+        let (a, b) = match x.split_once('-') {
+            Some((a, b)) => (a, b),
+            None => (x, x),
+        };
+        let a = a
+            .parse::<usize>()
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+        let b = b
+            .parse::<usize>()
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+        if a > b || b >= libc::CPU_SETSIZE.try_into().unwrap() {
+            return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
+        }
+        for i in a..=b {
+            if !l.contains(&i) {
+                l.push(i);
+            }
+        }
+    }
+    Ok(l)
+}
+
+// e.g. "idle", "be:4", "rt:0"
+pub(crate) fn parse_ioprio(s: &str) -> std::io::Result<IoPrio> {
+    let (c, x) = match s.split_once(':') {
+        Some((c, x)) => (
+            c,
+            Some(
+                x.parse::<u32>()
+                    .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))?,
+            ),
+        ),
+        None => (s, None),
+    };
+    if let Some(x) = x {
+        if x > MAX_IOPRIO_LEVEL {
+            return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
+        }
+    }
+    match (c, x) {
+        ("idle", None) => Ok(IoPrio::Idle),
+        ("be", x) => Ok(IoPrio::BestEffort(x.unwrap_or(4))),
+        ("rt", x) => Ok(IoPrio::Realtime(x.unwrap_or(4))),
+        _ => Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_affinity(cpus: &[usize]) -> std::io::Result<()> {
+    let mut set = unsafe { std::mem::zeroed::<libc::cpu_set_t>() };
+    for &i in cpus {
+        unsafe { libc::CPU_SET(i, &mut set) };
+    }
+    // 0 means the calling thread
+    if unsafe { libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn set_nice(nice: i32) -> std::io::Result<()> {
+    // nice value is per thread on Linux
+    let tid = unsafe { libc::gettid() };
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, tid.try_into().unwrap(), nice) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn set_ioprio(ioprio: IoPrio) -> std::io::Result<()> {
+    // 0 means the calling thread
+    if unsafe {
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            0,
+            ioprio.get_value(),
+        )
+    } == -1
+    {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_affinity(_cpus: &[usize]) -> std::io::Result<()> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_nice(_nice: i32) -> std::io::Result<()> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_ioprio(_ioprio: IoPrio) -> std::io::Result<()> {
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+// apply reader or writer settings to the calling thread
+pub(crate) fn set_thread_sched(is_reader: bool, opt: &Opt) -> std::io::Result<()> {
+    let sched = if is_reader {
+        &opt.reader_sched
+    } else {
+        &opt.writer_sched
+    };
+    if !sched.cpus.is_empty() {
+        set_affinity(&sched.cpus)?;
+    }
+    if let Some(x) = sched.nice {
+        set_nice(x)?;
+    }
+    if let Some(x) = sched.ioprio {
+        set_ioprio(x)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_parse_cpu_list() {
+        let cpu_list = [
+            ("0", vec![0]),
+            ("3", vec![3]),
+            ("0,1", vec![0, 1]),
+            ("0-3", vec![0, 1, 2, 3]),
+            ("0-1,4,6-7", vec![0, 1, 4, 6, 7]),
+            ("2-2", vec![2]),
+            ("1,1,0-1", vec![1, 0]),
+        ];
+        for x in &cpu_list {
+            match super::parse_cpu_list(x.0) {
+                Ok(v) => assert_eq!(v, x.1, "{x:?}"),
+                Err(e) => panic!("{e} {x:?}"),
+            }
+        }
+        let invalid_list = ["", ",", "-1", "1-", "3-1", "x", "0,x", "1-2-3", "999999"];
+        for s in &invalid_list {
+            assert!(super::parse_cpu_list(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_parse_ioprio() {
+        let ioprio_list = [
+            ("idle", super::IoPrio::Idle),
+            ("be", super::IoPrio::BestEffort(4)),
+            ("be:0", super::IoPrio::BestEffort(0)),
+            ("be:7", super::IoPrio::BestEffort(7)),
+            ("rt", super::IoPrio::Realtime(4)),
+            ("rt:1", super::IoPrio::Realtime(1)),
+        ];
+        for x in &ioprio_list {
+            match super::parse_ioprio(x.0) {
+                Ok(v) => assert_eq!(v, x.1, "{x:?}"),
+                Err(e) => panic!("{e} {x:?}"),
+            }
+        }
+        let invalid_list = ["", "idle:0", "be:8", "rt:-1", "be:", "xxx", "xxx:0"];
+        for s in &invalid_list {
+            assert!(super::parse_ioprio(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_ioprio_get_value() {
+        assert_eq!(super::IoPrio::Idle.get_value(), 3 << 13);
+        assert_eq!(super::IoPrio::BestEffort(0).get_value(), 2 << 13);
+        assert_eq!(super::IoPrio::BestEffort(7).get_value(), (2 << 13) | 7);
+        assert_eq!(super::IoPrio::Realtime(3).get_value(), (1 << 13) | 3);
+    }
+}
@@ -1,4 +1,157 @@
-#[derive(Clone, Debug)]
+use std::fmt::Write;
+
+// histogram buckets per power of 2, values within 12.5% error
+const HISTOGRAM_SUB_BUCKET_BITS: u32 = 3;
+const HISTOGRAM_SUB_BUCKETS: u64 = 1 << HISTOGRAM_SUB_BUCKET_BITS;
+const HISTOGRAM_BUCKETS: usize = (64 - HISTOGRAM_SUB_BUCKET_BITS as usize + 1) << 3;
+
+// log-linear histogram of latency in nanoseconds
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Histogram {
+    #[serde(with = "sparse_buckets")]
+    buckets: Vec<u64>, // allocated on first add
+    count: u64,
+    sum: u64,
+    min: u64,
+    max: u64,
+}
+
+impl Histogram {
+    fn get_index(x: u64) -> usize {
+        if x < HISTOGRAM_SUB_BUCKETS {
+            return x.try_into().unwrap();
+        }
+        let e = 63 - x.leading_zeros();
+        let sub = (x >> (e - HISTOGRAM_SUB_BUCKET_BITS)) - HISTOGRAM_SUB_BUCKETS;
+        usize::try_from(e - HISTOGRAM_SUB_BUCKET_BITS + 1).unwrap()
+            * usize::try_from(HISTOGRAM_SUB_BUCKETS).unwrap()
+            + usize::try_from(sub).unwrap()
+    }
+
+    // largest value in bucket
+    fn get_value(i: usize) -> u64 {
+        let n = usize::try_from(HISTOGRAM_SUB_BUCKETS).unwrap();
+        if i < n {
+            return i.try_into().unwrap();
+        }
+        let e = u32::try_from(i / n).unwrap() + HISTOGRAM_SUB_BUCKET_BITS - 1;
+        let sub = u64::try_from(i % n).unwrap();
+        let shift = e - HISTOGRAM_SUB_BUCKET_BITS;
+        ((HISTOGRAM_SUB_BUCKETS + sub) << shift) + ((1 << shift) - 1)
+    }
+
+    pub(crate) fn add(&mut self, x: u64) {
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; HISTOGRAM_BUCKETS];
+        }
+        self.buckets[Self::get_index(x)] += 1;
+        if self.count == 0 || x < self.min {
+            self.min = x;
+        }
+        if x > self.max {
+            self.max = x;
+        }
+        self.count += 1;
+        self.sum = self.sum.saturating_add(x);
+    }
+
+    pub(crate) fn merge(&mut self, h: &Histogram) {
+        if h.count == 0 {
+            return;
+        }
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; HISTOGRAM_BUCKETS];
+        }
+        for (i, x) in h.buckets.iter().enumerate() {
+            self.buckets[i] += x;
+        }
+        if self.count == 0 || h.min < self.min {
+            self.min = h.min;
+        }
+        if h.max > self.max {
+            self.max = h.max;
+        }
+        self.count += h.count;
+        self.sum = self.sum.saturating_add(h.sum);
+    }
+
+    pub(crate) fn get_mean(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum as f64 / self.count as f64
+        }
+    }
+
+    // p in [0, 100]
+    pub(crate) fn get_percentile(&self, p: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((p / 100.0 * self.count as f64).ceil() as u64).clamp(1, self.count);
+        let mut n = 0;
+        for (i, x) in self.buckets.iter().enumerate() {
+            n += x;
+            if n >= rank {
+                return Self::get_value(i).clamp(self.min, self.max);
+            }
+        }
+        self.max
+    }
+}
+
+// non zero buckets as (index, count) pairs, as stats are sent every interval,
+// dense buckets of older result files are accepted as well
+mod sparse_buckets {
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Buckets {
+        Sparse(Vec<(usize, u64)>),
+        Dense(Vec<u64>),
+    }
+
+    pub(super) fn serialize<S: serde::Serializer>(v: &[u64], s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(v.iter().enumerate().filter(|x| *x.1 > 0))
+    }
+
+    pub(super) fn deserialize<'de, D: serde::Deserializer<'de>>(
+        d: D,
+    ) -> Result<Vec<u64>, D::Error> {
+        let l = match <Buckets as serde::Deserialize>::deserialize(d)? {
+            Buckets::Sparse(l) => l,
+            Buckets::Dense(v) => return Ok(v),
+        };
+        if l.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut v = vec![0; super::HISTOGRAM_BUCKETS];
+        for (i, n) in l {
+            match v.get_mut(i) {
+                Some(x) => *x = n,
+                None => return Err(serde::de::Error::custom(format!("bucket {i}"))),
+            }
+        }
+        Ok(v)
+    }
+}
+
+// counters of a phase, accumulated across loops of schedule
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub(crate) struct PhaseStat {
+    time: std::time::Duration,
+    num_stat: usize,
+    num_read: usize,
+    num_read_bytes: usize,
+    num_write: usize,
+    num_write_bytes: usize,
+    #[serde(default)]
+    num_xattr_read: usize,
+    #[serde(default)]
+    num_xattr_write: usize,
+    latency: Histogram,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub(crate) struct ThreadStat {
     is_reader: bool,
     input_path: String,
@@ -10,7 +163,30 @@ pub(crate) struct ThreadStat {
     num_read_bytes: usize,
     num_write: usize,
     num_write_bytes: usize,
+    num_write_paths: usize,
+    #[serde(default)]
+    num_xattr_read: usize, // listxattr(2) and getxattr(2)
+    #[serde(default)]
+    num_xattr_write: usize, // setxattr(2)
+    latency: Histogram,
+    #[serde(default)]
+    idle: std::time::Duration, // think time, off period of burst or phase
+    #[serde(default)]
+    paced: bool, // think time or burst
+    #[serde(default)]
+    open_loop: bool,
+    #[serde(default)]
+    backlog: usize, // operations overdue in open-loop
+    #[serde(default)]
+    max_backlog: usize,
+    #[serde(default)]
+    num_dropped: usize,
+    #[serde(default)]
+    phase: Vec<PhaseStat>,
+    #[serde(skip)]
+    phase_mark: Option<(usize, PhaseStat, std::time::Instant)>, // current phase
     pub(crate) done: bool,
+    pub(crate) failed: bool,
 }
 
 impl Default for ThreadStat {
@@ -26,7 +202,20 @@ impl Default for ThreadStat {
             num_read_bytes: 0,
             num_write: 0,
             num_write_bytes: 0,
+            num_write_paths: 0,
+            num_xattr_read: 0,
+            num_xattr_write: 0,
+            latency: Histogram::default(),
+            idle: std::time::Duration::ZERO,
+            paced: false,
+            open_loop: false,
+            backlog: 0,
+            max_backlog: 0,
+            num_dropped: 0,
+            phase: vec![],
+            phase_mark: None,
             done: false,
+            failed: false,
         }
     }
 }
@@ -52,10 +241,18 @@ impl ThreadStat {
         }
     }
 
+    pub(crate) fn is_reader(&self) -> bool {
+        self.is_reader
+    }
+
     pub(crate) fn is_ready(&self) -> bool {
         !self.input_path.is_empty()
     }
 
+    pub(crate) fn get_input_path(&self) -> &str {
+        &self.input_path
+    }
+
     pub(crate) fn set_input_path(&mut self, f: &str) {
         self.input_path = f.to_string();
     }
@@ -72,6 +269,130 @@ impl ThreadStat {
         self.time_begin.elapsed().unwrap()
     }
 
+    pub(crate) fn get_time_sec(&self) -> f64 {
+        match self.time_end.duration_since(self.time_begin) {
+            Ok(v) => v.as_secs_f64(),
+            Err(_) => 0.0,
+        }
+    }
+
+    pub(crate) fn add_latency(&mut self, d: std::time::Duration) {
+        let x = d.as_nanos().try_into().unwrap_or(u64::MAX);
+        self.latency.add(x);
+        if let Some((i, ..)) = &self.phase_mark {
+            self.phase[*i].latency.add(x);
+        }
+    }
+
+    // accumulate counters of current phase, and enter next one if any
+    pub(crate) fn set_phase(&mut self, i: Option<usize>) {
+        if self.phase_mark.as_ref().map(|x| x.0) == i {
+            return;
+        }
+        if let Some((j, mark, t)) = self.phase_mark.take() {
+            let x = &mut self.phase[j];
+            x.time += t.elapsed();
+            x.num_stat += self.num_stat - mark.num_stat;
+            x.num_read += self.num_read - mark.num_read;
+            x.num_read_bytes += self.num_read_bytes - mark.num_read_bytes;
+            x.num_write += self.num_write - mark.num_write;
+            x.num_write_bytes += self.num_write_bytes - mark.num_write_bytes;
+            x.num_xattr_read += self.num_xattr_read - mark.num_xattr_read;
+            x.num_xattr_write += self.num_xattr_write - mark.num_xattr_write;
+        }
+        if let Some(i) = i {
+            if self.phase.len() <= i {
+                self.phase.resize(i + 1, PhaseStat::default());
+            }
+            let mark = PhaseStat {
+                num_stat: self.num_stat,
+                num_read: self.num_read,
+                num_read_bytes: self.num_read_bytes,
+                num_write: self.num_write,
+                num_write_bytes: self.num_write_bytes,
+                num_xattr_read: self.num_xattr_read,
+                num_xattr_write: self.num_xattr_write,
+                ..Default::default()
+            };
+            self.phase_mark = Some((i, mark, std::time::Instant::now()));
+        }
+    }
+
+    pub(crate) fn get_phase_index(&self) -> Option<usize> {
+        self.phase_mark.as_ref().map(|x| x.0)
+    }
+
+    // stats of a phase as if it were a thread running only for the phase
+    pub(crate) fn get_phase(&self, i: usize) -> ThreadStat {
+        let x = self.phase.get(i).cloned().unwrap_or_default();
+        ThreadStat {
+            is_reader: self.is_reader,
+            input_path: self.input_path.clone(),
+            time_begin: std::time::UNIX_EPOCH,
+            time_end: std::time::UNIX_EPOCH + x.time,
+            num_stat: x.num_stat,
+            num_read: x.num_read,
+            num_read_bytes: x.num_read_bytes,
+            num_write: x.num_write,
+            num_write_bytes: x.num_write_bytes,
+            num_xattr_read: x.num_xattr_read,
+            num_xattr_write: x.num_xattr_write,
+            latency: x.latency,
+            done: self.done,
+            failed: self.failed,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn get_latency(&self) -> &Histogram {
+        &self.latency
+    }
+
+    pub(crate) fn add_idle_time(&mut self, d: std::time::Duration) {
+        self.idle += d;
+    }
+
+    pub(crate) fn get_idle_sec(&self) -> f64 {
+        self.idle.as_secs_f64()
+    }
+
+    pub(crate) fn set_paced(&mut self) {
+        self.paced = true;
+    }
+
+    pub(crate) fn is_paced(&self) -> bool {
+        self.paced
+    }
+
+    pub(crate) fn set_open_loop(&mut self) {
+        self.open_loop = true;
+    }
+
+    pub(crate) fn is_open_loop(&self) -> bool {
+        self.open_loop
+    }
+
+    pub(crate) fn set_backlog(&mut self, n: usize) {
+        self.backlog = n;
+        self.max_backlog = std::cmp::max(self.max_backlog, n);
+    }
+
+    pub(crate) fn get_backlog(&self) -> usize {
+        self.backlog
+    }
+
+    pub(crate) fn get_max_backlog(&self) -> usize {
+        self.max_backlog
+    }
+
+    pub(crate) fn add_num_dropped(&mut self, n: usize) {
+        self.num_dropped += n;
+    }
+
+    pub(crate) fn get_num_dropped(&self) -> usize {
+        self.num_dropped
+    }
+
     pub(crate) fn inc_num_repeat(&mut self) {
         self.num_repeat += 1;
     }
@@ -95,9 +416,149 @@ impl ThreadStat {
     pub(crate) fn add_num_write_bytes(&mut self, siz: usize) {
         self.num_write_bytes += siz;
     }
+
+    pub(crate) fn inc_num_xattr_read(&mut self) {
+        self.num_xattr_read += 1;
+    }
+
+    pub(crate) fn inc_num_xattr_write(&mut self) {
+        self.num_xattr_write += 1;
+    }
+
+    pub(crate) fn get_num_xattr_read(&self) -> usize {
+        self.num_xattr_read
+    }
+
+    pub(crate) fn get_num_xattr_write(&self) -> usize {
+        self.num_xattr_write
+    }
+
+    pub(crate) fn set_num_write_paths(&mut self, n: usize) {
+        self.num_write_paths = n;
+    }
+
+    pub(crate) fn get_num_write_paths(&self) -> usize {
+        self.num_write_paths
+    }
+
+    pub(crate) fn get_num_stat(&self) -> usize {
+        self.num_stat
+    }
+
+    pub(crate) fn get_num_read(&self) -> usize {
+        self.num_read
+    }
+
+    pub(crate) fn get_num_read_bytes(&self) -> usize {
+        self.num_read_bytes
+    }
+
+    pub(crate) fn get_num_write(&self) -> usize {
+        self.num_write
+    }
+
+    pub(crate) fn get_num_write_bytes(&self) -> usize {
+        self.num_write_bytes
+    }
+
+    pub(crate) fn get_num_ops(&self) -> usize {
+        self.num_stat + self.num_read + self.num_write + self.num_xattr_read + self.num_xattr_write
+    }
+
+    pub(crate) fn get_num_bytes(&self) -> usize {
+        self.num_read_bytes + self.num_write_bytes
+    }
+
+    // ops/sec and MiB/sec since previous snapshot of the same thread
+    pub(crate) fn get_rate(&self, prev: &ThreadStat) -> (f64, f64) {
+        if !self.is_ready() {
+            return (0.0, 0.0);
+        }
+        let (ops, bytes, t) = if prev.is_ready() {
+            (prev.get_num_ops(), prev.get_num_bytes(), prev.time_end)
+        } else {
+            (0, 0, self.time_begin)
+        };
+        let sec = match self.time_end.duration_since(t) {
+            Ok(v) => v.as_secs_f64(),
+            Err(_) => 0.0,
+        };
+        if sec <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let ops = self.get_num_ops().saturating_sub(ops) as f64 / sec;
+        let mib = self.get_num_bytes().saturating_sub(bytes) as f64 / f64::from(1 << 20);
+        (ops, mib / sec)
+    }
+}
+
+// per thread and total rates between consecutive snapshots
+#[derive(Debug, Default)]
+pub(crate) struct IntervalStat {
+    prev: Vec<ThreadStat>,
+    pub(crate) rate: Vec<(f64, f64)>, // ops/sec, MiB/sec
+    pub(crate) total: (f64, f64),
+}
+
+impl IntervalStat {
+    pub(crate) fn new(n: usize) -> Self {
+        Self {
+            prev: vec![ThreadStat::new(); n],
+            rate: vec![(0.0, 0.0); n],
+            total: (0.0, 0.0),
+        }
+    }
+
+    pub(crate) fn update(&mut self, tsv: &[ThreadStat]) {
+        assert_eq!(tsv.len(), self.prev.len());
+        self.total = (0.0, 0.0);
+        for (i, ts) in tsv.iter().enumerate() {
+            self.rate[i] = ts.get_rate(&self.prev[i]);
+            self.total.0 += self.rate[i].0;
+            self.total.1 += self.rate[i].1;
+            self.prev[i] = ts.clone();
+        }
+    }
+}
+
+// one CSV record per interval for plotting
+pub(crate) fn format_interval_header(n: usize) -> String {
+    let mut s = "set,time,elapsed,ops/sec,MiB/sec".to_string();
+    for i in 0..n {
+        write!(s, ",#{i} ops/sec,#{i} MiB/sec").unwrap();
+    }
+    s
+}
+
+pub(crate) fn format_interval_record(
+    set: usize,
+    t: std::time::SystemTime,
+    elapsed: std::time::Duration,
+    is: &IntervalStat,
+) -> String {
+    let t = t
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64();
+    let mut s = format!(
+        "{set},{t:.3},{:.3},{:.2},{:.2}",
+        elapsed.as_secs_f64(),
+        is.total.0,
+        is.total.1
+    );
+    for x in &is.rate {
+        write!(s, ",{:.2},{:.2}", x.0, x.1).unwrap();
+    }
+    s
 }
 
 pub(crate) fn print_stat(tsv: &Vec<ThreadStat>) {
+    print!("{}", format_stat(tsv));
+}
+
+pub(crate) fn format_stat(tsv: &Vec<ThreadStat>) -> String {
+    let mut out = String::new();
+
     // repeat
     let mut width_repeat = "repeat".len();
     for ts in tsv {
@@ -170,6 +631,20 @@ pub(crate) fn print_stat(tsv: &Vec<ThreadStat>) {
         }
     }
 
+    // idle if any thread is paced or idled
+    let idle = tsv.iter().any(|x| x.is_paced() || !x.idle.is_zero());
+    let mut num_idle = vec![0f64; tsv.len()];
+    for (i, t) in num_idle.iter_mut().enumerate() {
+        *t = f64::trunc(tsv[i].get_idle_sec() * 100.0) / 100.0; // cut decimals
+    }
+    let mut width_idle = "idle".len();
+    for t in &num_idle {
+        let s = t.to_string();
+        if s.len() > width_idle {
+            width_idle = s.len();
+        }
+    }
+
     // MiB/sec
     let mut num_mibs = vec![0f64; tsv.len()];
     for (i, x) in num_mibs.iter_mut().enumerate() {
@@ -185,6 +660,29 @@ pub(crate) fn print_stat(tsv: &Vec<ThreadStat>) {
         }
     }
 
+    // backlog, max_backlog, dropped if any thread is open-loop
+    let open_loop = tsv.iter().any(ThreadStat::is_open_loop);
+    let mut width_backlog = "backlog".len();
+    let mut width_max_backlog = "max_backlog".len();
+    let mut width_dropped = "dropped".len();
+    for ts in tsv {
+        width_backlog = std::cmp::max(width_backlog, ts.get_backlog().to_string().len());
+        width_max_backlog =
+            std::cmp::max(width_max_backlog, ts.get_max_backlog().to_string().len());
+        width_dropped = std::cmp::max(width_dropped, ts.get_num_dropped().to_string().len());
+    }
+
+    // xattr_read, xattr_write if any thread issued xattr ops
+    let xattr = tsv
+        .iter()
+        .any(|x| x.num_xattr_read > 0 || x.num_xattr_write > 0);
+    let mut width_xattr_read = "xattr_read".len();
+    let mut width_xattr_write = "xattr_write".len();
+    for ts in tsv {
+        width_xattr_read = std::cmp::max(width_xattr_read, ts.num_xattr_read.to_string().len());
+        width_xattr_write = std::cmp::max(width_xattr_write, ts.num_xattr_write.to_string().len());
+    }
+
     // path
     let mut width_path = "path".len();
     for ts in tsv {
@@ -205,14 +703,14 @@ pub(crate) fn print_stat(tsv: &Vec<ThreadStat>) {
     }
 
     let mut slen = 0;
-    print!("{}", " ".repeat(1 + width_index + 1));
+    write!(out, "{}", " ".repeat(1 + width_index + 1)).unwrap();
     slen += 1 + width_index + 1;
-    print!("{:<6} ", "type");
+    write!(out, "{:<6} ", "type").unwrap();
     slen += 6 + 1;
-    let ls = [
-        "repeat", "stat", "read", "read[B]", "write", "write[B]", "sec", "MiB/sec", "path",
+    let mut ls = vec![
+        "repeat", "stat", "read", "read[B]", "write", "write[B]", "sec",
     ];
-    let lw = [
+    let mut lw = vec![
         width_repeat,
         width_stat,
         width_read,
@@ -220,49 +718,86 @@ pub(crate) fn print_stat(tsv: &Vec<ThreadStat>) {
         width_write,
         width_write_bytes,
         width_sec,
-        width_mibs,
-        width_path,
     ];
+    if idle {
+        ls.push("idle");
+        lw.push(width_idle);
+    }
+    ls.push("MiB/sec");
+    lw.push(width_mibs);
+    if open_loop {
+        ls.extend(["backlog", "max_backlog", "dropped"]);
+        lw.extend([width_backlog, width_max_backlog, width_dropped]);
+    }
+    if xattr {
+        ls.extend(["xattr_read", "xattr_write"]);
+        lw.extend([width_xattr_read, width_xattr_write]);
+    }
+    ls.push("path");
+    lw.push(width_path);
     for (i, s) in ls.iter().enumerate() {
-        print!("{0:1$}", s, lw[i]);
+        write!(out, "{0:1$}", s, lw[i]).unwrap();
         slen += lw[i];
         if i != ls.len() - 1 {
-            print!(" ");
+            write!(out, " ").unwrap();
             slen += 1;
         }
     }
-    println!();
-    println!("{}", "-".repeat(slen));
+    writeln!(out).unwrap();
+    writeln!(out, "{}", "-".repeat(slen)).unwrap();
 
     for i in 0..nlines {
         // index (left align)
-        print!("#{i:<width_index$} ");
+        write!(out, "#{i:<width_index$} ").unwrap();
         // type
         if tsv[i].is_reader {
-            print!("reader ");
+            write!(out, "reader ").unwrap();
         } else {
-            print!("writer ");
+            write!(out, "writer ").unwrap();
         }
         // repeat
-        print!("{0:>1$} ", tsv[i].num_repeat, lw[0]);
+        write!(out, "{0:>1$} ", tsv[i].num_repeat, lw[0]).unwrap();
         // stat
-        print!("{0:>1$} ", tsv[i].num_stat, lw[1]);
+        write!(out, "{0:>1$} ", tsv[i].num_stat, lw[1]).unwrap();
         // read
-        print!("{0:>1$} ", tsv[i].num_read, lw[2]);
+        write!(out, "{0:>1$} ", tsv[i].num_read, lw[2]).unwrap();
         // read[B]
-        print!("{0:>1$} ", tsv[i].num_read_bytes, lw[3]);
+        write!(out, "{0:>1$} ", tsv[i].num_read_bytes, lw[3]).unwrap();
         // write
-        print!("{0:>1$} ", tsv[i].num_write, lw[4]);
+        write!(out, "{0:>1$} ", tsv[i].num_write, lw[4]).unwrap();
         // write[B]
-        print!("{0:>1$} ", tsv[i].num_write_bytes, lw[5]);
+        write!(out, "{0:>1$} ", tsv[i].num_write_bytes, lw[5]).unwrap();
         // sec
-        print!("{0:>1$} ", num_sec[i], lw[6]);
+        write!(out, "{0:>1$} ", num_sec[i], lw[6]).unwrap();
+        let mut j = 7;
+        if idle {
+            // idle
+            write!(out, "{0:>1$} ", num_idle[i], lw[j]).unwrap();
+            j += 1;
+        }
         // MiB/sec
-        print!("{0:>1$} ", num_mibs[i], lw[7]);
+        write!(out, "{0:>1$} ", num_mibs[i], lw[j]).unwrap();
+        j += 1;
+        if open_loop {
+            // backlog
+            write!(out, "{0:>1$} ", tsv[i].get_backlog(), lw[j]).unwrap();
+            // max_backlog
+            write!(out, "{0:>1$} ", tsv[i].get_max_backlog(), lw[j + 1]).unwrap();
+            // dropped
+            write!(out, "{0:>1$} ", tsv[i].get_num_dropped(), lw[j + 2]).unwrap();
+            j += 3;
+        }
+        if xattr {
+            // xattr_read
+            write!(out, "{0:>1$} ", tsv[i].num_xattr_read, lw[j]).unwrap();
+            // xattr_write
+            write!(out, "{0:>1$} ", tsv[i].num_xattr_write, lw[j + 1]).unwrap();
+        }
         // path (left align)
-        print!("{0:<1$} ", tsv[i].input_path, lw[8]);
-        println!();
+        write!(out, "{0:<1$} ", tsv[i].input_path, lw[lw.len() - 1]).unwrap();
+        writeln!(out).unwrap();
     }
+    out
 }
 
 #[cfg(test)]
@@ -401,4 +936,281 @@ mod tests {
         ts.add_num_write_bytes(0);
         assert_eq!(ts.num_write_bytes, siz * 2, "{}", ts.num_write);
     }
+
+    #[test]
+    fn test_get_rate() {
+        let mut prev = super::ThreadStat::newread();
+        let mut ts = super::ThreadStat::newread();
+        assert_eq!(ts.get_rate(&prev), (0.0, 0.0));
+
+        ts.set_input_path("/path/to/dir");
+        ts.time_begin = std::time::UNIX_EPOCH;
+        ts.time_end = std::time::UNIX_EPOCH + std::time::Duration::from_secs(2);
+        ts.num_stat = 10;
+        ts.num_read = 10;
+        ts.num_read_bytes = 1 << 21;
+        assert_eq!(ts.get_num_ops(), 20);
+        assert_eq!(ts.get_num_bytes(), 1 << 21);
+        assert_eq!(ts.get_rate(&prev), (10.0, 1.0));
+
+        prev = ts.clone();
+        ts.time_end += std::time::Duration::from_secs(4);
+        ts.num_write = 20;
+        ts.num_write_bytes = 1 << 22;
+        assert_eq!(ts.get_rate(&prev), (5.0, 1.0));
+
+        // no time elapsed
+        assert_eq!(ts.get_rate(&ts), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_interval_stat() {
+        let mut tsv = vec![super::ThreadStat::newread(), super::ThreadStat::newwrite()];
+        let mut is = super::IntervalStat::new(tsv.len());
+        is.update(&tsv);
+        assert_eq!(is.rate, vec![(0.0, 0.0), (0.0, 0.0)]);
+        assert_eq!(is.total, (0.0, 0.0));
+
+        for ts in &mut tsv {
+            ts.set_input_path("/path/to/dir");
+            ts.time_begin = std::time::UNIX_EPOCH;
+            ts.time_end = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1);
+        }
+        tsv[0].num_read = 4;
+        tsv[0].num_read_bytes = 1 << 20;
+        tsv[1].num_write = 2;
+        is.update(&tsv);
+        assert_eq!(is.rate, vec![(4.0, 1.0), (2.0, 0.0)]);
+        assert_eq!(is.total, (6.0, 1.0));
+
+        tsv[0].time_end += std::time::Duration::from_secs(2);
+        tsv[0].num_read = 8;
+        is.update(&tsv);
+        assert_eq!(is.rate, vec![(2.0, 0.0), (0.0, 0.0)]);
+        assert_eq!(is.total, (2.0, 0.0));
+
+        assert_eq!(
+            super::format_interval_header(2),
+            "set,time,elapsed,ops/sec,MiB/sec,#0 ops/sec,#0 MiB/sec,#1 ops/sec,#1 MiB/sec"
+        );
+        assert_eq!(
+            super::format_interval_record(
+                1,
+                std::time::UNIX_EPOCH + std::time::Duration::from_millis(1500),
+                std::time::Duration::from_millis(250),
+                &is
+            ),
+            "1,1.500,0.250,2.00,0.00,2.00,0.00,0.00,0.00"
+        );
+    }
+
+    #[test]
+    fn test_histogram_index() {
+        assert_eq!(super::Histogram::get_index(0), 0);
+        assert_eq!(super::Histogram::get_index(7), 7);
+        assert_eq!(super::Histogram::get_index(8), 8);
+        assert_eq!(super::Histogram::get_index(15), 15);
+        assert_eq!(super::Histogram::get_index(16), 16);
+        assert_eq!(super::Histogram::get_index(17), 16);
+        assert_eq!(super::Histogram::get_index(18), 17);
+        assert_eq!(
+            super::Histogram::get_index(u64::MAX),
+            super::HISTOGRAM_BUCKETS - 1
+        );
+        assert_eq!(
+            super::Histogram::get_value(super::HISTOGRAM_BUCKETS - 1),
+            u64::MAX
+        );
+
+        // every value within its bucket and 12.5% error
+        for x in (0..100_000).chain([1 << 40, (1 << 40) + 12345, u64::MAX / 3]) {
+            let i = super::Histogram::get_index(x);
+            let v = super::Histogram::get_value(i);
+            assert!(v >= x, "{x} {i} {v}");
+            assert!((v - x) as f64 <= x as f64 / 8.0, "{x} {i} {v}");
+            if i > 0 {
+                assert!(super::Histogram::get_value(i - 1) < x, "{x} {i}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_histogram_percentile() {
+        let mut h = super::Histogram::default();
+        assert_eq!(h.count, 0);
+        assert_eq!(h.get_percentile(99.0), 0);
+        assert_eq!(h.get_mean(), 0.0);
+
+        for x in 1..=100 {
+            h.add(x);
+        }
+        assert_eq!(h.count, 100);
+        assert_eq!(h.get_mean(), 50.5);
+        assert_eq!(h.get_percentile(0.0), 1);
+        assert_eq!(h.get_percentile(100.0), 100);
+        let p50 = h.get_percentile(50.0);
+        assert!((50..=57).contains(&p50), "{p50}");
+        let p99 = h.get_percentile(99.0);
+        assert!((99..=100).contains(&p99), "{p99}");
+
+        let mut h2 = super::Histogram::default();
+        h2.add(1000);
+        h2.merge(&h);
+        h2.merge(&super::Histogram::default());
+        assert_eq!(h2.count, 101);
+        assert_eq!(h2.get_percentile(100.0), 1000);
+        assert_eq!(h2.get_percentile(0.0), 1);
+
+        // sparse round trip, and dense buckets of older results
+        let x = serde_json::to_string(&h2).unwrap();
+        assert!(x.len() < 1000, "{x}");
+        let h3: super::Histogram = serde_json::from_str(&x).unwrap();
+        assert_eq!(h3.buckets, h2.buckets);
+        assert_eq!(h3.get_percentile(50.0), h2.get_percentile(50.0));
+        let x = serde_json::to_string(&super::Histogram::default()).unwrap();
+        let h3: super::Histogram = serde_json::from_str(&x).unwrap();
+        assert!(h3.buckets.is_empty());
+        let x = format!(
+            r#"{{"buckets":{},"count":101,"sum":6050,"min":1,"max":1000}}"#,
+            serde_json::to_string(&h2.buckets).unwrap()
+        );
+        let h3: super::Histogram = serde_json::from_str(&x).unwrap();
+        assert_eq!(h3.buckets, h2.buckets);
+        assert!(serde_json::from_str::<super::Histogram>(
+            r#"{"buckets":[[9999,1]],"count":1,"sum":1,"min":1,"max":1}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_set_phase() {
+        let mut ts = super::ThreadStat::newread();
+        ts.inc_num_stat(); // not in any phase
+        ts.set_phase(Some(1));
+        ts.inc_num_stat();
+        ts.inc_num_read();
+        ts.add_num_read_bytes(10);
+        ts.add_latency(std::time::Duration::from_micros(5));
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        ts.set_phase(Some(0));
+        ts.inc_num_stat();
+        ts.set_phase(Some(1));
+        ts.inc_num_read();
+        ts.set_phase(None);
+        ts.inc_num_stat();
+        ts.set_phase(None);
+
+        let x = ts.get_phase(0);
+        assert!(x.is_reader());
+        assert_eq!(x.get_num_stat(), 1);
+        assert_eq!(x.get_num_read(), 0);
+        assert_eq!(x.get_latency().count, 0);
+
+        let x = ts.get_phase(1);
+        assert_eq!(x.get_num_stat(), 1);
+        assert_eq!(x.get_num_read(), 2);
+        assert_eq!(x.get_num_read_bytes(), 10);
+        assert_eq!(x.get_latency().count, 1);
+        assert!(x.get_time_sec() >= 0.01);
+
+        // totals are unaffected
+        assert_eq!(ts.get_num_stat(), 4);
+        assert_eq!(ts.get_num_read(), 2);
+        assert_eq!(ts.get_latency().count, 1);
+
+        let x = ts.get_phase(2);
+        assert_eq!(x.get_num_ops(), 0);
+        assert_eq!(x.get_time_sec(), 0.0);
+    }
+
+    #[test]
+    fn test_add_idle_time() {
+        let mut ts = super::ThreadStat::newread();
+        assert_eq!(ts.get_idle_sec(), 0.0);
+        ts.add_idle_time(std::time::Duration::from_millis(500));
+        ts.add_idle_time(std::time::Duration::from_millis(250));
+        assert_eq!(ts.get_idle_sec(), 0.75);
+    }
+
+    #[test]
+    fn test_set_backlog() {
+        let mut ts = super::ThreadStat::newread();
+        assert!(!ts.is_open_loop());
+        ts.set_open_loop();
+        assert!(ts.is_open_loop());
+        ts.set_backlog(3);
+        ts.set_backlog(1);
+        assert_eq!(ts.get_backlog(), 1);
+        assert_eq!(ts.get_max_backlog(), 3);
+        ts.add_num_dropped(2);
+        ts.add_num_dropped(5);
+        assert_eq!(ts.get_num_dropped(), 7);
+    }
+
+    #[test]
+    fn test_format_stat_open_loop() {
+        let mut tsv = vec![super::ThreadStat::newread()];
+        tsv[0].set_input_path("/path/to/dir");
+        let s = super::format_stat(&tsv);
+        assert!(!s.contains("backlog"), "{s}");
+
+        tsv[0].set_open_loop();
+        tsv[0].add_num_dropped(12345);
+        let s = super::format_stat(&tsv);
+        let l: Vec<&str> = s.lines().collect();
+        assert!(l[0].contains(" backlog max_backlog dropped path"), "{s}");
+        assert!(l[2].contains(" 12345 /path/to/dir"), "{s}");
+    }
+
+    #[test]
+    fn test_format_stat_idle() {
+        let mut tsv = vec![super::ThreadStat::newread(), super::ThreadStat::newread()];
+        tsv[0].set_input_path("/path/to/dir");
+        tsv[1].set_input_path("/path/to/dir");
+        let s = super::format_stat(&tsv);
+        assert!(!s.contains("idle"), "{s}");
+        assert!(
+            s.lines().next().unwrap().contains(" sec MiB/sec path"),
+            "{s}"
+        );
+
+        tsv[1].set_paced();
+        let s = super::format_stat(&tsv);
+        assert!(
+            s.lines().next().unwrap().contains(" sec idle MiB/sec path"),
+            "{s}"
+        );
+
+        tsv[1] = super::ThreadStat::newread();
+        tsv[1].set_input_path("/path/to/dir");
+        tsv[1].add_idle_time(std::time::Duration::from_millis(1500));
+        let s = super::format_stat(&tsv);
+        let l: Vec<&str> = s.lines().collect();
+        assert!(l[0].contains(" sec idle MiB/sec path"), "{s}");
+        assert!(l[3].contains(" 1.5 "), "{s}");
+    }
+
+    #[test]
+    fn test_format_stat_xattr() {
+        let mut tsv = vec![super::ThreadStat::newread(), super::ThreadStat::newwrite()];
+        tsv[0].set_input_path("/path/to/dir");
+        tsv[1].set_input_path("/path/to/dir");
+        let s = super::format_stat(&tsv);
+        assert!(!s.contains("xattr"), "{s}");
+
+        for _ in 0..3 {
+            tsv[0].inc_num_xattr_read();
+        }
+        tsv[1].inc_num_xattr_write();
+        assert_eq!(tsv[0].get_num_xattr_read(), 3);
+        assert_eq!(tsv[0].get_num_ops(), 3);
+        assert_eq!(tsv[1].get_num_xattr_write(), 1);
+
+        tsv[1].set_open_loop();
+        let s = super::format_stat(&tsv);
+        let l: Vec<&str> = s.lines().collect();
+        assert!(l[0].contains(" dropped xattr_read xattr_write path"), "{s}");
+        assert!(l[2].contains("          3           0 /path/to/dir"), "{s}");
+        assert!(l[3].contains("          0           1 /path/to/dir"), "{s}");
+    }
 }
@@ -0,0 +1,271 @@
+use std::fmt::Write as _;
+use std::io::Write;
+
+use crate::is_paused;
+use crate::set_interrupted;
+use crate::set_paused;
+use crate::stat;
+use crate::Opt;
+
+const MAX_HISTORY: usize = 60;
+const SPARK_CHARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// number of lines other than per thread rows
+const NUM_HEADER_LINES: usize = 10;
+
+#[derive(Debug)]
+pub(crate) struct Tui {
+    termios: Option<libc::termios>, // restored on drop
+    time_begin: std::time::Instant,
+    ops_history: std::collections::VecDeque<f64>,
+    mibs_history: std::collections::VecDeque<f64>,
+    message: String,
+}
+
+impl Tui {
+    pub(crate) fn new() -> Self {
+        // keys are only available if stdin is a terminal
+        let mut termios = None;
+        if unsafe { libc::isatty(libc::STDIN_FILENO) } == 1 {
+            let mut t = unsafe { std::mem::zeroed::<libc::termios>() };
+            if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut t) } == 0 {
+                let mut raw = t;
+                raw.c_lflag &= !(libc::ICANON | libc::ECHO); // keep ISIG for Ctrl-C
+                raw.c_cc[libc::VMIN] = 0;
+                raw.c_cc[libc::VTIME] = 0;
+                if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } == 0 {
+                    termios = Some(t);
+                }
+            }
+        }
+        // alternate screen, hide cursor
+        print!("\x1b[?1049h\x1b[?25l");
+        let _ = std::io::stdout().flush();
+        Self {
+            termios,
+            time_begin: std::time::Instant::now(),
+            ops_history: std::collections::VecDeque::new(),
+            mibs_history: std::collections::VecDeque::new(),
+            message: String::new(),
+        }
+    }
+
+    fn read_key(&self) -> Option<u8> {
+        self.termios?;
+        let mut b = [0u8; 1];
+        let n = unsafe { libc::read(libc::STDIN_FILENO, b.as_mut_ptr().cast(), 1) };
+        if n == 1 {
+            Some(b[0])
+        } else {
+            None
+        }
+    }
+
+    // returns true if dashboard needs to be redrawn
+    pub(crate) fn handle_keys(&mut self, tsv: &Vec<stat::ThreadStat>) -> bool {
+        let mut redraw = false;
+        while let Some(k) = self.read_key() {
+            match k {
+                b'p' => {
+                    set_paused(!is_paused());
+                    self.message = if is_paused() {
+                        "Paused".to_string()
+                    } else {
+                        "Resumed".to_string()
+                    };
+                    log::info!("{}", self.message);
+                }
+                b's' => {
+                    self.message = match save_snapshot(tsv) {
+                        Ok(v) => format!("Saved snapshot to {v}"),
+                        Err(e) => format!("Failed to save snapshot: {e}"),
+                    };
+                    log::info!("{}", self.message);
+                }
+                b'q' => {
+                    // same as SIGINT, workers resume to exit
+                    set_interrupted(true);
+                    set_paused(false);
+                    self.message = "Interrupted".to_string();
+                    log::info!("{}", self.message);
+                }
+                _ => continue,
+            }
+            redraw = true;
+        }
+        redraw
+    }
+
+    // called every monitor interval
+    pub(crate) fn update(&mut self, is: &stat::IntervalStat) {
+        for (h, x) in [
+            (&mut self.ops_history, is.total.0),
+            (&mut self.mibs_history, is.total.1),
+        ] {
+            h.push_back(x);
+            if h.len() > MAX_HISTORY {
+                h.pop_front();
+            }
+        }
+    }
+
+    pub(crate) fn draw(&self, tsv: &[stat::ThreadStat], is: &stat::IntervalStat, opt: &Opt) {
+        let (width, height) = get_terminal_size();
+        let mut out = String::new();
+        out.push_str("\x1b[H\x1b[2J"); // home, clear
+
+        // state and time
+        let elapsed = self.time_begin.elapsed().as_secs();
+        let state = if is_paused() { "paused" } else { "running" };
+        let mut s = format!("dirload {state}, elapsed {}", format_sec(elapsed));
+        if opt.time_second > 0 {
+            let remain = opt.time_second.saturating_sub(elapsed);
+            s.push_str(&format!(", remaining {}", format_sec(remain)));
+        }
+        writeln!(out, "{s}").unwrap();
+
+        // aggregate
+        let num_done = tsv.iter().filter(|x| x.done).count();
+        let num_error = tsv.iter().filter(|x| x.failed).count();
+        let (ops, mibs) = is.total;
+        writeln!(
+            out,
+            "threads {} done {num_done} error {num_error}, total {ops:.0} ops/sec {mibs:.2} MiB/sec",
+            tsv.len()
+        )
+        .unwrap();
+        writeln!(out).unwrap();
+
+        // sparklines
+        let n = width.saturating_sub(12).min(MAX_HISTORY);
+        writeln!(out, "ops/sec  {}", get_sparkline(&self.ops_history, n)).unwrap();
+        writeln!(out, "MiB/sec  {}", get_sparkline(&self.mibs_history, n)).unwrap();
+        writeln!(out).unwrap();
+
+        // per thread rates, as many as fit
+        let width_index = tsv.len().saturating_sub(1).to_string().len();
+        writeln!(
+            out,
+            "{} type   state {:>10} {:>10} path",
+            " ".repeat(1 + width_index),
+            "ops/sec",
+            "MiB/sec"
+        )
+        .unwrap();
+        let nrows = height.saturating_sub(NUM_HEADER_LINES).max(1);
+        for (i, ts) in tsv.iter().enumerate().take(nrows) {
+            let t = if ts.is_reader() { "reader" } else { "writer" };
+            let state = if ts.failed {
+                "error"
+            } else if ts.done {
+                "done"
+            } else if !ts.is_ready() {
+                "-"
+            } else {
+                "run"
+            };
+            let mut s = format!(
+                "#{i:<width_index$} {t} {state:<5} {:>10.0} {:>10.2} {}",
+                is.rate[i].0,
+                is.rate[i].1,
+                ts.get_input_path()
+            );
+            s.truncate(s.char_indices().nth(width).map_or(s.len(), |x| x.0));
+            writeln!(out, "{s}").unwrap();
+        }
+        if tsv.len() > nrows {
+            writeln!(out, "... {} more", tsv.len() - nrows).unwrap();
+        }
+
+        writeln!(out).unwrap();
+        writeln!(out, "[p] pause/resume  [s] snapshot  [q] interrupt").unwrap();
+        if !self.message.is_empty() {
+            writeln!(out, "{}", self.message).unwrap();
+        }
+        print!("{out}");
+        let _ = std::io::stdout().flush();
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        if let Some(t) = &self.termios {
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, t) };
+        }
+        // show cursor, leave alternate screen
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = std::io::stdout().flush();
+    }
+}
+
+fn get_terminal_size() -> (usize, usize) {
+    let mut ws = unsafe { std::mem::zeroed::<libc::winsize>() };
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) } == 0
+        && ws.ws_col > 0
+        && ws.ws_row > 0
+    {
+        (ws.ws_col.into(), ws.ws_row.into())
+    } else {
+        (80, 24)
+    }
+}
+
+fn format_sec(sec: u64) -> String {
+    format!("{:02}:{:02}:{:02}", sec / 3600, sec / 60 % 60, sec % 60)
+}
+
+// last n values scaled by max of them
+fn get_sparkline(h: &std::collections::VecDeque<f64>, n: usize) -> String {
+    let l: Vec<f64> = h.iter().skip(h.len().saturating_sub(n)).copied().collect();
+    let max = l.iter().copied().fold(0.0, f64::max);
+    let mut s = String::new();
+    for x in &l {
+        let i = if max > 0.0 {
+            (x / max * (SPARK_CHARS.len() - 1) as f64).round() as usize
+        } else {
+            0
+        };
+        s.push(SPARK_CHARS[i.min(SPARK_CHARS.len() - 1)]);
+    }
+    s
+}
+
+// stats table as printed after workers exit, in current directory
+fn save_snapshot(tsv: &Vec<stat::ThreadStat>) -> std::io::Result<String> {
+    if tsv.iter().any(|x| !x.is_ready()) {
+        return Err(std::io::Error::from(std::io::ErrorKind::WouldBlock));
+    }
+    let t = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    let f = format!("dirload_snapshot_{t}.txt");
+    std::fs::write(&f, stat::format_stat(tsv))?;
+    Ok(f)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_get_sparkline() {
+        let h = std::collections::VecDeque::from(vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+        assert_eq!(super::get_sparkline(&h, 8), "▁▂▃▄▅▆▇█");
+        assert_eq!(super::get_sparkline(&h, 2), "▇█");
+        assert_eq!(super::get_sparkline(&h, 100), "▁▂▃▄▅▆▇█");
+        assert_eq!(super::get_sparkline(&h, 0), "");
+
+        let h = std::collections::VecDeque::from(vec![0.0, 0.0, 0.0]);
+        assert_eq!(super::get_sparkline(&h, 3), "▁▁▁");
+
+        let h = std::collections::VecDeque::new();
+        assert_eq!(super::get_sparkline(&h, 3), "");
+    }
+
+    #[test]
+    fn test_format_sec() {
+        assert_eq!(super::format_sec(0), "00:00:00");
+        assert_eq!(super::format_sec(59), "00:00:59");
+        assert_eq!(super::format_sec(61), "00:01:01");
+        assert_eq!(super::format_sec(3600 + 120 + 3), "01:02:03");
+    }
+}
@@ -0,0 +1,219 @@
+pub(crate) const MAX_IO_DEPTH: usize = 1 << 10;
+
+// io_uring instance of a thread with its buffer registered as fixed buffer 0
+pub(crate) struct Ring {
+    ring: io_uring::IoUring,
+    depth: usize,
+}
+
+impl std::fmt::Debug for Ring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Ring").field("depth", &self.depth).finish()
+    }
+}
+
+impl Ring {
+    // buffer must not be reallocated or dropped while ring exists
+    pub(crate) fn new(depth: usize, buf: &mut [u8]) -> std::io::Result<Self> {
+        assert!(depth > 0 && depth <= MAX_IO_DEPTH);
+        let ring = io_uring::IoUring::new(depth.try_into().unwrap())?;
+        if !buf.is_empty() {
+            let iov = libc::iovec {
+                iov_base: buf.as_mut_ptr().cast(),
+                iov_len: buf.len(),
+            };
+            unsafe { ring.submitter().register_buffers(&[iov])? };
+        }
+        Ok(Self { ring, depth })
+    }
+
+    pub(crate) fn get_depth(&self) -> usize {
+        self.depth
+    }
+
+    // submit entries up to depth at a time and wait for all of them,
+    // returns results in order of entries, negative errno on failure
+    pub(crate) fn submit_all(
+        &mut self,
+        l: Vec<io_uring::squeue::Entry>,
+    ) -> std::io::Result<Vec<i32>> {
+        let mut resv = vec![0; l.len()];
+        for (i, x) in l.chunks(self.depth).enumerate() {
+            let base = i * self.depth;
+            for (j, e) in x.iter().enumerate() {
+                let e = e.clone().user_data((base + j).try_into().unwrap());
+                // memory referred by entries is owned by caller until return
+                if unsafe { self.ring.submission().push(&e) }.is_err() {
+                    return Err(std::io::Error::from(std::io::ErrorKind::OutOfMemory));
+                }
+            }
+            let mut n = 0;
+            while n < x.len() {
+                match self.ring.submit_and_wait(x.len() - n) {
+                    Ok(_) => (),
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+                    Err(e) => return Err(e),
+                }
+                for cqe in self.ring.completion() {
+                    resv[usize::try_from(cqe.user_data()).unwrap()] = cqe.result();
+                    n += 1;
+                }
+            }
+        }
+        Ok(resv)
+    }
+}
+
+pub(crate) fn get_result(res: i32) -> std::io::Result<usize> {
+    if res < 0 {
+        Err(std::io::Error::from_raw_os_error(-res))
+    } else {
+        Ok(res.try_into().unwrap())
+    }
+}
+
+pub(crate) fn new_statx(
+    f: &std::ffi::CStr,
+    buf: &mut libc::statx,
+    mask: crate::dir::StatxMask,
+    sync: crate::dir::StatxSync,
+) -> io_uring::squeue::Entry {
+    io_uring::opcode::Statx::new(
+        io_uring::types::Fd(libc::AT_FDCWD),
+        f.as_ptr(),
+        std::ptr::from_mut(buf).cast(),
+    )
+    .flags(libc::AT_SYMLINK_NOFOLLOW | sync.get_flags())
+    .mask(mask.get_mask())
+    .build()
+}
+
+pub(crate) fn new_openat(f: &std::ffi::CStr, flags: i32) -> io_uring::squeue::Entry {
+    io_uring::opcode::OpenAt::new(io_uring::types::Fd(libc::AT_FDCWD), f.as_ptr())
+        .flags(flags | libc::O_CLOEXEC)
+        .build()
+}
+
+pub(crate) fn new_read_fixed(fd: i32, buf: &mut [u8], offset: u64) -> io_uring::squeue::Entry {
+    io_uring::opcode::ReadFixed::new(
+        io_uring::types::Fd(fd),
+        buf.as_mut_ptr(),
+        buf.len().try_into().unwrap(),
+        0,
+    )
+    .offset(offset)
+    .build()
+}
+
+pub(crate) fn new_write_fixed(fd: i32, buf: &[u8], offset: u64) -> io_uring::squeue::Entry {
+    io_uring::opcode::WriteFixed::new(
+        io_uring::types::Fd(fd),
+        buf.as_ptr(),
+        buf.len().try_into().unwrap(),
+        0,
+    )
+    .offset(offset)
+    .build()
+}
+
+pub(crate) fn new_fsync(fd: i32) -> io_uring::squeue::Entry {
+    io_uring::opcode::Fsync::new(io_uring::types::Fd(fd)).build()
+}
+
+pub(crate) fn new_close(fd: i32) -> io_uring::squeue::Entry {
+    io_uring::opcode::Close::new(io_uring::types::Fd(fd)).build()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::fd::IntoRawFd;
+
+    #[test]
+    fn test_ring() {
+        let d = std::env::temp_dir();
+        let f = d.join(format!("dirload_test_ring_{}", std::process::id()));
+        std::fs::write(&f, vec![0x41; 10000]).unwrap();
+
+        let mut buf = vec![0; 4096 * 2];
+        let mut ring = match super::Ring::new(2, &mut buf) {
+            Ok(v) => v,
+            Err(e) => {
+                // io_uring may be disabled, e.g. by seccomp
+                println!("{e}");
+                return;
+            }
+        };
+        assert_eq!(ring.get_depth(), 2);
+
+        // statx of file and directory in one batch
+        let (a, b) = (
+            crate::util::get_cstring(f.as_os_str()).unwrap(),
+            crate::util::get_cstring(d.as_os_str()).unwrap(),
+        );
+        use crate::dir::StatxMask::Basic;
+        use crate::dir::StatxSync::{AsStat, Force};
+        let mut stx: [libc::statx; 2] = unsafe { std::mem::zeroed() };
+        let (x, y) = stx.split_at_mut(1);
+        let resv = ring
+            .submit_all(vec![
+                super::new_statx(&a, &mut x[0], Basic, AsStat),
+                super::new_statx(&b, &mut y[0], crate::dir::StatxMask::Type, Force),
+            ])
+            .unwrap();
+        assert_eq!(resv, vec![0, 0]);
+        assert!(crate::util::get_statx_type(&stx[0]).is_reg());
+        assert_eq!(stx[0].stx_size, 10000);
+        assert!(crate::util::get_statx_type(&stx[1]).is_dir());
+
+        // submit more entries than depth
+        let resv = ring
+            .submit_all(vec![super::new_openat(&a, libc::O_RDONLY); 3])
+            .unwrap();
+        let fdv: Vec<i32> = resv
+            .iter()
+            .map(|x| i32::try_from(super::get_result(*x).unwrap()).unwrap())
+            .collect();
+
+        // read from offset into second half of fixed buffer
+        let resv = ring
+            .submit_all(vec![super::new_read_fixed(fdv[0], &mut buf[4096..], 8192)])
+            .unwrap();
+        assert_eq!(super::get_result(resv[0]).unwrap(), 10000 - 8192);
+        assert_eq!(buf[4096], 0x41);
+        assert_eq!(buf[0], 0);
+
+        let resv = ring
+            .submit_all(fdv.iter().map(|x| super::new_close(*x)).collect())
+            .unwrap();
+        assert_eq!(resv, vec![0, 0, 0]);
+
+        // errno of failed entry
+        let c = crate::util::get_cstring(d.join("dirload_test_ring_noent").as_os_str()).unwrap();
+        let resv = ring
+            .submit_all(vec![super::new_openat(&c, libc::O_RDONLY)])
+            .unwrap();
+        assert_eq!(
+            super::get_result(resv[0]).unwrap_err().kind(),
+            std::io::ErrorKind::NotFound
+        );
+
+        // write and fsync
+        let fd = std::fs::OpenOptions::new()
+            .write(true)
+            .open(&f)
+            .unwrap()
+            .into_raw_fd();
+        buf[..4].copy_from_slice(b"abcd");
+        let resv = ring
+            .submit_all(vec![super::new_write_fixed(fd, &buf[..4], 1)])
+            .unwrap();
+        assert_eq!(resv, vec![4]);
+        // entries in a batch are unordered
+        let resv = ring.submit_all(vec![super::new_fsync(fd)]).unwrap();
+        assert_eq!(resv, vec![0]);
+        let resv = ring.submit_all(vec![super::new_close(fd)]).unwrap();
+        assert_eq!(resv, vec![0]);
+        assert_eq!(&std::fs::read(&f).unwrap()[..6], b"Aabcd\x41");
+        std::fs::remove_file(&f).unwrap();
+    }
+}
@@ -1,7 +1,12 @@
 use path_clean::PathClean;
 use rand::distributions::uniform::SampleRange;
 use rand::Rng;
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::ffi::OsStringExt;
 use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+use std::path::PathBuf;
 
 macro_rules! function {
     () => {{
@@ -14,7 +19,7 @@ macro_rules! function {
 }
 pub(crate) use function;
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub(crate) enum FileType {
     Dir,
     Reg,
@@ -47,54 +52,95 @@ impl FileType {
     }
 }
 
-pub(crate) fn read_link(f: &str) -> std::io::Result<String> {
-    std::fs::read_link(f)?
-        .into_os_string()
-        .into_string()
-        .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))
+pub(crate) fn read_link(f: &Path) -> std::io::Result<PathBuf> {
+    std::fs::read_link(f)
+}
+
+pub(crate) const MAX_XATTR_SIZE: usize = 64 * 1024; // XATTR_SIZE_MAX
+
+pub(crate) fn get_cstring(s: &std::ffi::OsStr) -> std::io::Result<std::ffi::CString> {
+    match std::ffi::CString::new(s.as_bytes()) {
+        Ok(v) => Ok(v),
+        Err(_) => Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+    }
+}
+
+// extended attributes of entry itself, not symlink target
+pub(crate) fn list_xattr(f: &Path) -> std::io::Result<Vec<OsString>> {
+    let c = get_cstring(f.as_os_str())?;
+    loop {
+        let n = unsafe { libc::llistxattr(c.as_ptr(), std::ptr::null_mut(), 0) };
+        if n == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let mut b = vec![0u8; n.try_into().unwrap()];
+        let n = unsafe { libc::llistxattr(c.as_ptr(), b.as_mut_ptr().cast(), b.len()) };
+        if n == -1 {
+            let e = std::io::Error::last_os_error();
+            if e.raw_os_error() == Some(libc::ERANGE) {
+                continue; // grown in between
+            }
+            return Err(e);
+        }
+        b.truncate(n.try_into().unwrap());
+        return Ok(b
+            .split(|x| *x == 0)
+            .filter(|x| !x.is_empty())
+            .map(|x| std::ffi::OsStr::from_bytes(x).to_os_string())
+            .collect());
+    }
+}
+
+pub(crate) fn get_xattr(f: &Path, name: &std::ffi::OsStr, b: &mut [u8]) -> std::io::Result<usize> {
+    let (c, name) = (get_cstring(f.as_os_str())?, get_cstring(name)?);
+    let n = unsafe { libc::lgetxattr(c.as_ptr(), name.as_ptr(), b.as_mut_ptr().cast(), b.len()) };
+    if n == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(n.try_into().unwrap())
+}
+
+pub(crate) fn set_xattr(f: &Path, name: &std::ffi::OsStr, b: &[u8]) -> std::io::Result<()> {
+    let (c, name) = (get_cstring(f.as_os_str())?, get_cstring(name)?);
+    if unsafe { libc::lsetxattr(c.as_ptr(), name.as_ptr(), b.as_ptr().cast(), b.len(), 0) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
 }
 
 // This function
 // * does not resolve symlink
 // * works with non existent path
-pub(crate) fn get_abspath(f: &str) -> std::io::Result<String> {
-    let p = std::path::Path::new(f);
-    if p.is_absolute() {
-        p.to_path_buf()
+pub(crate) fn get_abspath(f: &Path) -> std::io::Result<PathBuf> {
+    Ok(if f.is_absolute() {
+        f.to_path_buf()
     } else {
         std::env::current_dir()?.join(f)
     }
-    .clean()
-    .into_os_string()
-    .into_string()
-    .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))
+    .clean())
 }
 
 // fails if f is "/" or equivalent
-pub(crate) fn get_dirpath(f: &str) -> std::io::Result<String> {
-    Ok(std::path::Path::new(&get_abspath(f)?)
+pub(crate) fn get_dirpath(f: &Path) -> std::io::Result<PathBuf> {
+    Ok(get_abspath(f)?
         .parent()
         .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))?
-        .to_str()
-        .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::InvalidInput))?
-        .to_string())
+        .to_path_buf())
 }
 
 // fails if f is "/" or equivalent
-pub(crate) fn get_basename(f: &str) -> std::io::Result<String> {
-    Ok(std::path::Path::new(&get_abspath(f)?)
+pub(crate) fn get_basename(f: &Path) -> std::io::Result<OsString> {
+    Ok(get_abspath(f)?
         .file_name()
         .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))?
-        .to_str()
-        .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::InvalidInput))?
-        .to_string())
+        .to_os_string())
 }
 
-pub(crate) fn is_abspath(f: &str) -> bool {
-    std::path::Path::new(f).is_absolute()
+pub(crate) fn is_abspath(f: &Path) -> bool {
+    f.is_absolute()
 }
 
-pub(crate) fn is_dir(f: &str) -> bool {
+pub(crate) fn is_dir(f: &Path) -> bool {
     if let Ok(v) = std::fs::metadata(f) {
         v.file_type().is_dir()
     } else {
@@ -103,21 +149,12 @@ pub(crate) fn is_dir(f: &str) -> bool {
 }
 
 // XXX behaves differently from filepath.Join which resolves ".." entries
-pub(crate) fn join_path(f1: &str, f2: &str) -> String {
-    std::path::Path::new(f1)
-        .join(f2)
-        .as_path()
-        .to_str()
-        .unwrap()
-        .to_string()
+pub(crate) fn join_path(f1: &Path, f2: &Path) -> PathBuf {
+    f1.join(f2)
 }
 
-pub(crate) fn get_home_path() -> String {
-    home::home_dir()
-        .unwrap()
-        .into_os_string()
-        .into_string()
-        .unwrap()
+pub(crate) fn get_home_path() -> PathBuf {
+    home::home_dir().unwrap()
 }
 
 #[allow(dead_code)]
@@ -133,20 +170,198 @@ pub(crate) fn get_path_separator() -> char {
     std::path::MAIN_SEPARATOR
 }
 
-pub(crate) fn get_raw_file_type(f: &str) -> std::io::Result<FileType> {
+pub(crate) fn get_raw_file_type(f: &Path) -> std::io::Result<FileType> {
     match std::fs::symlink_metadata(f) {
         Ok(v) => Ok(get_mode_type(v.file_type())),
         Err(e) => Err(e),
     }
 }
 
-pub(crate) fn get_file_type(f: &str) -> std::io::Result<FileType> {
+pub(crate) fn get_file_type(f: &Path) -> std::io::Result<FileType> {
     match std::fs::metadata(f) {
         Ok(v) => Ok(get_mode_type(v.file_type())),
         Err(e) => Err(e),
     }
 }
 
+// name is relative to dirfd unless absolute
+pub(crate) fn statx(
+    dirfd: std::os::fd::RawFd,
+    f: &Path,
+    flags: i32,
+    mask: u32,
+) -> std::io::Result<libc::statx> {
+    let c = get_cstring(f.as_os_str())?;
+    let mut buf: libc::statx = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statx(dirfd, c.as_ptr(), flags, mask, &mut buf) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(buf)
+}
+
+pub(crate) fn fstatat(
+    dirfd: std::os::fd::RawFd,
+    f: &Path,
+    flags: i32,
+) -> std::io::Result<FileType> {
+    let c = get_cstring(f.as_os_str())?;
+    let mut buf: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstatat(dirfd, c.as_ptr(), &mut buf, flags) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(get_st_mode_type(buf.st_mode))
+}
+
+pub(crate) fn open_at(
+    dirfd: std::os::fd::RawFd,
+    f: &Path,
+    flags: i32,
+    mode: libc::mode_t,
+) -> std::io::Result<std::fs::File> {
+    let c = get_cstring(f.as_os_str())?;
+    let fd = unsafe { libc::openat(dirfd, c.as_ptr(), flags | libc::O_CLOEXEC, mode) };
+    if fd == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(unsafe { std::os::fd::FromRawFd::from_raw_fd(fd) })
+}
+
+pub(crate) fn read_link_at(dirfd: std::os::fd::RawFd, f: &Path) -> std::io::Result<PathBuf> {
+    let c = get_cstring(f.as_os_str())?;
+    let mut b = vec![0u8; libc::PATH_MAX.try_into().unwrap()];
+    let n = unsafe { libc::readlinkat(dirfd, c.as_ptr(), b.as_mut_ptr().cast(), b.len()) };
+    if n == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    b.truncate(n.try_into().unwrap());
+    Ok(PathBuf::from(OsString::from_vec(b)))
+}
+
+pub(crate) fn mkdir_at(
+    dirfd: std::os::fd::RawFd,
+    f: &Path,
+    mode: libc::mode_t,
+) -> std::io::Result<()> {
+    let c = get_cstring(f.as_os_str())?;
+    if unsafe { libc::mkdirat(dirfd, c.as_ptr(), mode) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+pub(crate) fn symlink_at(
+    target: &Path,
+    dirfd: std::os::fd::RawFd,
+    f: &Path,
+) -> std::io::Result<()> {
+    let (t, c) = (
+        get_cstring(target.as_os_str())?,
+        get_cstring(f.as_os_str())?,
+    );
+    if unsafe { libc::symlinkat(t.as_ptr(), dirfd, c.as_ptr()) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+pub(crate) fn link_at(
+    olddirfd: std::os::fd::RawFd,
+    oldf: &Path,
+    newdirfd: std::os::fd::RawFd,
+    newf: &Path,
+) -> std::io::Result<()> {
+    let (a, b) = (
+        get_cstring(oldf.as_os_str())?,
+        get_cstring(newf.as_os_str())?,
+    );
+    if unsafe { libc::linkat(olddirfd, a.as_ptr(), newdirfd, b.as_ptr(), 0) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+pub(crate) fn unlink_at(dirfd: std::os::fd::RawFd, f: &Path, flags: i32) -> std::io::Result<()> {
+    let c = get_cstring(f.as_os_str())?;
+    if unsafe { libc::unlinkat(dirfd, c.as_ptr(), flags) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+pub(crate) fn get_page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) }
+        .try_into()
+        .unwrap()
+}
+
+// RLIMIT_MEMLOCK in bytes, none if unlimited or not enforced
+pub(crate) fn get_memlock_limit() -> Option<u64> {
+    let mut r: libc::rlimit = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut r) } == -1
+        || r.rlim_cur == libc::RLIM_INFINITY
+        || has_cap_ipc_lock()
+    {
+        return None;
+    }
+    Some(r.rlim_cur)
+}
+
+// CAP_IPC_LOCK bypasses RLIMIT_MEMLOCK
+fn has_cap_ipc_lock() -> bool {
+    const CAP_IPC_LOCK: u32 = 14;
+    std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|s| {
+            s.lines()
+                .find_map(|x| x.strip_prefix("CapEff:"))
+                .and_then(|x| u64::from_str_radix(x.trim(), 16).ok())
+        })
+        .is_some_and(|x| x & (1 << CAP_IPC_LOCK) != 0)
+}
+
+// counts in fragments and inodes, avail ones are for unprivileged users
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Statvfs {
+    pub(crate) frsize: u64,
+    pub(crate) blocks: u64,
+    pub(crate) bfree: u64,
+    pub(crate) bavail: u64,
+    pub(crate) files: u64,
+    pub(crate) ffree: u64,
+    pub(crate) favail: u64,
+}
+
+pub(crate) fn get_statvfs(f: &Path) -> std::io::Result<Statvfs> {
+    let c = get_cstring(f.as_os_str())?;
+    let mut buf: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c.as_ptr(), &mut buf) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(Statvfs {
+        frsize: buf.f_frsize,
+        blocks: buf.f_blocks,
+        bfree: buf.f_bfree,
+        bavail: buf.f_bavail,
+        files: buf.f_files,
+        ffree: buf.f_ffree,
+        favail: buf.f_favail,
+    })
+}
+
+pub(crate) fn get_statx_type(buf: &libc::statx) -> FileType {
+    get_st_mode_type(u32::from(buf.stx_mode))
+}
+
+fn get_st_mode_type(mode: u32) -> FileType {
+    match mode & libc::S_IFMT {
+        libc::S_IFDIR => FileType::Dir,
+        libc::S_IFREG => FileType::Reg,
+        libc::S_IFLNK => FileType::Symlink,
+        libc::S_IFBLK | libc::S_IFCHR => FileType::Device,
+        _ => FileType::Unsupported,
+    }
+}
+
 fn get_mode_type(t: std::fs::FileType) -> FileType {
     if t.is_dir() {
         FileType::Dir
@@ -162,29 +377,35 @@ fn get_mode_type(t: std::fs::FileType) -> FileType {
 }
 
 // do not resolve symlink in this implementation
-pub(crate) fn path_exists_or_error(f: &str) -> std::io::Result<std::fs::Metadata> {
+pub(crate) fn path_exists_or_error(f: &Path) -> std::io::Result<std::fs::Metadata> {
     std::fs::symlink_metadata(f)
 }
 
 // not usable as this resolves symlink
 #[allow(dead_code)]
-pub(crate) fn path_exists(f: &str) -> bool {
-    std::path::Path::new(f).exists()
+pub(crate) fn path_exists(f: &Path) -> bool {
+    f.exists()
 }
 
-pub(crate) fn is_dot_path(f: &str) -> bool {
+pub(crate) fn is_dot_path(f: &Path) -> bool {
     match get_basename(f) {
-        Ok(v) => v.starts_with('.') || f.contains("/."),
+        Ok(v) => {
+            v.as_bytes().starts_with(b".")
+                || f.as_os_str().as_bytes().windows(2).any(|x| x == b"/.")
+        }
         Err(_) => false,
     }
 }
 
-pub(crate) fn is_dir_writable(f: &str) -> std::io::Result<bool> {
+pub(crate) fn is_dir_writable(f: &Path) -> std::io::Result<bool> {
     if !get_raw_file_type(f)?.is_dir() {
         return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
     }
 
-    let x = join_path(f, &format!("dirload_write_test_{}", get_time_string()));
+    let x = join_path(
+        f,
+        Path::new(&format!("dirload_write_test_{}", get_time_string())),
+    );
     match std::fs::create_dir(&x) {
         Ok(()) => {
             match std::fs::remove_dir(&x) {
@@ -196,17 +417,17 @@ pub(crate) fn is_dir_writable(f: &str) -> std::io::Result<bool> {
     }
 }
 
-pub(crate) fn remove_dup_string(input: &[String]) -> Vec<&str> {
+pub(crate) fn remove_dup_path(input: &[PathBuf]) -> Vec<&Path> {
     let mut l = vec![];
     for a in input {
         let mut exists = false;
         for b in &l {
-            if a.as_str() == *b {
+            if a.as_path() == *b {
                 exists = true;
             }
         }
         if !exists {
-            l.push(a.as_str());
+            l.push(a.as_path());
         }
     }
     l
@@ -226,16 +447,13 @@ where
     rand::thread_rng().gen_range(range)
 }
 
-pub(crate) fn parse_walkdir_entry(entry: &walkdir::DirEntry) -> std::io::Result<&str> {
-    entry
-        .path()
-        .to_str()
-        .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::InvalidInput))
+pub(crate) fn shuffle<T>(l: &mut [T]) {
+    rand::seq::SliceRandom::shuffle(l, &mut rand::thread_rng());
 }
 
 pub(crate) struct Timer {
     time_begin: std::time::SystemTime,
-    duration: u64,
+    duration: std::time::Duration,
     frequency: u64,
     counter: u64,
 }
@@ -244,7 +462,7 @@ impl Default for Timer {
     fn default() -> Timer {
         Timer {
             time_begin: std::time::SystemTime::now(),
-            duration: 0,
+            duration: std::time::Duration::ZERO,
             frequency: 0,
             counter: 0,
         }
@@ -252,7 +470,7 @@ impl Default for Timer {
 }
 
 impl Timer {
-    pub(crate) fn new(duration: u64, frequency: u64) -> Self {
+    pub(crate) fn new(duration: std::time::Duration, frequency: u64) -> Self {
         Self {
             duration,
             frequency,
@@ -261,12 +479,12 @@ impl Timer {
     }
 
     pub(crate) fn elapsed(&mut self) -> bool {
-        if self.duration == 0 {
+        if self.duration.is_zero() {
             return false; // consider 0 as unused
         }
         self.counter += 1;
-        if self.frequency == 0 || self.counter % self.frequency == 0 {
-            self.time_begin.elapsed().unwrap().as_secs() >= self.duration
+        if self.frequency == 0 || self.counter.is_multiple_of(self.frequency) {
+            self.time_begin.elapsed().unwrap() >= self.duration
         } else {
             false
         }
@@ -295,8 +513,8 @@ mod tests {
             ("/does/not/../NOT/exist", "/does/NOT/exist"),
         ];
         for x in &path_list {
-            match super::get_abspath(x.0) {
-                Ok(v) => assert_eq!(v, x.1),
+            match super::get_abspath(std::path::Path::new(x.0)) {
+                Ok(v) => assert_eq!(v, std::path::Path::new(x.1)),
                 Err(e) => panic!("{e} {x:?}"),
             }
         }
@@ -313,8 +531,8 @@ mod tests {
             ("/does/not/../NOT/exist", "/does/NOT"),
         ];
         for x in &path_list {
-            match super::get_dirpath(x.0) {
-                Ok(v) => assert_eq!(v, x.1),
+            match super::get_dirpath(std::path::Path::new(x.0)) {
+                Ok(v) => assert_eq!(v, std::path::Path::new(x.1)),
                 Err(e) => panic!("{e} {x:?}"),
             }
         }
@@ -331,7 +549,7 @@ mod tests {
             ("/does/not/../NOT/exist", "exist"),
         ];
         for x in &path_list {
-            match super::get_basename(x.0) {
+            match super::get_basename(std::path::Path::new(x.0)) {
                 Ok(v) => assert_eq!(v, x.1),
                 Err(e) => panic!("{e} {x:?}"),
             }
@@ -355,7 +573,7 @@ mod tests {
             ("does/not/exist", false),
         ];
         for x in &path_list {
-            assert_eq!(super::is_abspath(x.0), x.1, "{x:?}");
+            assert_eq!(super::is_abspath(std::path::Path::new(x.0)), x.1, "{x:?}");
         }
     }
 
@@ -373,7 +591,7 @@ mod tests {
     fn test_get_raw_file_type() {
         let dir_list = [".", "..", "/", "/dev"];
         for f in &dir_list {
-            match super::get_raw_file_type(f) {
+            match super::get_raw_file_type(std::path::Path::new(f)) {
                 Ok(v) => match v {
                     super::FileType::Dir => (),
                     x => panic!("{x:?}"),
@@ -383,7 +601,7 @@ mod tests {
         }
         let invalid_list = ["", "516e7cb4-6ecf-11d6-8ff8-00022d09712b"];
         for f in &invalid_list {
-            if let Ok(v) = super::get_raw_file_type(f) {
+            if let Ok(v) = super::get_raw_file_type(std::path::Path::new(f)) {
                 panic!("{v:?}");
             }
         }
@@ -393,7 +611,7 @@ mod tests {
     fn test_get_file_type() {
         let dir_list = [".", "..", "/", "/dev"];
         for f in &dir_list {
-            match super::get_file_type(f) {
+            match super::get_file_type(std::path::Path::new(f)) {
                 Ok(v) => match v {
                     super::FileType::Dir => (),
                     x => panic!("{x:?}"),
@@ -403,7 +621,7 @@ mod tests {
         }
         let invalid_list = ["", "516e7cb4-6ecf-11d6-8ff8-00022d09712b"];
         for f in &invalid_list {
-            if let Ok(v) = super::get_file_type(f) {
+            if let Ok(v) = super::get_file_type(std::path::Path::new(f)) {
                 panic!("{v:?}");
             }
         }
@@ -422,13 +640,16 @@ mod tests {
     fn test_path_exists_or_error() {
         let dir_list = [".", "..", "/", "/dev"];
         for f in &dir_list {
-            if let Err(e) = super::path_exists_or_error(f) {
+            if let Err(e) = super::path_exists_or_error(std::path::Path::new(f)) {
                 panic!("{e}");
             }
         }
         let invalid_list = ["", "516e7cb4-6ecf-11d6-8ff8-00022d09712b"];
         for f in &invalid_list {
-            assert!(super::path_exists_or_error(f).is_err(), "{f}");
+            assert!(
+                super::path_exists_or_error(std::path::Path::new(f)).is_err(),
+                "{f}"
+            );
         }
     }
 
@@ -436,14 +657,36 @@ mod tests {
     fn test_path_exists() {
         let dir_list = [".", "..", "/", "/dev"];
         for f in &dir_list {
-            assert!(super::path_exists(f), "{f}");
+            assert!(super::path_exists(std::path::Path::new(f)), "{f}");
         }
         let invalid_list = ["", "516e7cb4-6ecf-11d6-8ff8-00022d09712b"];
         for f in &invalid_list {
-            assert!(!super::path_exists(f), "{f}");
+            assert!(!super::path_exists(std::path::Path::new(f)), "{f}");
         }
     }
 
+    #[test]
+    fn test_xattr() {
+        let f = std::env::temp_dir().join(format!("dirload_test_xattr_{}", std::process::id()));
+        std::fs::File::create(&f).unwrap();
+        let name = std::ffi::OsStr::new("user.dirload.test");
+        // user namespace may be unsupported, e.g. tmpfs of older kernels
+        if let Err(e) = super::set_xattr(&f, name, b"abc") {
+            println!("{e}");
+            std::fs::remove_file(&f).unwrap();
+            return;
+        }
+        assert!(super::list_xattr(&f)
+            .unwrap()
+            .contains(&name.to_os_string()));
+        let mut b = [0; 16];
+        assert_eq!(super::get_xattr(&f, name, &mut b).unwrap(), 3);
+        assert_eq!(&b[..3], b"abc");
+        assert!(super::get_xattr(&f, std::ffi::OsStr::new("user.dirload.noent"), &mut b).is_err());
+        std::fs::remove_file(&f).unwrap();
+        assert!(super::list_xattr(&f).is_err());
+    }
+
     #[test]
     fn test_is_dot_path() {
         // XXX commented out paths behave differently vs dirload
@@ -465,7 +708,7 @@ mod tests {
             "/path/to/..git/.xxx",
         ];
         for (i, f) in dot_list.iter().enumerate() {
-            assert!(super::is_dot_path(f), "{i} {f}");
+            assert!(super::is_dot_path(std::path::Path::new(f)), "{i} {f}");
         }
 
         let non_dot_list = [
@@ -481,10 +724,28 @@ mod tests {
             "/path/to/git./x.xxx.",
         ];
         for (i, f) in non_dot_list.iter().enumerate() {
-            assert!(!super::is_dot_path(f), "{i} {f}");
+            assert!(!super::is_dot_path(std::path::Path::new(f)), "{i} {f}");
         }
     }
 
+    #[test]
+    fn test_non_utf8_path() {
+        use std::os::unix::ffi::OsStrExt;
+        let f = std::path::Path::new(std::ffi::OsStr::from_bytes(b"/path/to/\xff.x"));
+        match super::get_basename(f) {
+            Ok(v) => assert_eq!(v.as_bytes(), b"\xff.x"),
+            Err(e) => panic!("{e}"),
+        }
+        match super::get_dirpath(f) {
+            Ok(v) => assert_eq!(v, std::path::Path::new("/path/to")),
+            Err(e) => panic!("{e}"),
+        }
+        assert!(!super::is_dot_path(f));
+
+        let f = std::path::Path::new(std::ffi::OsStr::from_bytes(b"/path/to/.\xff/x"));
+        assert!(super::is_dot_path(f));
+    }
+
     #[test]
     fn test_is_dir_writable() {
         if !super::is_linux() {
@@ -493,7 +754,7 @@ mod tests {
 
         let writable_list = ["/tmp"];
         for (i, f) in writable_list.iter().enumerate() {
-            match super::is_dir_writable(f) {
+            match super::is_dir_writable(std::path::Path::new(f)) {
                 Ok(v) => {
                     assert!(v, "{i} {v}");
                 }
@@ -503,7 +764,7 @@ mod tests {
 
         let unwritable_list = ["/proc"];
         for (i, f) in unwritable_list.iter().enumerate() {
-            match super::is_dir_writable(f) {
+            match super::is_dir_writable(std::path::Path::new(f)) {
                 Ok(v) => {
                     assert!(!v, "{i} {v}");
                 }
@@ -513,14 +774,14 @@ mod tests {
 
         let invalid_list = ["/proc/vmstat", "516e7cb4-6ecf-11d6-8ff8-00022d09712b"];
         for (i, f) in invalid_list.iter().enumerate() {
-            if let Ok(v) = super::is_dir_writable(f) {
+            if let Ok(v) = super::is_dir_writable(std::path::Path::new(f)) {
                 assert!(!v, "{i} {v}");
             }
         }
     }
 
     #[test]
-    fn test_remove_dup_string() {
+    fn test_remove_dup_path() {
         let uniq_ll = [
             vec![String::new()],
             vec!["/path/to/xxx".to_string()],
@@ -549,15 +810,16 @@ mod tests {
             ],
         ];
         for l in &uniq_ll {
-            let x = super::remove_dup_string(l.as_slice());
+            let l: Vec<std::path::PathBuf> = l.iter().map(std::path::PathBuf::from).collect();
+            let x = super::remove_dup_path(l.as_slice());
             for (i, a) in x.iter().enumerate() {
                 for (j, b) in x.iter().enumerate() {
-                    assert!(!(i != j && a == b), "{l:?}: {i} {a} vs {j} {b}");
+                    assert!(!(i != j && a == b), "{l:?}: {i} {a:?} vs {j} {b:?}");
                 }
             }
             assert_eq!(l.len(), x.len(), "{:?}: {} != {}", l, l.len(), x.len());
             for i in 0..x.len() {
-                assert_eq!(x[i], l[i].as_str(), "{:?}: {} {} != {}", l, i, x[i], l[i]);
+                assert_eq!(x[i], l[i].as_path(), "{:?}: {}", l, i);
             }
         }
 
@@ -600,21 +862,22 @@ mod tests {
             ],
         ];
         for l in &dup_ll {
-            let x = super::remove_dup_string(l.as_slice());
+            let l: Vec<std::path::PathBuf> = l.iter().map(std::path::PathBuf::from).collect();
+            let x = super::remove_dup_path(l.as_slice());
             for (i, a) in x.iter().enumerate() {
                 for (j, b) in x.iter().enumerate() {
-                    assert!(!(i != j && a == b), "{l:?}: {i} {a} vs {j} {b}");
+                    assert!(!(i != j && a == b), "{l:?}: {i} {a:?} vs {j} {b:?}");
                 }
             }
             assert!(l.len() > x.len(), "{:?}: {} <= {}", l, l.len(), x.len());
             let mut v = vec![];
             for s in &x {
-                v.push((*s).to_string());
+                v.push(s.to_path_buf());
             }
-            let xx = super::remove_dup_string(&v);
+            let xx = super::remove_dup_path(&v);
             assert_eq!(x.len(), xx.len(), "{:?}: {} != {}", l, x.len(), xx.len());
             for i in 0..x.len() {
-                assert_eq!(x[i], xx[i], "{:?}: {} {} != {}", l, i, x[i], xx[i]);
+                assert_eq!(x[i], xx[i], "{:?}: {}", l, i);
             }
         }
     }
@@ -633,7 +896,7 @@ mod tests {
 
     #[test]
     fn test_timer1() {
-        let mut timer = super::Timer::new(0, 0); // unused
+        let mut timer = super::Timer::new(std::time::Duration::from_secs(0), 0); // unused
         assert!(!timer.elapsed());
         std::thread::sleep(std::time::Duration::from_millis(10));
         assert!(!timer.elapsed());
@@ -641,7 +904,7 @@ mod tests {
         timer.reset();
         assert!(!timer.elapsed());
 
-        let mut timer = super::Timer::new(1, 0);
+        let mut timer = super::Timer::new(std::time::Duration::from_secs(1), 0);
         assert!(!timer.elapsed());
         std::thread::sleep(std::time::Duration::from_secs(1));
         assert!(timer.elapsed());
@@ -649,7 +912,7 @@ mod tests {
         timer.reset();
         assert!(!timer.elapsed());
 
-        let mut timer = super::Timer::new(2, 0);
+        let mut timer = super::Timer::new(std::time::Duration::from_secs(2), 0);
         assert!(!timer.elapsed());
         std::thread::sleep(std::time::Duration::from_secs(1));
         assert!(!timer.elapsed());
@@ -660,16 +923,28 @@ mod tests {
 
     #[test]
     fn test_timer2() {
-        let mut timer = super::Timer::new(0, 1000); // unused
+        let mut timer = super::Timer::new(std::time::Duration::from_secs(0), 1000); // unused
         std::thread::sleep(std::time::Duration::from_secs(1));
         assert!(!timer.elapsed());
         std::thread::sleep(std::time::Duration::from_secs(1));
         assert!(!timer.elapsed());
 
-        let mut timer = super::Timer::new(1, 1000);
+        let mut timer = super::Timer::new(std::time::Duration::from_secs(1), 1000);
         std::thread::sleep(std::time::Duration::from_secs(1));
         assert!(!timer.elapsed());
         std::thread::sleep(std::time::Duration::from_secs(1));
         assert!(!timer.elapsed());
     }
+
+    #[test]
+    fn test_timer3() {
+        let mut timer = super::Timer::new(std::time::Duration::from_millis(200), 0);
+        assert!(!timer.elapsed());
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert!(!timer.elapsed());
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert!(timer.elapsed());
+        timer.reset();
+        assert!(!timer.elapsed());
+    }
 }
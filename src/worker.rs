@@ -1,11 +1,24 @@
+use std::io::BufRead;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::capacity;
 use crate::dir;
+use crate::fault;
 use crate::flist;
 use crate::is_interrupted;
+use crate::is_paused;
+use crate::metrics;
+use crate::pace;
+use crate::phase;
+use crate::sched;
 use crate::stat;
+use crate::tui;
 use crate::util;
 use crate::Opt;
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
 pub(crate) enum PathIter {
     Walk,
     Ordered,
@@ -42,6 +55,8 @@ pub(crate) struct Thread {
     num_complete: usize,
     num_interrupted: usize,
     num_error: usize,
+    arrival: Option<pace::Arrival>, // open-loop if specified
+    enospc: bool,                   // last write ran out of space
     txc: Option<std::sync::mpsc::Sender<(usize, stat::ThreadStat)>>,
 }
 
@@ -75,6 +90,7 @@ impl Thread {
     fn send_stat(&mut self) -> std::result::Result<(), Box<dyn std::error::Error>> {
         if let Some(txc) = &self.txc {
             self.stat.set_time_end();
+            self.stat.set_num_write_paths(self.dir.write_paths.len());
             txc.send((self.gid, self.stat.clone()))?;
         }
         Ok(())
@@ -82,6 +98,7 @@ impl Thread {
 
     fn send_done(&mut self) -> std::result::Result<(), Box<dyn std::error::Error>> {
         if let Some(txc) = &self.txc {
+            self.stat.set_num_write_paths(self.dir.write_paths.len());
             self.stat.done = true;
             txc.send((self.gid, self.stat.clone()))?;
         }
@@ -89,7 +106,7 @@ impl Thread {
     }
 }
 
-fn setup_flist_impl(input: &[String], opt: &Opt) -> std::io::Result<Vec<Vec<String>>> {
+fn setup_flist_impl(input: &[PathBuf], opt: &Opt) -> std::io::Result<Vec<Vec<PathBuf>>> {
     let mut fls = vec![];
     for _ in 0..input.len() {
         fls.push(vec![]);
@@ -99,17 +116,17 @@ fn setup_flist_impl(input: &[String], opt: &Opt) -> std::io::Result<Vec<Vec<Stri
         // load flist from flist file
         assert!(!opt.path_iter.is_walk());
         println!("flist_file {}", opt.flist_file);
-        for s in &flist::load_flist_file(&opt.flist_file)? {
+        for s in &flist::load_flist_file(Path::new(&opt.flist_file))? {
             let mut found = false;
             for (i, f) in input.iter().enumerate() {
                 if s.starts_with(f) {
-                    fls[i].push(s.to_string());
+                    fls[i].push(s.clone());
                     found = true;
                     // no break, s can exist in multiple fls[i]
                 }
             }
             if !found {
-                println!("{s} has no prefix in {input:?}");
+                println!("{} has no prefix in {input:?}", s.display());
                 return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
             }
         }
@@ -117,7 +134,7 @@ fn setup_flist_impl(input: &[String], opt: &Opt) -> std::io::Result<Vec<Vec<Stri
         // initialize flist by walking input directories
         for (i, f) in input.iter().enumerate() {
             let l = flist::init_flist(f, opt.ignore_dot)?;
-            println!("{} files scanned from {}", l.len(), f);
+            println!("{} files scanned from {}", l.len(), f.display());
             fls[i] = l;
         }
     }
@@ -125,20 +142,20 @@ fn setup_flist_impl(input: &[String], opt: &Opt) -> std::io::Result<Vec<Vec<Stri
     // don't allow empty flist as it results in spinning loop
     for (i, fl) in fls.iter().enumerate() {
         if !fl.is_empty() {
-            println!("flist {} {}", input[i], fl.len());
+            println!("flist {} {}", input[i].display(), fl.len());
         } else {
-            println!("empty flist {}", input[i]);
+            println!("empty flist {}", input[i].display());
             return Err(std::io::Error::from(std::io::ErrorKind::InvalidInput));
         }
     }
     Ok(fls)
 }
 
-fn setup_flist(input: &[String], opt: &Opt) -> std::io::Result<Vec<Vec<String>>> {
+fn setup_flist(input: &[PathBuf], opt: &Opt) -> std::io::Result<Vec<Vec<PathBuf>>> {
     // setup flist for non-walk iterations
     if opt.path_iter.is_walk() {
         for f in input {
-            println!("Walk {f}");
+            println!("Walk {}", f.display());
         }
         Ok(vec![])
     } else {
@@ -174,16 +191,53 @@ fn monitor_handler(
     opt: &Opt,
 ) -> std::result::Result<(), Box<dyn std::error::Error>> {
     assert!(rxc.is_some());
-    assert!(opt.monitor_int_second > 0);
+    assert!(opt.monitor_int_second > 0.0);
     let mut tsv = vec![stat::ThreadStat::new(); n];
-    let mut timer = util::Timer::new(opt.monitor_int_second, 0);
+    let interval = std::time::Duration::from_secs_f64(opt.monitor_int_second);
+    let mut timer = util::Timer::new(interval, 0);
     let mut ready = false;
     let rxc = rxc.unwrap();
+    let mut is = stat::IntervalStat::new(n);
+    let time_begin = std::time::Instant::now();
+    metrics::init(n);
+
+    // time-series of interval stats if specified, appended per set,
+    // header is written once unless already
+    let mut log = None;
+    if !opt.monitor_log.is_empty() {
+        let f = std::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&opt.monitor_log)?;
+        let empty = f.metadata()?.len() == 0;
+        let mut f = std::io::BufWriter::new(f);
+        if empty {
+            writeln!(f, "{}", stat::format_interval_header(n))?;
+            f.flush()?;
+        }
+        log = Some(f);
+    }
+
+    // poll keys more often than stats
+    let (mut tui, d) = if opt.tui {
+        (Some(tui::Tui::new()), std::time::Duration::from_millis(100))
+    } else {
+        (
+            None,
+            std::cmp::min(interval, std::time::Duration::from_secs(1)),
+        )
+    };
+    if let Some(tui) = &tui {
+        tui.draw(&tsv, &is, opt);
+    }
 
     loop {
         let mut timeout = false;
-        match rxc.recv_timeout(std::time::Duration::from_secs(1)) {
-            Ok((gid, ts)) => tsv[gid] = ts,
+        match rxc.recv_timeout(d) {
+            Ok((gid, ts)) => {
+                metrics::update(gid, &ts);
+                tsv[gid] = ts;
+            }
             Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
                 return Err(Box::new(std::io::Error::from(
                     std::io::ErrorKind::NotConnected,
@@ -212,14 +266,38 @@ fn monitor_handler(
             }
         }
         if timer.elapsed() {
-            let label = stringify!([monitor]);
-            if ready {
-                log::info!("{label} ready");
-                stat::print_stat(&tsv);
+            is.update(&tsv);
+            if let Some(f) = &mut log {
+                let s = stat::format_interval_record(
+                    opt.set_index,
+                    std::time::SystemTime::now(),
+                    time_begin.elapsed(),
+                    &is,
+                );
+                writeln!(f, "{s}")?;
+                f.flush()?;
+            }
+            if let Some(tui) = &mut tui {
+                tui.update(&is);
+                tui.draw(&tsv, &is, opt);
             } else {
-                log::info!("{label} not ready");
+                let label = stringify!([monitor]);
+                if ready {
+                    log::info!("{label} ready");
+                    stat::print_stat(&tsv);
+                    println!(
+                        "interval {:.0} ops/sec {:.2} MiB/sec",
+                        is.total.0, is.total.1
+                    );
+                } else {
+                    log::info!("{label} not ready");
+                }
             }
             timer.reset();
+        } else if let Some(tui) = &mut tui {
+            if tui.handle_keys(&tsv) {
+                tui.draw(&tsv, &is, opt);
+            }
         }
         // only allow existing via message by default
         if opt.debug && is_interrupted() {
@@ -229,17 +307,226 @@ fn monitor_handler(
     Ok(())
 }
 
+// workers block in between entries while paused, true if paused
+fn wait_paused() -> bool {
+    let mut paused = false;
+    while is_paused() && !is_interrupted() {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        paused = true;
+    }
+    paused
+}
+
+// sleeps in short steps to respond to interrupt, counted as idle time
+fn idle(thr: &mut Thread, d: std::time::Duration) {
+    let t = std::time::Instant::now();
+    while !is_interrupted() {
+        let elapsed = t.elapsed();
+        if elapsed >= d {
+            break;
+        }
+        std::thread::sleep(std::cmp::min(
+            d - elapsed,
+            std::time::Duration::from_millis(100),
+        ));
+    }
+    thr.stat.add_idle_time(t.elapsed());
+}
+
+// intended start of next operation, which open-loop thread waits for
+fn arrive(thr: &mut Thread, opt: &Opt) -> std::time::Instant {
+    let now = std::time::Instant::now();
+    let Some(x) = &mut thr.arrival else {
+        return now;
+    };
+    let (t, backlog, dropped) = x.get_next(now, opt.max_backlog);
+    thr.stat.set_backlog(backlog);
+    thr.stat.add_num_dropped(dropped);
+    if t > now {
+        idle(thr, t - now);
+    }
+    t
+}
+
+// think time after each operation, then wait for on period of burst if any,
+// queued entries are read before idle
+fn pace(thr: &mut Thread, dir: &dir::Dir, opt: &Opt) -> std::io::Result<()> {
+    if let Some(x) = &opt.think_time {
+        dir::flush_read_entries(thr, dir, opt)?;
+        idle(thr, x.get_duration());
+    }
+    if let Some(x) = &opt.burst {
+        let d = x.get_idle(thr.stat.time_elapsed());
+        if !d.is_zero() {
+            dir::flush_read_entries(thr, dir, opt)?;
+            idle(thr, d);
+        }
+    }
+    Ok(())
+}
+
+// factor is relative to size of input filesystem, which is shared by
+// writers of the same input
+fn get_age_bytes(gid: usize, input: &[PathBuf], opt: &Opt) -> std::io::Result<usize> {
+    if opt.age_bytes > 0 {
+        return Ok(opt.age_bytes);
+    } else if opt.age_factor <= 0.0 {
+        return Ok(0);
+    }
+    let i = gid % input.len();
+    let n = (opt.num_reader..opt.num_reader + opt.num_writer)
+        .filter(|x| x % input.len() == i)
+        .count();
+    let x = util::get_statvfs(&input[i])?;
+    let b = (x.blocks * x.frsize) as f64 * opt.age_factor / n as f64;
+    Ok(std::cmp::max(b as usize, 1))
+}
+
+fn is_capacity_guarded(opt: &Opt) -> bool {
+    opt.min_free_space > 0.0 || opt.min_free_inodes > 0.0 || opt.fill_utilization > 0.0
+}
+
+// writers delete one of own write paths per write once filled, and stop or
+// wait while input filesystem is below free thresholds, false to stop
+fn guard_capacity(thr: &mut Thread, dir: &dir::Dir, opt: &Opt) -> std::io::Result<bool> {
+    if !is_capacity_guarded(opt) {
+        return Ok(true);
+    }
+    let f = flist::decode_flist_path(thr.stat.get_input_path())?;
+    loop {
+        let x = dir.capacity.get_usage(&f)?;
+        let enospc = std::mem::take(&mut thr.enospc);
+        let low = enospc
+            || 100.0 - x.space < opt.min_free_space
+            || 100.0 - x.inodes < opt.min_free_inodes;
+        if opt.fill_utilization > 0.0 && (low || x.space >= opt.fill_utilization) {
+            // one per write, as space may be freed lazily after unlink,
+            // ones which free no space such as directories don't count
+            let mut unlinked = false;
+            for _ in 0..capacity::MAX_FILL_UNLINK {
+                let Some((bytes, inodes)) = dir::unlink_random_write_path(thr, dir)? else {
+                    break;
+                };
+                dir.capacity.sub(&f, bytes, inodes);
+                unlinked = true;
+                if bytes > 0 {
+                    break;
+                }
+            }
+            if unlinked {
+                return Ok(true);
+            }
+        }
+        if !low {
+            return Ok(true);
+        }
+        if opt.capacity_action.is_stop() {
+            log::info!("#{} stop on {:?}", thr.gid, x);
+            return Ok(false);
+        }
+        if is_interrupted()
+            || (opt.time_second > 0 && thr.stat.time_elapsed().as_secs() > opt.time_second)
+        {
+            return Ok(true);
+        }
+        idle(thr, capacity::CHECK_INTERVAL);
+    }
+}
+
+// out of space is handled by capacity guard if enabled
+fn write_entry(f: &Path, thr: &mut Thread, dir: &dir::Dir, opt: &Opt) -> std::io::Result<()> {
+    let write: fn(&Path, &mut Thread, &dir::Dir, &Opt) -> std::io::Result<()> =
+        if dir::is_aging(opt) {
+            dir::age_entry
+        } else {
+            dir::write_entry
+        };
+    if !is_capacity_guarded(opt) {
+        return write(f, thr, dir, opt);
+    }
+    let (bytes, inodes) = (thr.stat.get_num_write_bytes(), thr.dir.write_paths.len());
+    let res = write(f, thr, dir, opt);
+    let d = flist::decode_flist_path(thr.stat.get_input_path())?;
+    match res {
+        Err(e) if e.raw_os_error() == Some(libc::ENOSPC) => {
+            dir.capacity.invalidate(&d);
+            thr.enospc = true;
+            Ok(())
+        }
+        x => {
+            // usage until next check
+            dir.capacity.add(
+                &d,
+                thr.stat.get_num_write_bytes() - bytes,
+                thr.dir.write_paths.len().saturating_sub(inodes),
+            );
+            x
+        }
+    }
+}
+
+// blocks while thread is idle in current phase,
+// none if interrupted, timed out or schedule ended
+fn wait_phase<'a>(
+    thr: &mut Thread,
+    dir: &dir::Dir,
+    schedule: Option<&'a phase::Schedule>,
+    opt: &'a Opt,
+) -> std::result::Result<Option<&'a Opt>, Box<dyn std::error::Error>> {
+    let Some(schedule) = schedule else {
+        return Ok(Some(opt));
+    };
+    let mut sent = false;
+    loop {
+        let i = schedule.get_index();
+        // queued entries belong to previous phase
+        if let Some(j) = thr.stat.get_phase_index() {
+            if i != Some(j) {
+                dir::flush_read_entries(thr, dir, schedule.get_opt(j))?;
+            }
+        }
+        thr.stat.set_phase(i);
+        let Some(i) = i else {
+            return Ok(None);
+        };
+        if schedule.is_active(i, thr.gid, opt) {
+            // open-loop restarts rather than catching up with idle period
+            if sent {
+                if let Some(x) = &mut thr.arrival {
+                    x.reset();
+                }
+            }
+            return Ok(Some(schedule.get_opt(i)));
+        }
+        if is_interrupted()
+            || (opt.time_second > 0 && thr.stat.time_elapsed().as_secs() > opt.time_second)
+        {
+            return Ok(None);
+        }
+        // stats other than idle time don't change while idle
+        if !sent {
+            thr.send_stat()?;
+            sent = true;
+        }
+        idle(thr, std::time::Duration::from_millis(100));
+    }
+}
+
 fn worker_handler(
-    input_path: &str,
-    fl: Option<&Vec<String>>,
+    input_path: &Path,
+    fl: Option<&Vec<PathBuf>>,
     thr: &mut Thread,
     dir: &dir::Dir,
+    schedule: Option<&phase::Schedule>,
     opt: &Opt,
 ) -> std::result::Result<(), Box<dyn std::error::Error>> {
-    assert!(thr.txc.is_some() || opt.monitor_int_second == 0);
-    assert!(thr.txc.is_none() || opt.monitor_int_second > 0);
+    assert!(thr.txc.is_some() || opt.monitor_int_second <= 0.0);
+    assert!(thr.txc.is_none() || opt.monitor_int_second > 0.0);
     let d = opt.time_second;
-    let mut timer = util::Timer::new(opt.monitor_int_second, 100);
+    let mut timer = util::Timer::new(
+        std::time::Duration::from_secs_f64(opt.monitor_int_second),
+        100,
+    );
     let mut repeat = 0;
 
     // assert thr
@@ -247,7 +534,19 @@ fn worker_handler(
     assert_eq!(thr.num_interrupted, 0);
     assert_eq!(thr.num_error, 0);
 
-    thr.stat.set_input_path(input_path);
+    thr.stat
+        .set_input_path(&flist::encode_flist_path(input_path));
+    if opt.think_time.is_some() || opt.burst.is_some() {
+        thr.stat.set_paced();
+    }
+    if opt.arrival_rate > 0.0 {
+        thr.arrival = Some(pace::Arrival::new(opt.arrival_rate));
+        thr.stat.set_open_loop();
+    }
+    dir.engine.init_thread(thr)?;
+
+    // set CPU affinity, nice and I/O priority if specified
+    sched::set_thread_sched(thr.is_reader(opt), opt)?;
 
     // send initial stats
     thr.send_stat()?;
@@ -259,12 +558,39 @@ fn worker_handler(
                 .into_iter()
                 .filter_map(std::result::Result::ok)
             {
-                let f = util::parse_walkdir_entry(&entry)?;
+                let f = entry.path();
                 assert!(f.starts_with(input_path));
+                // options of current phase if any
+                let o = match wait_phase(thr, dir, schedule, opt)? {
+                    Some(v) => v,
+                    None if is_interrupted() => {
+                        thr.num_interrupted += 1;
+                        break;
+                    }
+                    None => {
+                        debug_print_complete(thr, repeat, opt);
+                        thr.num_complete += 1;
+                        break;
+                    }
+                };
+                if thr.is_writer(opt) && !guard_capacity(thr, dir, o)? {
+                    debug_print_complete(thr, repeat, opt);
+                    thr.num_complete += 1;
+                    break;
+                }
+                // latency of open-loop includes wait since intended start
+                let t = arrive(thr, o);
                 if thr.is_reader(opt) {
-                    dir::read_entry(f, thr, opt)?;
+                    dir::read_entry(f, t, thr, dir, o)?;
                 } else {
-                    dir::write_entry(f, thr, dir, opt)?;
+                    write_entry(f, thr, dir, o)?;
+                    thr.stat.add_latency(t.elapsed());
+                }
+                pace(thr, dir, o)?;
+                if wait_paused() {
+                    if let Some(x) = &mut thr.arrival {
+                        x.reset();
+                    }
                 }
                 if is_interrupted() {
                     thr.num_interrupted += 1;
@@ -295,10 +621,37 @@ fn worker_handler(
                 };
                 let f = &fl[idx];
                 assert!(f.starts_with(input_path));
+                // options of current phase if any
+                let o = match wait_phase(thr, dir, schedule, opt)? {
+                    Some(v) => v,
+                    None if is_interrupted() => {
+                        thr.num_interrupted += 1;
+                        break;
+                    }
+                    None => {
+                        debug_print_complete(thr, repeat, opt);
+                        thr.num_complete += 1;
+                        break;
+                    }
+                };
+                if thr.is_writer(opt) && !guard_capacity(thr, dir, o)? {
+                    debug_print_complete(thr, repeat, opt);
+                    thr.num_complete += 1;
+                    break;
+                }
+                // latency of open-loop includes wait since intended start
+                let t = arrive(thr, o);
                 if thr.is_reader(opt) {
-                    dir::read_entry(f, thr, opt)?;
+                    dir::read_entry(f, t, thr, dir, o)?;
                 } else {
-                    dir::write_entry(f, thr, dir, opt)?;
+                    write_entry(f, thr, dir, o)?;
+                    thr.stat.add_latency(t.elapsed());
+                }
+                pace(thr, dir, o)?;
+                if wait_paused() {
+                    if let Some(x) = &mut thr.arrival {
+                        x.reset();
+                    }
                 }
                 if is_interrupted() {
                     thr.num_interrupted += 1;
@@ -315,6 +668,7 @@ fn worker_handler(
                 }
             }
         }
+        dir::flush_read_entries(thr, dir, opt)?;
         // return if interrupted or complete
         if thr.num_interrupted > 0 || thr.num_complete > 0 {
             thr.send_done()?;
@@ -345,9 +699,333 @@ fn worker_handler(
     Ok(())
 }
 
+fn spawn_worker<'scope>(
+    s: &'scope std::thread::Scope<'scope, '_>,
+    thr: &'scope mut Thread,
+    input: &'scope [PathBuf],
+    fls: &'scope [Vec<PathBuf>],
+    dir: &'scope dir::Dir,
+    schedule: Option<&'scope phase::Schedule>,
+    opt: &'scope Opt,
+) {
+    s.spawn(move || {
+        let tid = std::thread::current().id();
+        log::info!("{:?} #{} start", tid, thr.gid);
+        let input_path = &input[thr.gid % input.len()];
+        let fl = if !fls.is_empty() {
+            Some(&fls[thr.gid % fls.len()])
+        } else {
+            None
+        };
+        thr.stat.set_time_begin();
+        if let Err(e) = worker_handler(input_path, fl, thr, dir, schedule, opt) {
+            thr.num_error += 1;
+            log::info!("{:?} #{} {}", tid, thr.gid, e);
+            println!("{e}");
+            thr.stat.failed = true;
+            let _ = thr.send_done();
+        }
+        thr.stat.set_phase(None);
+        thr.stat.set_time_end();
+        thr.txc = None; // let receiver see disconnect once all workers exit
+    });
+}
+
+// messages sent from worker processes to parent, one JSON per line
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+enum ProcessMessage {
+    Stat(usize, stat::ThreadStat),
+    Result {
+        gid: usize,
+        num_complete: usize,
+        num_interrupted: usize,
+        num_error: usize,
+        stat: stat::ThreadStat,
+        write_paths: Vec<String>, // flist encoded
+    },
+}
+
+fn send_process_message(
+    writer: &std::sync::Mutex<std::io::BufWriter<std::fs::File>>,
+    m: &ProcessMessage,
+) -> std::io::Result<()> {
+    let mut writer = writer.lock().unwrap();
+    serde_json::to_writer(&mut *writer, m)?;
+    writeln!(writer)?;
+    writer.flush()
+}
+
+// runs in a child process, never returns
+fn run_process(
+    thrv: &mut [&mut Thread],
+    fd: std::os::fd::OwnedFd,
+    input: &[PathBuf],
+    fls: &[Vec<PathBuf>],
+    dir: &dir::Dir,
+    schedule: Option<&phase::Schedule>,
+    opt: &Opt,
+) -> ! {
+    let writer = std::sync::Mutex::new(std::io::BufWriter::new(std::fs::File::from(fd)));
+
+    // forward stats to parent instead of monitor
+    let use_monitor = opt.monitor_int_second > 0.0;
+    let (txc, rxc) = std::sync::mpsc::channel::<(usize, stat::ThreadStat)>();
+    for thr in thrv.iter_mut() {
+        thr.txc = if use_monitor { Some(txc.clone()) } else { None };
+    }
+    drop(txc);
+
+    std::thread::scope(|s| {
+        if use_monitor {
+            s.spawn(|| {
+                for (gid, ts) in rxc {
+                    if let Err(e) = send_process_message(&writer, &ProcessMessage::Stat(gid, ts)) {
+                        log::info!("{e}");
+                        break;
+                    }
+                }
+            });
+        }
+        for thr in thrv.iter_mut() {
+            spawn_worker(s, thr, input, fls, dir, schedule, opt);
+        }
+    });
+
+    let mut status = 0;
+    for thr in thrv.iter() {
+        let m = ProcessMessage::Result {
+            gid: thr.gid,
+            num_complete: thr.num_complete,
+            num_interrupted: thr.num_interrupted,
+            num_error: thr.num_error,
+            stat: thr.stat.clone(),
+            write_paths: thr
+                .dir
+                .write_paths
+                .iter()
+                .map(|f| flist::encode_flist_path(f))
+                .collect(),
+        };
+        if let Err(e) = send_process_message(&writer, &m) {
+            log::info!("{e}");
+            println!("{e}");
+            status = 1;
+        }
+    }
+    std::process::exit(status);
+}
+
+// interrupt and reap forked worker processes on failure
+fn abort_process(pidv: &[nix::unistd::Pid]) {
+    for pid in pidv {
+        let _ = nix::sys::signal::kill(*pid, nix::sys::signal::Signal::SIGINT);
+    }
+    for pid in pidv {
+        let _ = nix::sys::wait::waitpid(*pid, None);
+    }
+}
+
+fn dispatch_process(
+    thrv: &mut [Thread],
+    input: &[PathBuf],
+    fls: &[Vec<PathBuf>],
+    dir: &dir::Dir,
+    rxc: Option<std::sync::mpsc::Receiver<(usize, stat::ThreadStat)>>,
+    schedule: Option<&phase::Schedule>,
+    opt: &Opt,
+) -> std::io::Result<()> {
+    // threads are distributed by gid, no more processes than threads
+    let n = std::cmp::min(opt.num_process, thrv.len());
+    let mut pidv = vec![];
+    let mut readerv = vec![];
+    for i in 0..n {
+        let res = nix::unistd::pipe()
+            .and_then(|(r, w)| unsafe { nix::unistd::fork() }.map(|x| (r, w, x)));
+        let (r, w, res) = match res {
+            Ok(v) => v,
+            Err(e) => {
+                drop(readerv);
+                abort_process(&pidv);
+                return Err(e.into());
+            }
+        };
+        match res {
+            nix::unistd::ForkResult::Child => {
+                drop(r);
+                drop(readerv);
+                let mut l: Vec<&mut Thread> = thrv.iter_mut().filter(|x| x.gid % n == i).collect();
+                // don't unwind into parent's code path on panic
+                let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    run_process(&mut l, w, input, fls, dir, schedule, opt)
+                }));
+                std::process::exit(1);
+            }
+            nix::unistd::ForkResult::Parent { child } => {
+                log::info!("worker process {child} start");
+                drop(w);
+                pidv.push(child);
+                readerv.push(std::io::BufReader::new(std::fs::File::from(r)));
+            }
+        }
+    }
+    println!("Forked {n} worker processes");
+
+    // parent monitors via stats relayed from children,
+    // receiver sees disconnect once all readers exit
+    let use_monitor = rxc.is_some();
+    let txc = thrv.first().and_then(|x| x.txc.clone());
+    for thr in thrv.iter_mut() {
+        thr.txc = None;
+    }
+    let nthr = thrv.len();
+    let resultv = std::thread::scope(|s| {
+        if use_monitor {
+            s.spawn(|| {
+                let tid = std::thread::current().id();
+                log::info!("{:?} monitor start", tid);
+                if let Err(e) = monitor_handler(nthr, rxc, opt) {
+                    log::info!("{tid:?} {e}");
+                    println!("{e}");
+                }
+            });
+        }
+        let mut hv = vec![];
+        for (i, reader) in readerv.into_iter().enumerate() {
+            let txc = txc.clone();
+            let owned: Vec<(usize, stat::ThreadStat)> = thrv
+                .iter()
+                .filter(|x| x.gid % n == i)
+                .map(|x| (x.gid, x.stat.clone()))
+                .collect();
+            hv.push(s.spawn(move || {
+                let mut l = vec![];
+                for line in reader.lines() {
+                    let m = match line.map(|x| serde_json::from_str::<ProcessMessage>(&x)) {
+                        Ok(Ok(v)) => v,
+                        Ok(Err(e)) => {
+                            log::info!("{e}");
+                            break;
+                        }
+                        Err(e) => {
+                            log::info!("{e}");
+                            break;
+                        }
+                    };
+                    match m {
+                        ProcessMessage::Stat(gid, ts) => {
+                            if let Some(txc) = &txc {
+                                let _ = txc.send((gid, ts));
+                            }
+                        }
+                        m => l.push(m),
+                    }
+                }
+                // child exited without results, e.g. killed
+                if let Some(txc) = &txc {
+                    let donev: Vec<usize> = l
+                        .iter()
+                        .filter_map(|m| match m {
+                            ProcessMessage::Result { gid, .. } => Some(*gid),
+                            ProcessMessage::Stat(..) => None,
+                        })
+                        .collect();
+                    for (gid, mut ts) in owned {
+                        if donev.contains(&gid) {
+                            continue;
+                        }
+                        if !ts.is_ready() {
+                            ts.set_input_path(&flist::encode_flist_path(&input[gid % input.len()]));
+                        }
+                        ts.failed = true;
+                        ts.done = true;
+                        let _ = txc.send((gid, ts));
+                    }
+                }
+                l
+            }));
+        }
+        drop(txc);
+
+        // forward SIGINT in case it was only sent to parent,
+        // and pause or resume children as parent does
+        let mut forwarded = false;
+        let mut paused = false;
+        while !hv.iter().all(std::thread::ScopedJoinHandle::is_finished) {
+            if is_paused() != paused && !forwarded {
+                paused = is_paused();
+                let sig = if paused {
+                    nix::sys::signal::Signal::SIGSTOP
+                } else {
+                    nix::sys::signal::Signal::SIGCONT
+                };
+                for pid in &pidv {
+                    let _ = nix::sys::signal::kill(*pid, sig);
+                }
+            }
+            if is_interrupted() && !forwarded {
+                for pid in &pidv {
+                    if paused {
+                        let _ = nix::sys::signal::kill(*pid, nix::sys::signal::Signal::SIGCONT);
+                    }
+                    let _ = nix::sys::signal::kill(*pid, nix::sys::signal::Signal::SIGINT);
+                }
+                forwarded = true;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+        hv.into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect::<Vec<ProcessMessage>>()
+    });
+    for pid in &pidv {
+        nix::sys::wait::waitpid(*pid, None)?;
+    }
+
+    // reflect results, threads without results are considered failed
+    for thr in thrv.iter_mut() {
+        thr.num_error = 1;
+    }
+    for m in resultv {
+        if let ProcessMessage::Result {
+            gid,
+            num_complete,
+            num_interrupted,
+            num_error,
+            stat,
+            write_paths,
+        } = m
+        {
+            let thr = &mut thrv[gid];
+            thr.num_complete = num_complete;
+            thr.num_interrupted = num_interrupted;
+            thr.num_error = num_error;
+            thr.stat = stat;
+            for s in &write_paths {
+                thr.dir.write_paths.push(flist::decode_flist_path(s)?);
+            }
+        }
+    }
+    for thr in thrv.iter_mut() {
+        if !thr.stat.is_ready() {
+            thr.stat
+                .set_input_path(&flist::encode_flist_path(&input[thr.gid % input.len()]));
+        }
+    }
+    Ok(())
+}
+
 pub(crate) fn dispatch_worker(
-    input: &[String],
+    input: &[PathBuf],
     opt: &Opt,
+) -> std::io::Result<(usize, usize, usize, usize, Vec<stat::ThreadStat>)> {
+    dispatch_worker_barrier(input, opt, || Ok(()))
+}
+
+// barrier is called after setup and right before starting workers
+pub(crate) fn dispatch_worker_barrier(
+    input: &[PathBuf],
+    opt: &Opt,
+    barrier: impl FnOnce() -> std::io::Result<()>,
 ) -> std::io::Result<(usize, usize, usize, usize, Vec<stat::ThreadStat>)> {
     for f in input {
         assert!(util::is_abspath(f));
@@ -360,8 +1038,21 @@ pub(crate) fn dispatch_worker(
         return Ok((0, 0, 0, 0, vec![]));
     }
 
+    // aged files of all sets are recorded to one flist file
+    if !opt.age_flist_file.is_empty() && opt.set_index == 0 {
+        flist::remove_flist_file(Path::new(&opt.age_flist_file), opt.force)?;
+    }
+
     // initialize dir
-    let dir = dir::Dir::new(opt.random_write_data);
+    let mut dir = dir::Dir::new(opt);
+    log::info!(
+        "{} engine, {:?}",
+        dir.engine.get_name(),
+        dir.engine.get_stat()
+    );
+    if opt.num_writer > 0 {
+        dir.init_write_paths_dirs(input, opt)?;
+    }
 
     // initialize thread structure
     let mut thrv = vec![];
@@ -369,7 +1060,12 @@ pub(crate) fn dispatch_worker(
         if i < opt.num_reader {
             thrv.push(Thread::newread(i, opt.read_buffer_size));
         } else {
-            thrv.push(Thread::newwrite(i, opt.write_buffer_size));
+            let mut thr = Thread::newwrite(i, opt.write_buffer_size);
+            thr.dir.age_bytes = get_age_bytes(i, input, opt)?;
+            if thr.dir.age_bytes > 0 {
+                log::info!("#{} age {} bytes", i, thr.dir.age_bytes);
+            }
+            thrv.push(thr);
         }
     }
     assert_eq!(thrv.len(), opt.num_reader + opt.num_writer);
@@ -383,7 +1079,7 @@ pub(crate) fn dispatch_worker(
     }
 
     // create channels for workers to send stats to monitor
-    let use_monitor = opt.monitor_int_second > 0;
+    let use_monitor = opt.monitor_int_second > 0.0;
     let n = thrv.len();
     let mut rxc = None;
     if use_monitor {
@@ -394,38 +1090,32 @@ pub(crate) fn dispatch_worker(
         rxc = Some(l.1);
     }
 
-    // spawn + join threads
-    std::thread::scope(|s| {
-        if use_monitor {
-            s.spawn(|| {
-                let tid = std::thread::current().id();
-                log::info!("{:?} monitor start", tid);
-                if let Err(e) = monitor_handler(n, rxc, opt) {
-                    log::info!("{tid:?} {e}");
-                    println!("{e}");
-                }
-            });
-        }
-        for thr in &mut thrv {
-            s.spawn(|| {
-                let tid = std::thread::current().id();
-                log::info!("{:?} #{} start", tid, thr.gid);
-                let input_path = &input[thr.gid % input.len()];
-                let fl = if !fls.is_empty() {
-                    Some(&fls[thr.gid % fls.len()])
-                } else {
-                    None
-                };
-                thr.stat.set_time_begin();
-                if let Err(e) = worker_handler(input_path, fl, thr, &dir, opt) {
-                    thr.num_error += 1;
-                    log::info!("{:?} #{} {}", tid, thr.gid, e);
-                    println!("{e}");
-                }
-                thr.stat.set_time_end();
-            });
-        }
-    });
+    // wait for others to be ready if any
+    barrier()?;
+
+    // phases start with workers, forked processes share the clock
+    let schedule = phase::Schedule::new(opt);
+
+    // spawn + join threads, or fork worker processes which run them
+    if opt.num_process > 1 {
+        dispatch_process(&mut thrv, input, &fls, &dir, rxc, schedule.as_ref(), opt)?;
+    } else {
+        std::thread::scope(|s| {
+            if use_monitor {
+                s.spawn(|| {
+                    let tid = std::thread::current().id();
+                    log::info!("{:?} monitor start", tid);
+                    if let Err(e) = monitor_handler(n, rxc, opt) {
+                        log::info!("{tid:?} {e}");
+                        println!("{e}");
+                    }
+                });
+            }
+            for thr in &mut thrv {
+                spawn_worker(s, thr, input, &fls, &dir, schedule.as_ref(), opt);
+            }
+        });
+    }
 
     // collect result
     let mut num_complete = 0;
@@ -447,13 +1137,32 @@ pub(crate) fn dispatch_worker(
         tdv.push(&thr.dir);
         tsv.push(thr.stat.clone());
     }
-    Ok((
-        num_complete,
-        num_interrupted,
-        num_error,
-        dir::cleanup_write_paths(tdv.as_slice(), opt)?,
-        tsv,
-    ))
+    let num_remain = dir::cleanup_write_paths(tdv.as_slice(), &dir, opt)?;
+
+    // aged tree for subsequent read benchmarks, in the same form as flist,
+    // appended per set as write paths of previous sets are left behind
+    if !opt.age_flist_file.is_empty() {
+        let mut fl = vec![];
+        for x in &tdv {
+            for f in &x.write_paths {
+                if let Ok(t) = dir.engine.lstat(f) {
+                    if t.is_reg() || t.is_symlink() {
+                        fl.push(f.clone());
+                    }
+                }
+            }
+        }
+        fl.sort();
+        flist::append_flist_file(&fl, Path::new(&opt.age_flist_file))?;
+        println!("{} files recorded to {}", fl.len(), opt.age_flist_file);
+    }
+    if let Some(x) = dir.engine.get_plan() {
+        x.print();
+    }
+    if let Some(x) = dir.engine.get_faults() {
+        fault::print_faults(&x);
+    }
+    Ok((num_complete, num_interrupted, num_error, num_remain, tsv))
 }
 
 #[cfg(test)]
@@ -465,4 +1174,180 @@ mod tests {
         assert!(super::PathIter::Reverse.is_reverse());
         assert!(super::PathIter::Random.is_random());
     }
+
+    #[test]
+    fn test_process_message() {
+        let mut ts = crate::stat::ThreadStat::newwrite();
+        ts.set_input_path("/path/to/dir");
+        ts.set_time_begin();
+        ts.inc_num_write();
+        ts.set_time_end();
+        let m = super::ProcessMessage::Result {
+            gid: 1,
+            num_complete: 1,
+            num_interrupted: 0,
+            num_error: 0,
+            stat: ts,
+            write_paths: vec!["/path/to/dir/\\xff".to_string()],
+        };
+        let s = match serde_json::to_string(&m) {
+            Ok(v) => v,
+            Err(e) => panic!("{e}"),
+        };
+        assert!(!s.contains('\n'), "{s}");
+        match serde_json::from_str::<super::ProcessMessage>(&s) {
+            Ok(v) => assert_eq!(format!("{v:?}"), format!("{m:?}")),
+            Err(e) => panic!("{e} {s}"),
+        }
+    }
+
+    #[test]
+    fn test_guard_capacity() {
+        let d = std::env::temp_dir().join(format!("dirload_test_capacity_{}", std::process::id()));
+        std::fs::create_dir(&d).unwrap();
+        let mut opt = crate::Opt::default();
+        let dir = crate::dir::Dir::new(&opt);
+        let mut thr = super::Thread::newwrite(0, 0);
+        thr.stat.set_input_path(d.to_str().unwrap());
+        for i in 0..2 {
+            let f = d.join(i.to_string());
+            std::fs::write(&f, b"x").unwrap();
+            thr.dir.write_paths.push(f);
+        }
+        assert!(super::guard_capacity(&mut thr, &dir, &opt).unwrap()); // disabled
+
+        // filled, so delete a write path per write
+        opt.fill_utilization = 0.001;
+        assert!(super::guard_capacity(&mut thr, &dir, &opt).unwrap());
+        assert_eq!(thr.dir.write_paths.len(), 1);
+        assert_eq!(std::fs::read_dir(&d).unwrap().count(), 1);
+        assert!(super::guard_capacity(&mut thr, &dir, &opt).unwrap());
+        assert!(thr.dir.write_paths.is_empty());
+        assert_eq!(std::fs::read_dir(&d).unwrap().count(), 0);
+
+        // ones which free no space don't count
+        for i in 0..2 {
+            let f = d.join(i.to_string());
+            std::fs::create_dir(&f).unwrap();
+            thr.dir.write_paths.push(f);
+        }
+        assert!(super::guard_capacity(&mut thr, &dir, &opt).unwrap());
+        assert!(thr.dir.write_paths.is_empty());
+
+        // nothing to delete, stop below free threshold
+        assert!(super::guard_capacity(&mut thr, &dir, &opt).unwrap());
+        opt.min_free_space = 100.0;
+        assert!(!super::guard_capacity(&mut thr, &dir, &opt).unwrap());
+        opt.min_free_space = 0.0;
+        opt.fill_utilization = 0.0;
+        opt.min_free_inodes = 0.001;
+        thr.enospc = true;
+        assert!(!super::guard_capacity(&mut thr, &dir, &opt).unwrap());
+        assert!(!thr.enospc);
+        std::fs::remove_dir(&d).unwrap();
+    }
+
+    #[test]
+    fn test_get_age_bytes() {
+        let d = std::env::temp_dir();
+        let input = vec![d.clone(); 2];
+        let mut opt = crate::Opt {
+            num_reader: 1,
+            num_writer: 3,
+            ..Default::default()
+        };
+        assert_eq!(super::get_age_bytes(1, &input, &opt).unwrap(), 0);
+        opt.age_bytes = 100;
+        assert_eq!(super::get_age_bytes(1, &input, &opt).unwrap(), 100);
+
+        // gid 1 and 3 share input 1
+        opt.age_bytes = 0;
+        opt.age_factor = 1.0;
+        let x = crate::util::get_statvfs(&d).unwrap();
+        let n = usize::try_from(x.blocks * x.frsize).unwrap();
+        let b = super::get_age_bytes(1, &input, &opt).unwrap();
+        assert!(b.abs_diff(n / 2) <= 1, "{b} {n}");
+        let b = super::get_age_bytes(2, &input, &opt).unwrap();
+        assert!(b.abs_diff(n) <= 1, "{b} {n}");
+    }
+
+    #[test]
+    fn test_dispatch_process_killed() {
+        let d = std::env::temp_dir().join(format!("dirload_test_process_{}", std::process::id()));
+        std::fs::create_dir(&d).unwrap();
+        std::fs::write(d.join("a"), b"xxx").unwrap();
+        let input = vec![d.clone()];
+        let opt = crate::Opt {
+            num_reader: 2,
+            num_process: 2,
+            time_second: 3,
+            monitor_int_second: 1.0,
+            ..Default::default()
+        };
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(super::dispatch_worker(&input, &opt).map(|x| x.2));
+        });
+
+        // kill one worker process once forked
+        let mut pidv = vec![];
+        for _ in 0..100 {
+            for x in std::fs::read_dir("/proc/self/task").unwrap() {
+                let f = x.unwrap().path().join("children");
+                for s in std::fs::read_to_string(f).unwrap().split_whitespace() {
+                    pidv.push(s.parse::<i32>().unwrap());
+                }
+            }
+            if !pidv.is_empty() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        assert!(!pidv.is_empty());
+        nix::sys::signal::kill(
+            nix::unistd::Pid::from_raw(pidv[0]),
+            nix::sys::signal::Signal::SIGKILL,
+        )
+        .unwrap();
+
+        let num_error = rx
+            .recv_timeout(std::time::Duration::from_secs(30))
+            .unwrap()
+            .unwrap();
+        assert_eq!(num_error, 1);
+        std::fs::remove_dir_all(&d).unwrap();
+    }
+
+    #[test]
+    fn test_dispatch_worker_non_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+        let mut b = std::env::temp_dir().as_os_str().as_bytes().to_vec();
+        b.extend_from_slice(b"/dirload_test_\xff_");
+        b.extend_from_slice(std::process::id().to_string().as_bytes());
+        let d = std::path::PathBuf::from(std::ffi::OsStr::from_bytes(&b));
+        assert!(d.to_str().is_none());
+        std::fs::create_dir(&d).unwrap();
+        std::fs::write(d.join("a"), b"xxx").unwrap();
+
+        let input = vec![d.clone()];
+        let opt = crate::Opt {
+            num_reader: 1,
+            num_writer: 1,
+            num_repeat: 1,
+            num_write_paths: 1,
+            ..Default::default()
+        };
+        let (num_complete, _, num_error, num_remain, tsv) =
+            super::dispatch_worker(&input, &opt).unwrap();
+        assert_eq!(num_complete, 2);
+        assert_eq!(num_error, 0);
+        assert_eq!(num_remain, 0);
+        for ts in &tsv {
+            assert_eq!(
+                crate::flist::decode_flist_path(ts.get_input_path()).unwrap(),
+                d
+            );
+        }
+        std::fs::remove_dir_all(&d).unwrap();
+    }
 }